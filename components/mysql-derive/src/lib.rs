@@ -0,0 +1,163 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Derive macros for [opensrv-mysql](https://docs.rs/opensrv-mysql).
+//!
+//! `#[derive(MysqlRow)]` implements `opensrv_mysql::MysqlRow` for a struct with named fields.
+//! Every field becomes a column named after the field, whose type and flags are inferred from
+//! the field's type through `opensrv_mysql::MysqlColumnType`. Fields accept these attributes:
+//!
+//!  - `#[mysql(rename = "name")]` uses `name` as the column name.
+//!  - `#[mysql(column_type = "LONG")]` overrides the column type; the `MYSQL_TYPE_` prefix may
+//!    be omitted.
+//!  - `#[mysql(unsigned)]` adds the `UNSIGNED_FLAG` to the column.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+#[proc_macro_derive(MysqlRow, attributes(mysql))]
+pub fn derive_mysql_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct FieldAttrs {
+    rename: Option<String>,
+    column_type: Option<Ident>,
+    unsigned: bool,
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs {
+        rename: None,
+        column_type: None,
+        unsigned: false,
+    };
+
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("mysql")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "expected #[mysql(...)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    match nv.lit {
+                        Lit::Str(s) => attrs.rename = Some(s.value()),
+                        lit => return Err(syn::Error::new(lit.span(), "expected a string")),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("column_type") => {
+                    match nv.lit {
+                        Lit::Str(s) => {
+                            let name = s.value();
+                            let name = if name.starts_with("MYSQL_TYPE_") {
+                                name
+                            } else {
+                                format!("MYSQL_TYPE_{}", name)
+                            };
+                            attrs.column_type = Some(Ident::new(&name, s.span()));
+                        }
+                        lit => return Err(syn::Error::new(lit.span(), "expected a string")),
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("unsigned") => {
+                    attrs.unsigned = true;
+                }
+                nested => return Err(syn::Error::new(
+                    nested.span(),
+                    "unknown mysql attribute, expected one of `rename`, `column_type`, `unsigned`",
+                )),
+            }
+        }
+    }
+
+    Ok(attrs)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "MysqlRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "MysqlRow can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut columns = Vec::with_capacity(fields.len());
+    let mut writes = Vec::with_capacity(fields.len());
+    for field in fields {
+        let attrs = parse_field_attrs(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+
+        let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+        let coltype = match attrs.column_type {
+            Some(ct) => quote!(::opensrv_mysql::ColumnType::#ct),
+            None => quote!(<#ty as ::opensrv_mysql::MysqlColumnType>::column_type()),
+        };
+        let unsigned = if attrs.unsigned {
+            quote!(| ::opensrv_mysql::ColumnFlags::UNSIGNED_FLAG)
+        } else {
+            quote!()
+        };
+
+        columns.push(quote! {
+            ::opensrv_mysql::Column {
+                table: ::std::string::String::new(),
+                column: ::std::string::String::from(#name),
+                coltype: #coltype,
+                colflags: <#ty as ::opensrv_mysql::MysqlColumnType>::column_flags() #unsigned,
+                ..::std::default::Default::default()
+            }
+        });
+        writes.push(quote! {
+            w.write_col(&self.#ident)?;
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::opensrv_mysql::MysqlRow for #name #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<::opensrv_mysql::Column> {
+                ::std::vec![#(#columns),*]
+            }
+
+            fn write_to<W: ::opensrv_mysql::__private::AsyncWrite + ::std::marker::Unpin>(
+                &self,
+                w: &mut ::opensrv_mysql::RowWriter<'_, W>,
+            ) -> ::std::io::Result<()> {
+                #(#writes)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
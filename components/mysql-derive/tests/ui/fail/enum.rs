@@ -0,0 +1,8 @@
+use opensrv_mysql_derive::MysqlRow;
+
+#[derive(MysqlRow)]
+enum Row {
+    A,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use opensrv_mysql_derive::MysqlRow;
+
+#[derive(MysqlRow)]
+struct Row(i32, String);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use opensrv_mysql_derive::MysqlRow;
+
+#[derive(MysqlRow)]
+struct Row {
+    #[mysql(nullable)]
+    a: i32,
+}
+
+fn main() {}
@@ -0,0 +1,40 @@
+use opensrv_mysql::{ColumnFlags, ColumnType, MysqlRow};
+use opensrv_mysql_derive::MysqlRow;
+
+#[derive(MysqlRow)]
+struct User<'a> {
+    id: u64,
+    #[mysql(rename = "user_name")]
+    name: &'a str,
+    #[mysql(column_type = "LONG", unsigned)]
+    age: u8,
+    #[mysql(column_type = "MYSQL_TYPE_MEDIUM_BLOB")]
+    avatar: Option<Vec<u8>>,
+}
+
+fn main() {
+    let cols = User::columns();
+    assert_eq!(cols.len(), 4);
+
+    assert_eq!(cols[0].column, "id");
+    assert_eq!(cols[0].coltype, ColumnType::MYSQL_TYPE_LONGLONG);
+    assert_eq!(
+        cols[0].colflags,
+        ColumnFlags::NOT_NULL_FLAG | ColumnFlags::UNSIGNED_FLAG
+    );
+
+    assert_eq!(cols[1].column, "user_name");
+    assert_eq!(cols[1].coltype, ColumnType::MYSQL_TYPE_VAR_STRING);
+    assert_eq!(cols[1].colflags, ColumnFlags::NOT_NULL_FLAG);
+
+    assert_eq!(cols[2].column, "age");
+    assert_eq!(cols[2].coltype, ColumnType::MYSQL_TYPE_LONG);
+    assert_eq!(
+        cols[2].colflags,
+        ColumnFlags::NOT_NULL_FLAG | ColumnFlags::UNSIGNED_FLAG
+    );
+
+    assert_eq!(cols[3].column, "avatar");
+    assert_eq!(cols[3].coltype, ColumnType::MYSQL_TYPE_MEDIUM_BLOB);
+    assert_eq!(cols[3].colflags, ColumnFlags::empty());
+}
@@ -0,0 +1,119 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Times sending wide binary rows made of NOT NULL columns against the same rows made of
+//! nullable columns.
+//!
+//! ```console
+//! $ cargo bench --bench not_null_rows
+//! ```
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use mysql_async::prelude::*;
+use opensrv_mysql::*;
+use tokio::io::AsyncWrite;
+use tokio::net::TcpListener;
+
+const COLUMNS: usize = 64;
+const ROWS: i64 = 10_000;
+const RUNS: u32 = 20;
+
+struct Backend {
+    columns: Vec<Column>,
+}
+
+#[async_trait::async_trait]
+impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for Backend {
+    type Error = io::Error;
+
+    async fn on_prepare<'a>(
+        &'a mut self,
+        _: &'a str,
+        info: StatementMetaWriter<'a, W>,
+    ) -> io::Result<()> {
+        info.reply(1, &[], &self.columns).await
+    }
+
+    async fn on_execute<'a>(
+        &'a mut self,
+        _: u32,
+        _: ParamParser<'a>,
+        results: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        let mut w = results.start(&self.columns).await?;
+        for r in 0..ROWS {
+            w.write_row((0..COLUMNS as i64).map(|c| r + c)).await?;
+        }
+        w.finish().await
+    }
+
+    async fn on_close(&mut self, _: u32) {}
+
+    async fn on_query<'a>(
+        &'a mut self,
+        _: &'a str,
+        results: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        results.completed(OkResponse::default()).await
+    }
+}
+
+async fn time_rows(colflags: ColumnFlags) -> Duration {
+    let columns: Vec<_> = (0..COLUMNS)
+        .map(|i| Column {
+            colflags,
+            ..Column::new(&format!("c{}", i), ColumnType::MYSQL_TYPE_LONGLONG)
+        })
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (r, w) = stream.into_split();
+        AsyncMysqlIntermediary::run_on(Backend { columns }, r, w).await
+    });
+
+    let mut db = mysql_async::Conn::new(
+        mysql_async::OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(port),
+    )
+    .await
+    .unwrap();
+    let stmt = db.prep("SELECT * FROM wide").await.unwrap();
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let rows: Vec<mysql_async::Row> = db.exec(&stmt, ()).await.unwrap();
+        elapsed += start.elapsed();
+        assert_eq!(rows.len(), ROWS as usize);
+    }
+    elapsed / RUNS
+}
+
+#[tokio::main]
+async fn main() {
+    for (name, colflags) in [
+        ("NOT NULL", ColumnFlags::NOT_NULL_FLAG),
+        ("nullable", ColumnFlags::empty()),
+    ] {
+        let elapsed = time_rows(colflags).await;
+        println!(
+            "{:>8}: {} rows of {} columns in {:?}",
+            name, ROWS, COLUMNS, elapsed
+        );
+    }
+}
@@ -65,6 +65,7 @@ impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for Backend {
             column: "abc".to_string(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
             colflags: myc::constants::ColumnFlags::UNSIGNED_FLAG,
+            ..Default::default()
         }];
 
         let mut w = results.start(cols).await?;
@@ -102,7 +103,7 @@ impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for Backend {
         "mysql_native_password"
     }
 
-    fn salt(&self) -> [u8; 20] {
+    fn salt(&self) -> io::Result<[u8; 20]> {
         let bs = ";X,po_k}>o6^Wz!/kM}N".as_bytes();
         let mut scramble: [u8; 20] = [0; 20];
         for i in 0..20 {
@@ -111,7 +112,7 @@ impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for Backend {
                 scramble[i] += 1;
             }
         }
-        scramble
+        Ok(scramble)
     }
 }
 
@@ -0,0 +1,251 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, BufWriter};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+
+use crate::{
+    AsyncMysqlIntermediary, AsyncMysqlShim, IntermediaryOptions, QueryObserver, SystemVariables,
+};
+
+/// Collects the configuration of an [`AsyncMysqlIntermediary`](struct.AsyncMysqlIntermediary.html),
+/// and then serves connections with it.
+///
+/// Every option defaults to the value of
+/// [`IntermediaryOptions::default`](struct.IntermediaryOptions.html), so that
+///
+/// ```rust,ignore
+/// MysqlIntermediaryBuilder::new().run_on_tcp(shim, stream).await
+/// ```
+///
+/// serves a connection just like [`AsyncMysqlIntermediary::run_on`](struct.AsyncMysqlIntermediary.html#method.run_on)
+/// would.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MysqlIntermediaryBuilder {
+    opts: IntermediaryOptions,
+}
+
+impl From<IntermediaryOptions> for MysqlIntermediaryBuilder {
+    fn from(opts: IntermediaryOptions) -> Self {
+        MysqlIntermediaryBuilder { opts }
+    }
+}
+
+impl MysqlIntermediaryBuilder {
+    /// Create a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The options collected so far.
+    pub fn options(&self) -> &IntermediaryOptions {
+        &self.opts
+    }
+
+    /// See [`IntermediaryOptions::process_use_statement_on_query`](struct.IntermediaryOptions.html#structfield.process_use_statement_on_query).
+    pub fn process_use_statement_on_query(mut self, enabled: bool) -> Self {
+        self.opts.process_use_statement_on_query = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::acknowledge_set_statements`](struct.IntermediaryOptions.html#structfield.acknowledge_set_statements).
+    pub fn acknowledge_set_statements(mut self, enabled: bool) -> Self {
+        self.opts.acknowledge_set_statements = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::query_attributes`](struct.IntermediaryOptions.html#structfield.query_attributes).
+    pub fn query_attributes(mut self, enabled: bool) -> Self {
+        self.opts.query_attributes = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::query_observer`](struct.IntermediaryOptions.html#structfield.query_observer).
+    pub fn query_observer(mut self, observer: QueryObserver) -> Self {
+        self.opts.query_observer = Some(observer);
+        self
+    }
+
+    /// See [`IntermediaryOptions::secure_transport`](struct.IntermediaryOptions.html#structfield.secure_transport).
+    pub fn secure_transport(mut self, secure: bool) -> Self {
+        self.opts.secure_transport = secure;
+        self
+    }
+
+    /// See [`IntermediaryOptions::max_result_set_size`](struct.IntermediaryOptions.html#structfield.max_result_set_size).
+    pub fn max_result_set_size(mut self, max_size: usize) -> Self {
+        self.opts.max_result_set_size = Some(max_size);
+        self
+    }
+
+    /// See [`IntermediaryOptions::statement_cache_size`](struct.IntermediaryOptions.html#structfield.statement_cache_size).
+    pub fn statement_cache_size(mut self, size: usize) -> Self {
+        self.opts.statement_cache_size = size;
+        self
+    }
+
+    /// See [`IntermediaryOptions::max_prepared_statements`](struct.IntermediaryOptions.html#structfield.max_prepared_statements).
+    pub fn max_prepared_statements(mut self, max: usize) -> Self {
+        self.opts.max_prepared_statements = Some(max);
+        self
+    }
+
+    /// See [`IntermediaryOptions::strict_types`](struct.IntermediaryOptions.html#structfield.strict_types).
+    pub fn strict_types(mut self, strict: bool) -> Self {
+        self.opts.strict_types = strict;
+        self
+    }
+
+    /// See [`IntermediaryOptions::system_variables`](struct.IntermediaryOptions.html#structfield.system_variables).
+    pub fn system_variables(mut self, variables: SystemVariables) -> Self {
+        self.opts.system_variables = Some(variables);
+        self
+    }
+
+    /// See [`IntermediaryOptions::session_track`](struct.IntermediaryOptions.html#structfield.session_track).
+    pub fn session_track(mut self, enabled: bool) -> Self {
+        self.opts.session_track = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::tcp_nodelay`](struct.IntermediaryOptions.html#structfield.tcp_nodelay).
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.opts.tcp_nodelay = nodelay;
+        self
+    }
+
+    /// See [`IntermediaryOptions::tcp_keepalive`](struct.IntermediaryOptions.html#structfield.tcp_keepalive).
+    pub fn tcp_keepalive(mut self, time: Duration) -> Self {
+        self.opts.tcp_keepalive = Some(time);
+        self
+    }
+
+    /// See [`IntermediaryOptions::metrics`](struct.IntermediaryOptions.html#structfield.metrics).
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.opts.metrics = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::defer_flush`](struct.IntermediaryOptions.html#structfield.defer_flush).
+    pub fn defer_flush(mut self, defer: bool) -> Self {
+        self.opts.defer_flush = defer;
+        self
+    }
+
+    /// See [`IntermediaryOptions::query_timeout`](struct.IntermediaryOptions.html#structfield.query_timeout).
+    pub fn query_timeout(mut self, timeout: Duration) -> Self {
+        self.opts.query_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`IntermediaryOptions::mariadb`](struct.IntermediaryOptions.html#structfield.mariadb).
+    pub fn mariadb(mut self, enabled: bool) -> Self {
+        self.opts.mariadb = enabled;
+        self
+    }
+
+    /// See [`IntermediaryOptions::bulk_execute`](struct.IntermediaryOptions.html#structfield.bulk_execute).
+    pub fn bulk_execute(mut self, enabled: bool) -> Self {
+        self.opts.bulk_execute = enabled;
+        self
+    }
+
+    /// Serve a client over two one-way channels with these options, see
+    /// [`AsyncMysqlIntermediary::run_with_options`](struct.AsyncMysqlIntermediary.html#method.run_with_options).
+    pub async fn run_on<B, S, W>(
+        &self,
+        shim: B,
+        stream: S,
+        output_stream: W,
+    ) -> Result<(), B::Error>
+    where
+        W: AsyncWrite + Send + Unpin,
+        B: AsyncMysqlShim<W> + Send + Sync,
+        S: AsyncRead + Unpin,
+    {
+        AsyncMysqlIntermediary::run_with_options(shim, stream, output_stream, &self.opts).await
+    }
+
+    /// Serve a client connected from `peer_addr` over two one-way channels with these options, see
+    /// [`AsyncMysqlIntermediary::run_with_peer_addr`](struct.AsyncMysqlIntermediary.html#method.run_with_peer_addr).
+    pub async fn run_with_peer_addr<B, S, W>(
+        &self,
+        shim: B,
+        stream: S,
+        output_stream: W,
+        peer_addr: Option<SocketAddr>,
+    ) -> Result<(), B::Error>
+    where
+        W: AsyncWrite + Send + Unpin,
+        B: AsyncMysqlShim<W> + Send + Sync,
+        S: AsyncRead + Unpin,
+    {
+        AsyncMysqlIntermediary::run_with_peer_addr(
+            shim,
+            stream,
+            output_stream,
+            peer_addr,
+            &self.opts,
+        )
+        .await
+    }
+
+    /// Serve a client over an accepted TCP connection with these options, see
+    /// [`AsyncMysqlIntermediary::run_on_tcp`](struct.AsyncMysqlIntermediary.html#method.run_on_tcp).
+    pub async fn run_on_tcp<B>(&self, shim: B, stream: TcpStream) -> Result<(), B::Error>
+    where
+        B: AsyncMysqlShim<BufWriter<OwnedWriteHalf>> + Send + Sync,
+    {
+        AsyncMysqlIntermediary::run_on_tcp(shim, stream, &self.opts).await
+    }
+
+    /// Serve a client over two one-way channels with these options until `shutdown` completes,
+    /// see [`AsyncMysqlIntermediary::run_until`](struct.AsyncMysqlIntermediary.html#method.run_until).
+    pub async fn run_until<B, S, W, F>(
+        &self,
+        shim: B,
+        stream: S,
+        output_stream: W,
+        shutdown: F,
+    ) -> Result<(), B::Error>
+    where
+        W: AsyncWrite + Send + Unpin,
+        B: AsyncMysqlShim<W> + Send + Sync,
+        S: AsyncRead + Unpin,
+        F: Future<Output = ()>,
+    {
+        AsyncMysqlIntermediary::run_until(shim, stream, output_stream, &self.opts, shutdown).await
+    }
+
+    /// Serve a client over an accepted TCP connection with these options until `shutdown`
+    /// completes, see
+    /// [`AsyncMysqlIntermediary::run_on_tcp_until`](struct.AsyncMysqlIntermediary.html#method.run_on_tcp_until).
+    pub async fn run_on_tcp_until<B, F>(
+        &self,
+        shim: B,
+        stream: TcpStream,
+        shutdown: F,
+    ) -> Result<(), B::Error>
+    where
+        B: AsyncMysqlShim<BufWriter<OwnedWriteHalf>> + Send + Sync,
+        F: Future<Output = ()>,
+    {
+        AsyncMysqlIntermediary::run_on_tcp_until(shim, stream, &self.opts, shutdown).await
+    }
+}
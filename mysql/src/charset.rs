@@ -0,0 +1,38 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// The maximum number of bytes a single character takes in the character set of the given
+/// collation id.
+///
+/// see: https://dev.mysql.com/doc/refman/8.0/en/charset-charsets.html
+pub(crate) fn max_bytes_per_char(collation: u16) -> u32 {
+    match collation {
+        // big5, sjis, euckr, gb2312, gbk, cp932
+        1 | 84 | 13 | 88 | 19 | 85 | 24 | 86 | 28 | 87 | 95 | 96 => 2,
+        // ucs2, including ucs2_general_mysql500_ci (159)
+        35 | 90 | 128..=151 | 159 => 2,
+        // ujis, eucjpms
+        12 | 91 | 97 | 98 => 3,
+        // utf8
+        33 | 76 | 83 | 192..=215 | 223 => 3,
+        // utf8mb4
+        45 | 46 | 224..=247 | 255..=323 => 4,
+        // utf16, utf16le, utf32
+        54 | 55 | 56 | 60 | 61 | 62 | 101..=124 | 160..=183 => 4,
+        // gb18030
+        248..=250 => 4,
+        // binary, latin1 and the other single-byte character sets
+        _ => 1,
+    }
+}
@@ -14,6 +14,10 @@
 
 use crate::myc::constants::{CapabilityFlags, Command as CommandByte};
 
+// MariaDB's command to execute a prepared statement for a batch of parameter rows, which MySQL
+// does not have
+const COM_STMT_BULK_EXECUTE: u8 = 0xfa;
+
 #[derive(Debug)]
 pub struct ClientHandshake {
     #[allow(dead_code)]
@@ -21,13 +25,16 @@ pub struct ClientHandshake {
     pub(crate) capabilities: CapabilityFlags,
     #[allow(dead_code)]
     pub(crate) collation: u16,
-    #[allow(dead_code)]
     pub(crate) db: Option<Vec<u8>>,
     pub(crate) username: Vec<u8>,
     pub(crate) auth_response: Vec<u8>,
     pub(crate) auth_plugin: Vec<u8>,
 }
 
+/// Parse the handshake response of a client.
+///
+/// Every field is read with bounds checks, so truncated or garbage input from a hostile client
+/// yields an error rather than a panic; the `handshake_response` fuzz target checks this.
 #[allow(clippy::branches_sharing_code)]
 pub fn client_handshake(i: &[u8]) -> nom::IResult<&[u8], ClientHandshake> {
     // mysql handshake protocol documentation
@@ -102,10 +109,10 @@ pub fn client_handshake(i: &[u8]) -> nom::IResult<&[u8], ClientHandshake> {
 
         let (i, auth_response, db) =
             if capabilities.contains(CapabilityFlags::CLIENT_CONNECT_WITH_DB) {
-                let (i, auth_response) = nom::bytes::complete::tag(b"\0")(i)?;
+                let (i, auth_response) = nom::bytes::complete::take_until(&b"\0"[..])(i)?;
                 let (i, _) = nom::bytes::complete::tag(b"\0")(i)?;
 
-                let (i, db) = nom::bytes::complete::tag(b"\0")(i)?;
+                let (i, db) = nom::bytes::complete::take_until(&b"\0"[..])(i)?;
                 let (i, _) = nom::bytes::complete::tag(b"\0")(i)?;
 
                 (i, auth_response, Some(db))
@@ -152,6 +159,12 @@ pub enum Command<'a> {
     Init(&'a [u8]),
     Execute {
         stmt: u32,
+        flags: u8,
+        params: &'a [u8],
+    },
+    BulkExecute {
+        stmt: u32,
+        flags: u16,
         params: &'a [u8],
     },
     SendLongData {
@@ -159,15 +172,38 @@ pub enum Command<'a> {
         param: u16,
         data: &'a [u8],
     },
+    ProcessInfo,
+    ProcessKill(u32),
+    ResetConnection,
     Ping,
     Quit,
 }
 
 pub fn execute(i: &[u8]) -> nom::IResult<&[u8], Command<'_>> {
     let (i, stmt) = nom::number::complete::le_u32(i)?;
-    let (i, _flags) = nom::bytes::complete::take(1u8)(i)?;
+    let (i, flags) = nom::number::complete::le_u8(i)?;
     let (i, _iterations) = nom::number::complete::le_u32(i)?;
-    Ok((&[], Command::Execute { stmt, params: i }))
+    Ok((
+        &[],
+        Command::Execute {
+            stmt,
+            flags,
+            params: i,
+        },
+    ))
+}
+
+pub fn bulk_execute(i: &[u8]) -> nom::IResult<&[u8], Command<'_>> {
+    let (i, stmt) = nom::number::complete::le_u32(i)?;
+    let (i, flags) = nom::number::complete::le_u16(i)?;
+    Ok((
+        &[],
+        Command::BulkExecute {
+            stmt,
+            flags,
+            params: i,
+        },
+    ))
 }
 
 pub fn send_long_data(i: &[u8]) -> nom::IResult<&[u8], Command<'_>> {
@@ -205,6 +241,7 @@ pub fn parse(i: &[u8]) -> nom::IResult<&[u8], Command<'_>> {
             Command::Prepare,
         ),
         preceded(tag(&[CommandByte::COM_STMT_EXECUTE as u8]), execute),
+        preceded(tag(&[COM_STMT_BULK_EXECUTE]), bulk_execute),
         preceded(
             tag(&[CommandByte::COM_STMT_SEND_LONG_DATA as u8]),
             send_long_data,
@@ -216,6 +253,19 @@ pub fn parse(i: &[u8]) -> nom::IResult<&[u8], Command<'_>> {
             ),
             Command::Close,
         ),
+        map(tag(&[CommandByte::COM_PROCESS_INFO as u8]), |_| {
+            Command::ProcessInfo
+        }),
+        map(
+            preceded(
+                tag(&[CommandByte::COM_PROCESS_KILL as u8]),
+                nom::number::complete::le_u32,
+            ),
+            Command::ProcessKill,
+        ),
+        map(tag(&[CommandByte::COM_RESET_CONNECTION as u8]), |_| {
+            Command::ResetConnection
+        }),
         map(tag(&[CommandByte::COM_QUIT as u8]), |_| Command::Quit),
         map(tag(&[CommandByte::COM_PING as u8]), |_| Command::Ping),
     ))(i)
@@ -25,23 +25,43 @@
 extern crate mysql_common as myc;
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::io;
 use std::io::Write;
 use std::iter;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use futures_util::future::{self, Either};
+use futures_util::pin_mut;
+use socket2::{SockRef, TcpKeepalive};
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
+use tokio::io::BufWriter;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
 
 pub use crate::myc::constants::{CapabilityFlags, ColumnFlags, ColumnType, StatusFlags};
 
+mod builder;
+mod charset;
 mod commands;
 mod errorcodes;
+mod metrics;
+mod observer;
 mod packet_reader;
 mod packet_writer;
 mod params;
 mod resultset;
+mod row;
+mod schemas;
+mod session;
+mod statement_cache;
+mod statements;
 mod value;
+mod variables;
 mod writers;
 
 #[cfg(test)]
@@ -62,12 +82,167 @@ pub struct Column {
     ///
     /// Note that this is *technically* the column's alias.
     pub column: String,
+    /// The original name of this column's table, before it was aliased in the query.
+    ///
+    /// Empty for columns that do not come from a table, e.g. expressions.
+    pub org_table: String,
+    /// The original name of this column, before it was aliased in the query.
+    ///
+    /// Empty for columns that do not come from a table, e.g. expressions.
+    pub org_name: String,
     /// This column's type>
     pub coltype: ColumnType,
     /// Any flags associated with this column.
     ///
     /// Of particular interest are `ColumnFlags::UNSIGNED_FLAG` and `ColumnFlags::NOT_NULL_FLAG`.
     pub colflags: ColumnFlags,
+    /// The number of decimal digits for this column.
+    ///
+    /// For temporal types (`TIME`, `DATETIME`, `TIMESTAMP`) this is the fractional seconds
+    /// precision (fsp), between 0 and 6.
+    pub decimals: u8,
+    /// The collation id of this column's character set, e.g. `UTF8MB4_GENERAL_CI` (45).
+    ///
+    /// If 0, the column is sent with the collation the client chose when connecting; see
+    /// [`SessionContext::charset`](struct.SessionContext.html#method.charset).
+    pub charset: u16,
+    /// The declared length of this column, e.g. 10 for a `VARCHAR(10)`, or the display width 3 of
+    /// an `INT(3)`. Numbers sent as a `ZEROFILL` column are left-padded with zeros to this width.
+    ///
+    /// For character columns this is a number of characters; see
+    /// [`column_length_bytes`](struct.Column.html#method.column_length_bytes). For a
+    /// `DECIMAL(M,D)` this is the precision M, and values with more than M-D digits before the
+    /// point are rejected; the column definition then reports its display length instead, with
+    /// room for the point and the sign.
+    pub column_length: Option<u32>,
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Column {
+            table: String::new(),
+            column: String::new(),
+            org_table: String::new(),
+            org_name: String::new(),
+            coltype: ColumnType::MYSQL_TYPE_VAR_STRING,
+            colflags: ColumnFlags::empty(),
+            decimals: 0,
+            charset: 0,
+            column_length: None,
+        }
+    }
+}
+
+impl Column {
+    /// A column named `column` of the given type, with no flags.
+    ///
+    /// Flags can then be added with e.g. [`primary_key`](struct.Column.html#method.primary_key):
+    ///
+    /// ```
+    /// # use opensrv_mysql::{Column, ColumnFlags, ColumnType};
+    /// let id = Column::new("id", ColumnType::MYSQL_TYPE_LONGLONG)
+    ///     .primary_key()
+    ///     .auto_increment();
+    /// assert!(id.colflags.contains(ColumnFlags::NOT_NULL_FLAG));
+    /// ```
+    pub fn new(column: &str, coltype: ColumnType) -> Self {
+        Column {
+            column: column.to_owned(),
+            coltype,
+            ..Default::default()
+        }
+    }
+
+    /// Set this column's table.
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = table.to_owned();
+        self
+    }
+
+    /// Set the original, un-aliased names of this column and of its table, which clients use to
+    /// tell apart columns with the same name, e.g. the `id` columns of two joined tables.
+    pub fn original(mut self, org_table: &str, org_name: &str) -> Self {
+        self.org_table = org_table.to_owned();
+        self.org_name = org_name.to_owned();
+        self
+    }
+
+    /// Set the collation id of this column, e.g. `UTF8MB4_GENERAL_CI` (45), so that a resultset
+    /// may mix columns of different collations; see
+    /// [`charset`](struct.Column.html#structfield.charset).
+    pub fn charset(mut self, charset: u16) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Mark this column as `NOT NULL`.
+    ///
+    /// Note that no `NULL` value may then be written to it.
+    pub fn not_null(mut self) -> Self {
+        self.colflags |= ColumnFlags::NOT_NULL_FLAG;
+        self
+    }
+
+    /// Mark this column as (part of) the table's primary key, which also makes it `NOT NULL`.
+    pub fn primary_key(mut self) -> Self {
+        self.colflags |= ColumnFlags::PRI_KEY_FLAG | ColumnFlags::NOT_NULL_FLAG;
+        self
+    }
+
+    /// Mark this column as `AUTO_INCREMENT`.
+    pub fn auto_increment(mut self) -> Self {
+        self.colflags |= ColumnFlags::AUTO_INCREMENT_FLAG;
+        self
+    }
+
+    /// Mark this column as `UNSIGNED`.
+    pub fn unsigned(mut self) -> Self {
+        self.colflags |= ColumnFlags::UNSIGNED_FLAG;
+        self
+    }
+
+    /// The length of this column in bytes, as sent in its column definition.
+    ///
+    /// The declared length of a character column is scaled by the maximum number of bytes per
+    /// character of its character set, so that a `VARCHAR(10)` in `utf8mb4` is 40 bytes long.
+    /// A `DECIMAL(M,D)` reports its display length, i.e. M plus the decimal point and the sign
+    /// when it has them. Columns without a declared length report 1024 bytes.
+    ///
+    /// A column without a `charset` is assumed to be sent as `UTF8_GENERAL_CI`.
+    pub fn column_length_bytes(&self) -> u32 {
+        let charset = match self.charset {
+            0 => myc::constants::UTF8_GENERAL_CI,
+            charset => charset,
+        };
+        self.column_length_bytes_in(charset)
+    }
+
+    pub(crate) fn column_length_bytes_in(&self, charset: u16) -> u32 {
+        match self.column_length {
+            None => 1024,
+            Some(len) => match self.coltype {
+                ColumnType::MYSQL_TYPE_STRING
+                | ColumnType::MYSQL_TYPE_VAR_STRING
+                | ColumnType::MYSQL_TYPE_VARCHAR
+                | ColumnType::MYSQL_TYPE_BLOB
+                | ColumnType::MYSQL_TYPE_TINY_BLOB
+                | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+                | ColumnType::MYSQL_TYPE_LONG_BLOB
+                | ColumnType::MYSQL_TYPE_ENUM
+                | ColumnType::MYSQL_TYPE_SET => {
+                    len.saturating_mul(charset::max_bytes_per_char(charset))
+                }
+                // the display length of a DECIMAL(M,D): its precision, the point and the sign
+                ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                    let point = u32::from(self.decimals > 0 && self.decimals < 31);
+                    let sign =
+                        u32::from(len > 0 && !self.colflags.contains(ColumnFlags::UNSIGNED_FLAG));
+                    len.saturating_add(point + sign)
+                }
+                _ => len,
+            },
+        }
+    }
 }
 
 /// QueryStatusInfo represents the status of a query.
@@ -83,19 +258,127 @@ pub struct OkResponse {
     pub status_flags: StatusFlags,
     /// Warnings
     pub warnings: u16,
-    /// Extra infomation
+    /// Human-readable information about the query's outcome, e.g.
+    /// `Records: 3  Duplicates: 1  Warnings: 0` after a multi-row `INSERT`, which clients expose
+    /// through e.g. `mysql_info()`
     pub info: String,
     /// session state change information
     pub session_state_info: String,
+    /// session state changes, sent after `session_state_info` to clients that negotiated
+    /// `CLIENT_SESSION_TRACK`
+    pub session_state_changes: Vec<SessionStateChange>,
 }
 
+pub use crate::builder::MysqlIntermediaryBuilder;
 pub use crate::errorcodes::ErrorKind;
-pub use crate::params::{ParamParser, ParamValue, Params};
-pub use crate::resultset::{InitWriter, QueryResultWriter, RowWriter, StatementMetaWriter};
-pub use crate::value::{ToMysqlValue, Value, ValueInner};
+pub use crate::metrics::ConnectionMetrics;
+use crate::observer::Timer;
+pub use crate::observer::{ObservedCommand, QueryEvent, QueryObserver};
+pub use crate::params::{BulkParam, ParamParser, ParamValue, Params, QueryAttribute};
+use crate::resultset::Unfinished;
+pub use crate::resultset::{
+    write_stream, InitWriter, QueryResultWriter, RowWriter, StatementMetaWriter,
+};
+pub use crate::row::{MysqlColumnType, MysqlRow, ProcessRow};
+pub use crate::session::{SessionContext, SessionStateChange};
+use crate::statement_cache::StatementCache;
+pub use crate::value::{
+    Clamped, CommaJoined, FiniteOrNull, JsonArray, JsonScalar, Lossy, MysqlTime, Null, NullIfEmpty,
+    RawBinCell, RawTextCell, Scaled, ToMysqlValue, Utf8Bytes, Value, ValueInner,
+};
+pub use crate::variables::SystemVariables;
+#[cfg(feature = "derive")]
+pub use opensrv_mysql_derive::MysqlRow;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use tokio::io::AsyncWrite;
+
+    #[cfg(feature = "fuzzing")]
+    pub use crate::commands::client_handshake;
+
+    /// The column types accepted by [`columns!`](../macro.columns.html), by their short names.
+    pub mod column_type {
+        use crate::ColumnType;
+
+        pub const DECIMAL: ColumnType = ColumnType::MYSQL_TYPE_DECIMAL;
+        pub const TINY: ColumnType = ColumnType::MYSQL_TYPE_TINY;
+        pub const SHORT: ColumnType = ColumnType::MYSQL_TYPE_SHORT;
+        pub const LONG: ColumnType = ColumnType::MYSQL_TYPE_LONG;
+        pub const FLOAT: ColumnType = ColumnType::MYSQL_TYPE_FLOAT;
+        pub const DOUBLE: ColumnType = ColumnType::MYSQL_TYPE_DOUBLE;
+        pub const NULL: ColumnType = ColumnType::MYSQL_TYPE_NULL;
+        pub const TIMESTAMP: ColumnType = ColumnType::MYSQL_TYPE_TIMESTAMP;
+        pub const LONGLONG: ColumnType = ColumnType::MYSQL_TYPE_LONGLONG;
+        pub const INT24: ColumnType = ColumnType::MYSQL_TYPE_INT24;
+        pub const DATE: ColumnType = ColumnType::MYSQL_TYPE_DATE;
+        pub const TIME: ColumnType = ColumnType::MYSQL_TYPE_TIME;
+        pub const DATETIME: ColumnType = ColumnType::MYSQL_TYPE_DATETIME;
+        pub const YEAR: ColumnType = ColumnType::MYSQL_TYPE_YEAR;
+        pub const NEWDATE: ColumnType = ColumnType::MYSQL_TYPE_NEWDATE;
+        pub const VARCHAR: ColumnType = ColumnType::MYSQL_TYPE_VARCHAR;
+        pub const BIT: ColumnType = ColumnType::MYSQL_TYPE_BIT;
+        pub const TIMESTAMP2: ColumnType = ColumnType::MYSQL_TYPE_TIMESTAMP2;
+        pub const DATETIME2: ColumnType = ColumnType::MYSQL_TYPE_DATETIME2;
+        pub const TIME2: ColumnType = ColumnType::MYSQL_TYPE_TIME2;
+        pub const JSON: ColumnType = ColumnType::MYSQL_TYPE_JSON;
+        pub const NEWDECIMAL: ColumnType = ColumnType::MYSQL_TYPE_NEWDECIMAL;
+        pub const ENUM: ColumnType = ColumnType::MYSQL_TYPE_ENUM;
+        pub const SET: ColumnType = ColumnType::MYSQL_TYPE_SET;
+        pub const TINY_BLOB: ColumnType = ColumnType::MYSQL_TYPE_TINY_BLOB;
+        pub const MEDIUM_BLOB: ColumnType = ColumnType::MYSQL_TYPE_MEDIUM_BLOB;
+        pub const LONG_BLOB: ColumnType = ColumnType::MYSQL_TYPE_LONG_BLOB;
+        pub const BLOB: ColumnType = ColumnType::MYSQL_TYPE_BLOB;
+        pub const VAR_STRING: ColumnType = ColumnType::MYSQL_TYPE_VAR_STRING;
+        pub const STRING: ColumnType = ColumnType::MYSQL_TYPE_STRING;
+        pub const GEOMETRY: ColumnType = ColumnType::MYSQL_TYPE_GEOMETRY;
+    }
+}
+
+/// Build a `Vec<Column>` from `(name, type, builder methods...)` tuples.
+///
+/// The type is the name of a `ColumnType` without its `MYSQL_TYPE_` prefix, and every further
+/// element calls the [`Column`](struct.Column.html) builder method of that name, so that
+///
+/// ```
+/// # use opensrv_mysql::{columns, Column, ColumnType};
+/// let cols = columns![("id", LONG, unsigned, primary_key), ("name", VAR_STRING, table("t"))];
+/// assert_eq!(
+///     cols,
+///     vec![
+///         Column::new("id", ColumnType::MYSQL_TYPE_LONG)
+///             .unsigned()
+///             .primary_key(),
+///         Column::new("name", ColumnType::MYSQL_TYPE_VAR_STRING).table("t"),
+///     ]
+/// );
+/// ```
+#[macro_export]
+macro_rules! columns {
+    ($(($name:expr, $coltype:ident $(, $method:ident $(($($arg:expr),*))?)*)),* $(,)?) => {
+        vec![$(
+            $crate::Column::new($name, $crate::__private::column_type::$coltype)
+                $(.$method($($($arg),*)?))*
+        ),*]
+    };
+}
 
 const SCRAMBLE_SIZE: usize = 20;
 const MYSQL_NATIVE_PASSWORD: &str = "mysql_native_password";
+const MYSQL_CLEAR_PASSWORD: &str = "mysql_clear_password";
+
+/// The outcome of one step of authenticating a client, see
+/// [`AsyncMysqlShim::auth_step`](trait.AsyncMysqlShim.html#method.auth_step).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthStep {
+    /// The client is authenticated.
+    Accept,
+    /// The client is refused with `ER_ACCESS_DENIED_NO_PASSWORD_ERROR`.
+    Reject,
+    /// Send this plugin data to the client in an AuthMoreData (`0x01`) packet, and take another
+    /// step with its reply.
+    MoreData(Vec<u8>),
+}
 
 #[async_trait]
 /// Implementors of this async-trait can be used to drive a MySQL-compatible database backend.
@@ -112,11 +395,35 @@ pub trait AsyncMysqlShim<W: Send> {
     }
 
     /// Connection id
+    ///
+    /// This is asked for once per connection, and is then available from
+    /// [`SessionContext::connection_id`](struct.SessionContext.html#method.connection_id).
     fn connect_id(&self) -> u32 {
         u32::from_le_bytes([0x08, 0x00, 0x00, 0x00])
     }
 
-    /// get auth plugin
+    /// The server status flags reported in the initial handshake and in every OK and EOF packet
+    /// sent afterwards, e.g. `SERVER_STATUS_AUTOCOMMIT` when autocommit is on, or
+    /// `SERVER_STATUS_IN_TRANS_READONLY` to emulate a read-only replica.
+    ///
+    /// This is called once per connection, and by default no flag is set, i.e. autocommit is off.
+    fn server_status(&self) -> StatusFlags {
+        StatusFlags::empty()
+    }
+
+    /// Called when a client connects, before the initial handshake is sent.
+    ///
+    /// Returning an error refuses the connection: the client is sent an ERR packet with that error
+    /// instead of the handshake, and the connection is closed, as MySQL does with
+    /// `ER_CON_COUNT_ERROR` (1040) when there are too many connections. By default, every
+    /// connection is accepted.
+    async fn on_connect(&self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    /// The auth plugin name advertised to the client in the initial handshake packet.
+    ///
+    /// Return an empty string to advertise no plugin at all.
     fn default_auth_plugin(&self) -> &str {
         MYSQL_NATIVE_PASSWORD
     }
@@ -126,20 +433,39 @@ pub trait AsyncMysqlShim<W: Send> {
         MYSQL_NATIVE_PASSWORD
     }
 
-    /// Default salt(scramble) for auth plugin
-    fn salt(&self) -> [u8; SCRAMBLE_SIZE] {
-        let bs = ";X,po_k}>o6^Wz!/kM}N".as_bytes();
+    /// The salt (scramble) sent to the client in the handshake, and given to
+    /// [`authenticate`](trait.AsyncMysqlShim.html#method.authenticate).
+    ///
+    /// This is called once per connection, and by default returns random printable bytes
+    /// (`0x21..=0x7e`) from the operating system's secure random number generator, as MySQL
+    /// servers do. Override it with a fixed scramble to test authentication deterministically.
+    ///
+    /// An error fails the handshake.
+    fn salt(&self) -> io::Result<[u8; SCRAMBLE_SIZE]> {
         let mut scramble: [u8; SCRAMBLE_SIZE] = [0; SCRAMBLE_SIZE];
-        for i in 0..SCRAMBLE_SIZE {
-            scramble[i] = bs[i];
-            if scramble[i] == b'\0' || scramble[i] == b'$' {
-                scramble[i] += 1;
+        getrandom::getrandom(&mut scramble)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        for b in scramble.iter_mut() {
+            *b = 0x21 + *b % 94;
+            if *b == b'$' {
+                *b += 1;
             }
         }
-        scramble
+        Ok(scramble)
     }
 
     /// authenticate method for the specified plugin
+    ///
+    /// `salt` holds the exact scramble sent to the client in the handshake (or in the auth switch
+    /// request), and `auth_data` the client's response to it. For `mysql_native_password`, a
+    /// client knowing the password responds with
+    /// `SHA1(password) XOR SHA1(salt + SHA1(SHA1(password)))`.
+    ///
+    /// For `mysql_clear_password`, `auth_data` is the password itself in cleartext, without its
+    /// trailing NUL. Since the password would otherwise travel unprotected, that plugin is refused
+    /// before asking the client for it unless
+    /// [`IntermediaryOptions::secure_transport`](struct.IntermediaryOptions.html#structfield.secure_transport)
+    /// is set.
     async fn authenticate(
         &self,
         _auth_plugin: &str,
@@ -150,11 +476,42 @@ pub trait AsyncMysqlShim<W: Send> {
         true
     }
 
+    /// Take a step of authenticating a client with `auth_plugin`, for plugins that exchange more
+    /// data with the client than its response to the scramble, e.g. `caching_sha2_password`.
+    ///
+    /// `auth_data` holds the client's response to the scramble in the first step, where `round`
+    /// is 0, and its reply to the data of the previous [`AuthStep::MoreData`](enum.AuthStep.html)
+    /// in every later one. By default, the client is accepted or rejected right away by
+    /// [`authenticate`](trait.AsyncMysqlShim.html#method.authenticate).
+    async fn auth_step(
+        &self,
+        auth_plugin: &str,
+        username: &[u8],
+        salt: &[u8],
+        round: usize,
+        auth_data: &[u8],
+    ) -> AuthStep {
+        let _ = round;
+        if self
+            .authenticate(auth_plugin, username, salt, auth_data)
+            .await
+        {
+            AuthStep::Accept
+        } else {
+            AuthStep::Reject
+        }
+    }
+
     /// Called when the client issues a request to prepare `query` for later execution.
     ///
     /// The provided [`StatementMetaWriter`](struct.StatementMetaWriter.html) should be used to
     /// notify the client of the statement id assigned to the prepared statement, as well as to
     /// give metadata about the types of parameters and returned columns.
+    ///
+    /// The statement id is chosen by the shim itself, and is the one later given to
+    /// [`on_execute`](trait.AsyncMysqlShim.html#tymethod.on_execute) and
+    /// [`on_close`](trait.AsyncMysqlShim.html#tymethod.on_close), so any per-statement state
+    /// (e.g. a query plan) can be kept keyed by it.
     async fn on_prepare<'a>(
         &'a mut self,
         query: &'a str,
@@ -173,6 +530,32 @@ pub trait AsyncMysqlShim<W: Send> {
         results: QueryResultWriter<'a, W>,
     ) -> Result<(), Self::Error>;
 
+    /// Called when a MariaDB client executes a previously prepared statement once for each of the
+    /// given `rows` of parameters, with a single `COM_STMT_BULK_EXECUTE`.
+    ///
+    /// Clients only do so when
+    /// [`IntermediaryOptions::bulk_execute`](struct.IntermediaryOptions.html#structfield.bulk_execute)
+    /// is set. The whole batch is answered with a single response, typically one OK packet
+    /// counting the rows affected by every execution. By default, an `ER_UNKNOWN_COM_ERROR` error
+    /// is sent.
+    async fn on_bulk_execute<'a>(
+        &'a mut self,
+        _id: u32,
+        _rows: Vec<Vec<BulkParam<'a>>>,
+        results: QueryResultWriter<'a, W>,
+    ) -> Result<(), Self::Error>
+    where
+        W: AsyncWrite + Unpin + 'async_trait,
+    {
+        results
+            .error(
+                ErrorKind::ER_UNKNOWN_COM_ERROR,
+                b"bulk execution is not supported",
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Called when the client wishes to deallocate resources associated with a previously prepared
     /// statement.
     async fn on_close<'a>(&'a mut self, stmt: u32)
@@ -189,12 +572,74 @@ pub trait AsyncMysqlShim<W: Send> {
         results: QueryResultWriter<'a, W>,
     ) -> Result<(), Self::Error>;
 
-    /// Called when client switches database.
+    /// Called when the client asks for the list of processes (`COM_PROCESS_INFO`), which is sent
+    /// back as the resultset of `SHOW PROCESSLIST`.
+    ///
+    /// By default, the list is empty.
+    async fn on_process_list(&self) -> Vec<ProcessRow> {
+        Vec::new()
+    }
+
+    /// Called when the client runs `SHOW DATABASES [LIKE 'pattern']`, or `SHOW SCHEMAS`, to list
+    /// the databases.
+    ///
+    /// Returning names answers the query with a `Database` column listing those that match the
+    /// pattern, the `_` and `%` wildcards included. By default, `None` is returned, and the query
+    /// goes to [`on_query`](trait.AsyncMysqlShim.html#tymethod.on_query) instead.
+    async fn on_show_databases(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Called when the client runs `SHOW [FULL] TABLES {FROM | IN} schema [LIKE 'pattern']` to
+    /// list the tables of `schema`.
+    ///
+    /// Returning names answers the query with a `Tables_in_<schema>` column listing those that
+    /// match the pattern, the `_` and `%` wildcards included, and a `Table_type` column of
+    /// `BASE TABLE` for `SHOW FULL TABLES`. By default, `None` is returned, and the query goes to
+    /// [`on_query`](trait.AsyncMysqlShim.html#tymethod.on_query) instead, as does a `SHOW TABLES`
+    /// that names no schema.
+    async fn on_show_tables(&self, _schema: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Called when the client resets its session with `COM_RESET_CONNECTION`, so that the shim
+    /// can reset its own session state too, e.g. session variables or autocommit.
+    ///
+    /// By then, the client's prepared statements were closed with
+    /// [`on_close`](trait.AsyncMysqlShim.html#tymethod.on_close), and its
+    /// [`SessionContext`](struct.SessionContext.html) restored to what it was right after
+    /// connecting, i.e. its SQL mode, database and charset. By default, nothing else is done.
+    async fn on_reset_connection(&mut self) {}
+
+    /// Called when the client asks to kill the connection with the given id
+    /// (`COM_PROCESS_KILL`).
+    ///
+    /// Returning an error sends it to the client, e.g. `ER_NO_SUCH_THREAD` for an unknown id,
+    /// which is what the default implementation does for every id.
+    async fn on_kill(&mut self, _id: u32) -> Result<(), ErrorKind> {
+        Err(ErrorKind::ER_NO_SUCH_THREAD)
+    }
+
+    /// Called when the client sends a command that is not supported, given its command byte.
+    ///
+    /// The client is then sent an `ER_UNKNOWN_COM_ERROR` error, and the connection is kept open.
+    fn on_unknown_command(&mut self, _command: u8) {}
+
+    /// Called when client switches database, either with `USE`, `COM_INIT_DB`, or by naming a
+    /// database when connecting. In the latter case, this is called during the handshake.
+    ///
+    /// Implementations must reply through `writer`, e.g. with `InitWriter::ok` or
+    /// `InitWriter::error`: the client waits for that reply, and hangs if `Ok` is returned without
+    /// one. By default, every database is accepted.
     async fn on_init<'a>(
         &'a mut self,
         _: &'a str,
-        _: InitWriter<'a, W>,
-    ) -> Result<(), Self::Error> {
+        writer: InitWriter<'a, W>,
+    ) -> Result<(), Self::Error>
+    where
+        W: AsyncWrite + Unpin + 'async_trait,
+    {
+        writer.ok().await?;
         Ok(())
     }
 }
@@ -204,6 +649,92 @@ pub trait AsyncMysqlShim<W: Send> {
 pub struct IntermediaryOptions {
     /// process use statement on the on_query handler
     pub process_use_statement_on_query: bool,
+    /// answer every `SET` statement with an OK packet rather than calling `on_query`, e.g. the
+    /// `SET NAMES` and `SET autocommit` drivers send as they connect, for handlers that have no
+    /// use for them; a `SET sql_mode` still updates `SessionContext::sql_mode`
+    pub acknowledge_set_statements: bool,
+    /// advertise `CLIENT_QUERY_ATTRIBUTES`, so that clients may attach query attributes to their
+    /// queries, see `QueryResultWriter::query_attributes`
+    pub query_attributes: bool,
+    /// called with the timing of every `on_query` and `on_execute`, see `QueryObserver`
+    pub query_observer: Option<QueryObserver>,
+    /// the streams are known to be secure (e.g. TLS terminated by the caller, or a Unix socket),
+    /// which is required to authenticate with `mysql_clear_password`
+    pub secure_transport: bool,
+    /// the most bytes of rows a single resultset may send, the query is failed with an error once
+    /// a row goes over it
+    pub max_result_set_size: Option<usize>,
+    /// how many prepared statements to remember by their SQL, so that preparing the same SQL
+    /// again reuses the open statement rather than calling `on_prepare`; 0 disables the cache
+    ///
+    /// Statement ids given to `StatementMetaWriter::reply` must then be unique among the open
+    /// statements. A cached statement stays open after the client closes it, `on_close` is only
+    /// called once it is evicted, or on `COM_RESET_CONNECTION`.
+    pub statement_cache_size: usize,
+    /// how many prepared statements a client may have open at once, like MySQL's
+    /// `max_prepared_stmt_count`; preparing one more fails with
+    /// `ER_MAX_PREPARED_STMT_COUNT_REACHED` (1461) until the client closes some
+    pub max_prepared_statements: Option<usize>,
+    /// send integers in binary resultsets only as columns of their own type, e.g. a `u8` only as
+    /// `MYSQL_TYPE_TINY`, rather than widening or narrowing them to the column's type
+    pub strict_types: bool,
+    /// answer `SELECT @@variable`, `SHOW VARIABLES` and `SHOW STATUS` from these variables rather
+    /// than calling `on_query`, see `SystemVariables`
+    pub system_variables: Option<SystemVariables>,
+    /// advertise `CLIENT_SESSION_TRACK`, so that session state changes are sent to clients in OK
+    /// packets, see `OkResponse::session_state_changes`
+    pub session_track: bool,
+    /// set `TCP_NODELAY` on connections served with `AsyncMysqlIntermediary::run_on_tcp`, so that
+    /// small responses are not delayed by Nagle's algorithm, as MySQL servers do
+    pub tcp_nodelay: bool,
+    /// enable TCP keepalive on connections served with `AsyncMysqlIntermediary::run_on_tcp`,
+    /// probing the client after it has been idle for this long
+    pub tcp_keepalive: Option<Duration>,
+    /// keep counters of the bytes, rows and queries of every connection, see `ConnectionMetrics`
+    pub metrics: bool,
+    /// don't flush a response to a query or statement execution as soon as it is complete, only
+    /// once the handler returns, so that responses sent by the handler are batched in the output
+    /// stream's buffer rather than each being written on its own
+    pub defer_flush: bool,
+    /// interrupt `on_query` and `on_execute` handlers that run for longer than this, answering
+    /// the client with `ER_QUERY_INTERRUPTED` (1317) in their stead and going on with the next
+    /// command
+    ///
+    /// The handler's future is dropped when it times out, so handlers must be cancellation safe:
+    /// anything they hold across an `.await` must stay consistent if they never resume. Rows sent
+    /// before the timeout reach the client, and the error ends their resultset. Should the
+    /// timeout cut a packet short as it is written out, the connection is closed instead.
+    pub query_timeout: Option<Duration>,
+    /// present as a MariaDB server, for clients that gate features on it
+    ///
+    /// The handshake then advertises the `5.5.5-` prefixed version MariaDB clients look for, and
+    /// sends MariaDB's extended capabilities in place of its last four filler bytes, of which only
+    /// bulk execution is supported, see [`bulk_execute`](#structfield.bulk_execute). A shim's
+    /// [`version`](trait.AsyncMysqlShim.html#method.version) is kept if it has that prefix,
+    /// otherwise `5.5.5-10.6.0-MariaDB-opensrv` is advertised.
+    pub mariadb: bool,
+    /// advertise MariaDB's bulk execution of prepared statements (`COM_STMT_BULK_EXECUTE`), for
+    /// shims that implement `AsyncMysqlShim::on_bulk_execute`; this has no effect unless
+    /// [`mariadb`](#structfield.mariadb) is set
+    pub bulk_execute: bool,
+}
+
+impl IntermediaryOptions {
+    /// Apply the TCP options to `stream`, see
+    /// [`tcp_nodelay`](struct.IntermediaryOptions.html#structfield.tcp_nodelay) and
+    /// [`tcp_keepalive`](struct.IntermediaryOptions.html#structfield.tcp_keepalive).
+    ///
+    /// This is done by [`AsyncMysqlIntermediary::run_on_tcp`](struct.AsyncMysqlIntermediary.html#method.run_on_tcp),
+    /// and is only needed to serve a `TcpStream` some other way.
+    pub fn configure_tcp(&self, stream: &TcpStream) -> io::Result<()> {
+        if self.tcp_nodelay {
+            stream.set_nodelay(true)?;
+        }
+        if let Some(time) = self.tcp_keepalive {
+            SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Default)]
@@ -211,20 +742,154 @@ struct StatementData {
     long_data: HashMap<u16, Vec<u8>>,
     bound_types: Vec<(myc::constants::ColumnType, bool)>,
     params: u16,
+    // how many times the client was handed this statement and has not closed it yet
+    handles: usize,
 }
 
 const AUTH_PLUGIN_DATA_PART_1_LENGTH: usize = 8;
 
+// COM_STMT_EXECUTE flag telling that the parameter count is sent along with the parameters
+const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+// The version advertised to MariaDB clients, whose version must follow the `5.5.5-` prefix that
+// older MySQL clients, which expect a 5.x server, skip over.
+const MARIADB_VERSION: &str = "5.5.5-10.6.0-MariaDB-opensrv";
+
+fn server_version(version: &str, mariadb: bool) -> &str {
+    if mariadb && !version.starts_with("5.5.5-") {
+        MARIADB_VERSION
+    } else {
+        version
+    }
+}
+
+// The MariaDB capability allowing `COM_STMT_BULK_EXECUTE`, within the extended capabilities that
+// make up the upper half of MariaDB's 64-bit capabilities.
+const MARIADB_CLIENT_STMT_BULK_OPERATIONS: u32 = 1 << 2;
+
+// Run a handler to completion, or until the query timeout passes, see
+// `IntermediaryOptions::query_timeout`.
+async fn timed<F, E>(timeout: Option<Duration>, handler: F) -> Option<Result<(), E>>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, handler).await.ok(),
+        None => Some(handler.await),
+    }
+}
+
+// Pass on the result of a handler, or if it timed out, answer the client with an error in
+// place of whatever the handler had yet to send. This only takes the writer, as the command
+// being handled still borrows the intermediary's reader.
+async fn interrupt_if_timed_out<W, E>(
+    writer: &mut packet_writer::PacketWriter<W>,
+    res: Option<Result<(), E>>,
+) -> Result<(), E>
+where
+    W: AsyncWrite + Unpin,
+    E: From<io::Error>,
+{
+    if let Some(res) = res {
+        return res;
+    }
+    if writer.partial_write {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "query timed out while its response was being written",
+        )
+        .into());
+    }
+    writer.discard_packet();
+    if writer.unfinished.take().is_some() {
+        writers::write_err(
+            ErrorKind::ER_QUERY_INTERRUPTED,
+            b"Query execution was interrupted",
+            writer,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+// Count a query or execution the shim handled, and report it to `observer` if it was timed.
+// This takes the fields it needs rather than the intermediary, whose reader is still borrowed
+// by the command being handled.
+fn observe(
+    observer: &Option<QueryObserver>,
+    session: &SessionContext,
+    command: ObservedCommand<'_>,
+    timer: Option<Timer>,
+    succeeded: bool,
+) {
+    if let Some(metrics) = &session.metrics {
+        metrics.add_query();
+    }
+    if let (Some(observer), Some(timer)) = (observer, timer) {
+        observer.notify(&timer.finish(command, succeeded));
+    }
+}
+
 /// A server that speaks the MySQL/MariaDB protocol, and can delegate client commands to a backend
 /// that implements [`AsyncMysqlShim`](trait.AsyncMysqlShim.html).
 pub struct AsyncMysqlIntermediary<B, S: AsyncRead + Unpin, W> {
     pub(crate) client_capabilities: CapabilityFlags,
     process_use_statement_on_query: bool,
+    acknowledge_set_statements: bool,
+    query_attributes: bool,
+    query_observer: Option<QueryObserver>,
+    secure_transport: bool,
+    max_result_set_size: Option<usize>,
+    statement_cache_size: usize,
+    max_prepared_statements: Option<usize>,
+    strict_types: bool,
+    system_variables: Option<SystemVariables>,
+    session_track: bool,
+    query_timeout: Option<Duration>,
+    mariadb: bool,
+    bulk_execute: bool,
+    session: SessionContext,
     shim: B,
     reader: packet_reader::PacketReader<S>,
     writer: packet_writer::PacketWriter<W>,
 }
 
+impl<B> AsyncMysqlIntermediary<B, OwnedReadHalf, BufWriter<OwnedWriteHalf>>
+where
+    B: AsyncMysqlShim<BufWriter<OwnedWriteHalf>> + Send + Sync,
+{
+    /// Create a new server over an accepted TCP connection and process client commands until the
+    /// client disconnects or an error occurs, with config options.
+    ///
+    /// The socket is configured with the TCP options of `opts`, and the client's address is
+    /// available through [`SessionContext::peer_addr`](struct.SessionContext.html#method.peer_addr).
+    pub async fn run_on_tcp(
+        shim: B,
+        stream: TcpStream,
+        opts: &IntermediaryOptions,
+    ) -> Result<(), B::Error> {
+        Self::run_on_tcp_until(shim, stream, opts, future::pending()).await
+    }
+
+    /// Like [`run_on_tcp`](struct.AsyncMysqlIntermediary.html#method.run_on_tcp), but stops
+    /// once `shutdown` completes, see
+    /// [`run_until`](struct.AsyncMysqlIntermediary.html#method.run_until).
+    pub async fn run_on_tcp_until<F>(
+        shim: B,
+        stream: TcpStream,
+        opts: &IntermediaryOptions,
+        shutdown: F,
+    ) -> Result<(), B::Error>
+    where
+        F: Future<Output = ()>,
+    {
+        opts.configure_tcp(&stream)?;
+        let peer_addr = stream.peer_addr().ok();
+        let (r, w) = stream.into_split();
+        Self::serve(shim, r, BufWriter::new(w), peer_addr, opts, shutdown).await
+    }
+}
+
 impl<B, S, W> AsyncMysqlIntermediary<B, S, W>
 where
     W: AsyncWrite + Send + Unpin,
@@ -245,44 +910,155 @@ where
         output_stream: W,
         opts: &IntermediaryOptions,
     ) -> Result<(), B::Error> {
-        let r = packet_reader::PacketReader::new(input_stream);
-        let w = packet_writer::PacketWriter::new(output_stream);
+        Self::run_with_peer_addr(shim, input_stream, output_stream, None, opts).await
+    }
+
+    /// Create a new server over two one-way channels of a client connected from `peer_addr`, and
+    /// process client commands until the client disconnects or an error occurs, with config
+    /// options.
+    ///
+    /// The address is available to the shim through
+    /// [`SessionContext::peer_addr`](struct.SessionContext.html#method.peer_addr).
+    pub async fn run_with_peer_addr(
+        shim: B,
+        input_stream: S,
+        output_stream: W,
+        peer_addr: Option<SocketAddr>,
+        opts: &IntermediaryOptions,
+    ) -> Result<(), B::Error> {
+        Self::serve(
+            shim,
+            input_stream,
+            output_stream,
+            peer_addr,
+            opts,
+            future::pending(),
+        )
+        .await
+    }
+
+    /// Create a new server over two one-way channels and process client commands until the client
+    /// disconnects, an error occurs, or `shutdown` completes, with config options.
+    ///
+    /// `shutdown` is only checked between commands: a command being processed when it completes,
+    /// e.g. a running query, is answered as usual, and the connection is closed right after,
+    /// without reading any further command. An idle connection is closed right away. Waiting on
+    /// the same signal from every connection, e.g. with a `tokio::sync::watch` channel, lets a
+    /// server drain its connections gracefully.
+    pub async fn run_until<F>(
+        shim: B,
+        input_stream: S,
+        output_stream: W,
+        opts: &IntermediaryOptions,
+        shutdown: F,
+    ) -> Result<(), B::Error>
+    where
+        F: Future<Output = ()>,
+    {
+        Self::serve(shim, input_stream, output_stream, None, opts, shutdown).await
+    }
+
+    async fn serve<F>(
+        shim: B,
+        input_stream: S,
+        output_stream: W,
+        peer_addr: Option<SocketAddr>,
+        opts: &IntermediaryOptions,
+        shutdown: F,
+    ) -> Result<(), B::Error>
+    where
+        F: Future<Output = ()>,
+    {
+        let metrics = opts.metrics.then(Arc::default);
+        let mut r = packet_reader::PacketReader::new(input_stream);
+        r.metrics = metrics.clone();
+        let mut w = packet_writer::PacketWriter::new(output_stream);
+        w.metrics = metrics.clone();
+        w.defer_flush = opts.defer_flush;
         let mut mi = AsyncMysqlIntermediary {
             client_capabilities: CapabilityFlags::from_bits_truncate(0),
             process_use_statement_on_query: opts.process_use_statement_on_query,
+            acknowledge_set_statements: opts.acknowledge_set_statements,
+            query_attributes: opts.query_attributes,
+            query_observer: opts.query_observer.clone(),
+            secure_transport: opts.secure_transport,
+            max_result_set_size: opts.max_result_set_size,
+            statement_cache_size: opts.statement_cache_size,
+            max_prepared_statements: opts.max_prepared_statements,
+            strict_types: opts.strict_types,
+            system_variables: opts
+                .system_variables
+                .clone()
+                .map(|v| v.with_default_version(server_version(shim.version(), opts.mariadb))),
+            session_track: opts.session_track,
+            query_timeout: opts.query_timeout,
+            mariadb: opts.mariadb,
+            bulk_execute: opts.bulk_execute,
+            session: SessionContext {
+                connection_id: shim.connect_id(),
+                peer_addr,
+                metrics,
+                sql_mode: opts
+                    .system_variables
+                    .as_ref()
+                    .and_then(|v| v.variable("sql_mode"))
+                    .unwrap_or_default()
+                    .to_owned(),
+                ..Default::default()
+            },
             shim,
             reader: r,
             writer: w,
         };
         mi.init().await?;
-        mi.run().await
+        mi.run(shutdown).await
     }
 
     async fn init(&mut self) -> Result<(), B::Error> {
+        if let Err(kind) = self.shim.on_connect().await {
+            let msg = match kind {
+                ErrorKind::ER_CON_COUNT_ERROR => "Too many connections".to_owned(),
+                kind => format!("Connection refused: {:?}", kind),
+            };
+            // the client's capabilities are not known yet, so the SQL state is left out, as it is
+            // for clients that predate CLIENT_PROTOCOL_41
+            self.writer.write_all(&[0xFF])?;
+            self.writer.write_all(&(kind as u16).to_le_bytes())?;
+            self.writer.write_all(msg.as_bytes())?;
+            self.writer.end_packet().await?;
+            self.writer.flush_all().await?;
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, msg).into());
+        }
+
         // https://dev.mysql.com/doc/internals/en/connection-phase-packets.html#packet-Protocol::HandshakeV10
         self.writer.write_all(&[10])?; // protocol 10
 
-        self.writer.write_all(self.shim.version().as_bytes())?;
+        self.writer
+            .write_all(server_version(self.shim.version(), self.mariadb).as_bytes())?;
         self.writer.write_all(&[0x00])?;
 
         // connection_id (4 bytes)
         self.writer
-            .write_all(&self.shim.connect_id().to_le_bytes())?;
-
-        let server_capabilities = (
-            CapabilityFlags::CLIENT_PROTOCOL_41
-                | CapabilityFlags::CLIENT_SECURE_CONNECTION
-                | CapabilityFlags::CLIENT_PLUGIN_AUTH
-                | CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA
-                | CapabilityFlags::CLIENT_CONNECT_WITH_DB
-                | CapabilityFlags::CLIENT_DEPRECATE_EOF
-            // | CapabilityFlags::CLIENT_SSL
-        )
-            .bits();
+            .write_all(&self.session.connection_id.to_le_bytes())?;
+
+        let mut server_capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+            | CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | CapabilityFlags::CLIENT_PLUGIN_AUTH
+            | CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA
+            | CapabilityFlags::CLIENT_CONNECT_WITH_DB
+            | CapabilityFlags::CLIENT_DEPRECATE_EOF;
+        // | CapabilityFlags::CLIENT_SSL
+        if self.query_attributes {
+            server_capabilities |= CapabilityFlags::CLIENT_QUERY_ATTRIBUTES;
+        }
+        if self.session_track {
+            server_capabilities |= CapabilityFlags::CLIENT_SESSION_TRACK;
+        }
+        let server_capabilities = server_capabilities.bits();
 
         let server_capabilities = server_capabilities.to_le_bytes();
         let default_auth_plugin = self.shim.default_auth_plugin();
-        let scramble = self.shim.salt();
+        let scramble = self.shim.salt()?;
 
         self.writer
             .write_all(&scramble[0..AUTH_PLUGIN_DATA_PART_1_LENGTH])?; // auth-plugin-data-part-1
@@ -291,7 +1067,9 @@ where
         self.writer.write_all(&server_capabilities[..2])?; // The lower 2 bytes of the Capabilities Flags, 0x42
                                                            // self.writer.write_all(&[0x00, 0x42])?;
         self.writer.write_all(&[0x21])?; // UTF8_GENERAL_CI
-        self.writer.write_all(&[0x00, 0x00])?; // status_flags
+        let server_status = self.shim.server_status();
+        self.writer.server_status = server_status;
+        self.writer.write_all(&server_status.bits().to_le_bytes())?; // status_flags
         self.writer.write_all(&server_capabilities[2..4])?; // The upper 2 bytes of the Capabilities Flags
 
         if default_auth_plugin.is_empty() {
@@ -301,7 +1079,19 @@ where
             self.writer
                 .write_all(&((scramble.len() + 1) as u8).to_le_bytes())?; // length of the combined auth_plugin_data(scramble), if auth_plugin_data_len is > 0
         }
-        self.writer.write_all(&[0x00; 10][..])?; // 10 bytes filler
+        if self.mariadb {
+            // MariaDB clients read the last 4 filler bytes as the extended capabilities, since
+            // CLIENT_LONG_PASSWORD is not set; progress reports and extended metadata are not
+            // supported
+            let mut mariadb_capabilities = 0u32;
+            if self.bulk_execute {
+                mariadb_capabilities |= MARIADB_CLIENT_STMT_BULK_OPERATIONS;
+            }
+            self.writer.write_all(&[0x00; 6][..])?;
+            self.writer.write_all(&mariadb_capabilities.to_le_bytes())?;
+        } else {
+            self.writer.write_all(&[0x00; 10][..])?; // 10 bytes filler
+        }
 
         // Part2 of the auth_plugin_data
         // $len=MAX(13, length of auth-plugin-data - 8)
@@ -322,6 +1112,26 @@ where
                     "peer terminated connection",
                 )
             })?;
+            // CLIENT_SSL is never advertised, so a client that asks to switch to TLS regardless is
+            // refused, rather than having its SSL request taken for an incomplete handshake
+            let requested = handshake
+                .get(..2)
+                .map(|c| u32::from(u16::from_le_bytes([c[0], c[1]])))
+                .map(CapabilityFlags::from_bits_truncate)
+                .unwrap_or_else(CapabilityFlags::empty);
+            if requested.contains(CapabilityFlags::CLIENT_SSL) {
+                let err_msg = "TLS is not supported by this server";
+                self.writer.set_seq(seq.wrapping_add(1));
+                writers::write_err(
+                    ErrorKind::ER_HANDSHAKE_ERROR,
+                    err_msg.as_bytes(),
+                    &mut self.writer,
+                )
+                .await?;
+                self.writer.flush_all().await?;
+                return Err(io::Error::new(io::ErrorKind::ConnectionRefused, err_msg).into());
+            }
+
             let handshake = commands::client_handshake(&handshake)
                 .map_err(|e| match e {
                     nom::Err::Incomplete(_) => io::Error::new(
@@ -362,17 +1172,54 @@ where
             }
 
             self.client_capabilities = handshake.capabilities;
+            if handshake.collation != 0 {
+                self.session.charset = handshake.collation;
+            }
+            if !self.query_attributes {
+                self.client_capabilities
+                    .remove(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES);
+            }
+            if !self.session_track {
+                self.client_capabilities
+                    .remove(CapabilityFlags::CLIENT_SESSION_TRACK);
+            }
+            // LOAD DATA LOCAL INFILE requests are never sent, so the capability is not negotiated
+            // even if the client offers it
+            self.client_capabilities
+                .remove(CapabilityFlags::CLIENT_LOCAL_FILES);
+            self.session.client_capabilities = self.client_capabilities;
             let mut auth_response = handshake.auth_response.clone();
             let auth_plugin_expect = self
                 .shim
                 .auth_plugin_for_username(&handshake.username)
                 .await;
 
-            // auth switch
-            if !auth_plugin_expect.is_empty()
-                && auth_response.is_empty()
-                && handshake.auth_plugin != auth_plugin_expect.as_bytes()
-            {
+            if auth_plugin_expect == MYSQL_CLEAR_PASSWORD && !self.secure_transport {
+                let err_msg = format!(
+                    "Authenticate failed, user: {:?}, auth_plugin: {:?} requires a secure transport",
+                    String::from_utf8_lossy(&handshake.username),
+                    auth_plugin_expect,
+                );
+                self.writer.set_seq(seq + 1);
+                writers::write_err(
+                    ErrorKind::ER_ACCESS_DENIED_ERROR,
+                    err_msg.as_bytes(),
+                    &mut self.writer,
+                )
+                .await?;
+                self.writer.flush_all().await?;
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, err_msg).into());
+            }
+
+            // auth switch, whenever the client answered the scramble with another plugin than the
+            // one expected for the user; clients without CLIENT_PLUGIN_AUTH send no plugin name,
+            // and answer with mysql_native_password
+            let auth_plugin_sent: &[u8] = if handshake.auth_plugin.is_empty() {
+                MYSQL_NATIVE_PASSWORD.as_bytes()
+            } else {
+                &handshake.auth_plugin
+            };
+            if !auth_plugin_expect.is_empty() && auth_plugin_sent != auth_plugin_expect.as_bytes() {
                 self.writer.set_seq(seq + 1);
                 self.writer.write_all(&[0xfe])?;
                 self.writer.write_all(auth_plugin_expect.as_bytes())?;
@@ -399,16 +1246,46 @@ where
 
             self.writer.set_seq(seq + 1);
 
-            if !self
-                .shim
-                .authenticate(
-                    auth_plugin_expect,
-                    &handshake.username,
-                    &scramble,
-                    auth_response.as_slice(),
-                )
-                .await
-            {
+            // the cleartext password is sent NUL-terminated
+            if auth_plugin_expect == MYSQL_CLEAR_PASSWORD && auth_response.last() == Some(&0) {
+                auth_response.pop();
+            }
+
+            let mut round = 0;
+            let accepted = loop {
+                let step = self
+                    .shim
+                    .auth_step(
+                        auth_plugin_expect,
+                        &handshake.username,
+                        &scramble,
+                        round,
+                        auth_response.as_slice(),
+                    )
+                    .await;
+                match step {
+                    AuthStep::Accept => break true,
+                    AuthStep::Reject => break false,
+                    AuthStep::MoreData(data) => {
+                        self.writer.write_all(&[0x01])?;
+                        self.writer.write_all(&data)?;
+                        self.writer.end_packet().await?;
+                        self.writer.flush_all().await?;
+
+                        let (rseq, reply) = self.reader.next_async().await?.ok_or_else(|| {
+                            io::Error::new(
+                                io::ErrorKind::ConnectionAborted,
+                                "peer terminated connection",
+                            )
+                        })?;
+                        self.writer.set_seq(rseq.wrapping_add(1));
+                        auth_response = reply.to_vec();
+                        round += 1;
+                    }
+                }
+            };
+
+            if !accepted {
                 let err_msg = format!(
                     "Authenticate failed, user: {:?}, auth_plugin: {:?}",
                     String::from_utf8_lossy(&handshake.username),
@@ -428,6 +1305,8 @@ where
                 let w = InitWriter {
                     client_capabilities: self.client_capabilities,
                     writer: &mut self.writer,
+                    session: &mut self.session,
+                    schema: db,
                 };
                 self.shim.on_init(db, w).await?;
             } else {
@@ -445,23 +1324,91 @@ where
         Ok(())
     }
 
-    async fn run(mut self) -> Result<(), B::Error> {
+    // Complete a response that the shim left unfinished by dropping its writer.
+    async fn finish_response(&mut self) -> io::Result<()> {
+        match self.writer.unfinished.take() {
+            None => Ok(()),
+            Some(Unfinished::Nothing) => {
+                writers::write_err(
+                    ErrorKind::ER_UNKNOWN_ERROR,
+                    b"the query handler returned without a response",
+                    &mut self.writer,
+                )
+                .await
+            }
+            Some(Unfinished::End(end)) => {
+                resultset::write_finalizer(&mut self.writer, self.client_capabilities, end, false)
+                    .await
+            }
+        }
+    }
+
+    async fn run<F>(mut self, shutdown: F) -> Result<(), B::Error>
+    where
+        F: Future<Output = ()>,
+    {
         use crate::commands::Command;
 
-        let mut stmts: HashMap<u32, _> = HashMap::new();
-        while let Some((seq, packet)) = self.reader.next_async().await? {
+        pin_mut!(shutdown);
+        // the session as the handshake left it, which COM_RESET_CONNECTION goes back to
+        let defaults = self.session.clone();
+        let mut stmts: HashMap<u32, StatementData> = HashMap::new();
+        let mut stmt_cache =
+            (self.statement_cache_size > 0).then(|| StatementCache::new(self.statement_cache_size));
+        loop {
+            let next = {
+                let next = self.reader.next_async();
+                pin_mut!(next);
+                // shutting down takes precedence over a command that is already waiting
+                match future::select(shutdown.as_mut(), next).await {
+                    Either::Left(_) => break,
+                    Either::Right((next, _)) => next?,
+                }
+            };
+            let (seq, packet) = match next {
+                Some(next) => next,
+                None => break,
+            };
             self.writer.set_seq(seq + 1);
             let res = commands::parse(&packet);
             match res {
                 Ok(cmd) => {
                     match cmd.1 {
                         Command::Query(q) => {
-                            if q.starts_with(b"SELECT @@") || q.starts_with(b"select @@") {
+                            let (attrs, q) = if self
+                                .client_capabilities
+                                .contains(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
+                            {
+                                params::split_query_attributes(q)?
+                            } else {
+                                (Vec::new(), q)
+                            };
+
+                            let variables = self.system_variables.as_ref().and_then(|v| {
+                                ::std::str::from_utf8(q).ok().and_then(|q| v.answer(q))
+                            });
+                            if let Some((cols, rows)) = variables {
                                 let w = QueryResultWriter::new(
                                     &mut self.writer,
                                     false,
                                     self.client_capabilities,
-                                );
+                                    &self.session,
+                                )
+                                .with_max_size(self.max_result_set_size);
+                                let mut w = w.start(&cols).await?;
+                                for row in rows {
+                                    w.write_row(row).await?;
+                                }
+                                w.finish().await?;
+                            } else if q.starts_with(b"SELECT @@") || q.starts_with(b"select @@") {
+                                let w = QueryResultWriter::new(
+                                    &mut self.writer,
+                                    false,
+                                    self.client_capabilities,
+                                    &self.session,
+                                )
+                                .with_query_attributes(attrs)
+                                .with_max_size(self.max_result_set_size);
 
                                 let var = &q[b"SELECT @@".len()..];
                                 let var_with_at = &q[b"SELECT ".len()..];
@@ -470,6 +1417,7 @@ where
                                     column: String::from_utf8_lossy(var_with_at).to_string(),
                                     coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
                                     colflags: myc::constants::ColumnFlags::UNSIGNED_FLAG,
+                                    ..Default::default()
                                 }];
 
                                 match var {
@@ -479,60 +1427,160 @@ where
                                         w.finish().await?;
                                     }
                                     _ => {
-                                        self.shim
-                                            .on_query(
-                                                ::std::str::from_utf8(q).map_err(|e| {
-                                                    io::Error::new(io::ErrorKind::InvalidData, e)
-                                                })?,
-                                                w,
-                                            )
-                                            .await?;
+                                        let q = ::std::str::from_utf8(q).map_err(|e| {
+                                            io::Error::new(io::ErrorKind::InvalidData, e)
+                                        })?;
+                                        let timer =
+                                            self.query_observer.as_ref().map(|_| Timer::start());
+                                        let res =
+                                            timed(self.query_timeout, self.shim.on_query(q, w))
+                                                .await;
+                                        observe(
+                                            &self.query_observer,
+                                            &self.session,
+                                            ObservedCommand::Query(q),
+                                            timer,
+                                            matches!(res, Some(Ok(()))),
+                                        );
+                                        interrupt_if_timed_out(&mut self.writer, res).await?;
                                     }
                                 }
                             } else if !self.process_use_statement_on_query
                                 && (q.starts_with(b"USE ") || q.starts_with(b"use "))
                             {
+                                let schema = ::std::str::from_utf8(&q[b"USE ".len()..])
+                                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                                let schema = schema.trim().trim_end_matches(';').trim_matches('`');
                                 let w = InitWriter {
                                     client_capabilities: self.client_capabilities,
                                     writer: &mut self.writer,
+                                    session: &mut self.session,
+                                    schema,
                                 };
-                                let schema = ::std::str::from_utf8(&q[b"USE ".len()..])
-                                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                                let schema = schema.trim().trim_end_matches(';').trim_matches('`');
                                 self.shim.on_init(schema, w).await?;
+                            } else if let Some(q) = ::std::str::from_utf8(q)
+                                .ok()
+                                .filter(|q| self.acknowledge_set_statements && variables::is_set(q))
+                            {
+                                if let Some(mode) = variables::sql_mode_set_by(q) {
+                                    self.session.sql_mode = mode;
+                                }
+                                writers::write_ok_packet(
+                                    &mut self.writer,
+                                    self.client_capabilities,
+                                    OkResponse::default(),
+                                )
+                                .await?;
                             } else {
+                                let q = ::std::str::from_utf8(q)
+                                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                                let show = schemas::Show::parse(q);
+                                let names = match show.as_ref().map(|s| &s.listing) {
+                                    Some(schemas::Listing::Databases) => {
+                                        self.shim.on_show_databases().await
+                                    }
+                                    Some(schemas::Listing::Tables { schema, .. }) => {
+                                        self.shim.on_show_tables(schema).await
+                                    }
+                                    None => None,
+                                };
+                                let answer =
+                                    show.zip(names).map(|(show, names)| show.answer(names));
                                 let w = QueryResultWriter::new(
                                     &mut self.writer,
                                     false,
                                     self.client_capabilities,
-                                );
-                                self.shim
-                                    .on_query(
-                                        ::std::str::from_utf8(q).map_err(|e| {
-                                            io::Error::new(io::ErrorKind::InvalidData, e)
-                                        })?,
-                                        w,
-                                    )
-                                    .await?;
+                                    &self.session,
+                                )
+                                .with_query_attributes(attrs)
+                                .with_max_size(self.max_result_set_size);
+                                if let Some((cols, rows)) = &answer {
+                                    w.write_table(cols, rows).await?;
+                                } else {
+                                    let timer =
+                                        self.query_observer.as_ref().map(|_| Timer::start());
+                                    let res =
+                                        timed(self.query_timeout, self.shim.on_query(q, w)).await;
+                                    let succeeded = matches!(res, Some(Ok(())));
+                                    observe(
+                                        &self.query_observer,
+                                        &self.session,
+                                        ObservedCommand::Query(q),
+                                        timer,
+                                        succeeded,
+                                    );
+                                    let sql_mode =
+                                        succeeded.then(|| variables::sql_mode_set_by(q)).flatten();
+                                    interrupt_if_timed_out(&mut self.writer, res).await?;
+                                    if let Some(mode) = sql_mode {
+                                        self.session.sql_mode = mode;
+                                    }
+                                }
                             }
                         }
                         Command::Prepare(q) => {
-                            let w = StatementMetaWriter {
-                                writer: &mut self.writer,
-                                stmts: &mut stmts,
-                                client_capabilities: self.client_capabilities,
-                            };
+                            let q = ::std::str::from_utf8(q)
+                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-                            self.shim
-                                .on_prepare(
-                                    ::std::str::from_utf8(q).map_err(|e| {
-                                        io::Error::new(io::ErrorKind::InvalidData, e)
-                                    })?,
-                                    w,
+                            // every statement the client prepared and has not closed counts,
+                            // including each time the same cached statement was prepared again
+                            let open: usize = stmts.values().map(|s| s.handles).sum();
+                            let full = self.max_prepared_statements.filter(|&max| open >= max);
+                            if let Some(max) = full {
+                                let msg = format!(
+                                    "Can't create more than max_prepared_stmt_count statements \
+                                     (current value: {})",
+                                    max
+                                );
+                                writers::write_err(
+                                    ErrorKind::ER_MAX_PREPARED_STMT_COUNT_REACHED,
+                                    msg.as_bytes(),
+                                    &mut self.writer,
+                                )
+                                .await?;
+                            } else if let Some(stmt) = stmt_cache.as_mut().and_then(|c| c.get(q)) {
+                                if let Some(state) = stmts.get_mut(&stmt.id) {
+                                    state.handles += 1;
+                                }
+                                writers::write_prepare_ok(
+                                    stmt.id,
+                                    &stmt.params,
+                                    &stmt.columns,
+                                    &mut self.writer,
+                                    self.client_capabilities,
+                                    self.session.charset,
                                 )
                                 .await?;
+                            } else {
+                                let w = StatementMetaWriter {
+                                    writer: &mut self.writer,
+                                    stmts: &mut stmts,
+                                    client_capabilities: self.client_capabilities,
+                                    session: &self.session,
+                                    cache: stmt_cache.as_mut().map(|c| (c, q)),
+                                };
+
+                                self.shim.on_prepare(q, w).await?;
+
+                                // statements pushed out of the cache that the client already
+                                // closed are now closed for good
+                                let evicted = stmt_cache
+                                    .as_mut()
+                                    .map(|c| c.take_evicted())
+                                    .unwrap_or_default();
+                                for stmt in evicted {
+                                    if stmts.get(&stmt).map_or(false, |s| s.handles == 0) {
+                                        self.shim.on_close(stmt).await;
+                                        stmts.remove(&stmt);
+                                    }
+                                }
+                            }
                         }
-                        Command::Execute { stmt, params } => {
+                        Command::Execute {
+                            stmt,
+                            flags,
+                            params,
+                        } => {
                             let state = stmts.get_mut(&stmt).ok_or_else(|| {
                                 io::Error::new(
                                     io::ErrorKind::InvalidData,
@@ -540,16 +1588,76 @@ where
                                 )
                             })?;
                             {
-                                let params = params::ParamParser::new(params, state);
+                                // https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_stmt_execute.html
+                                let params = if self
+                                    .client_capabilities
+                                    .contains(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES)
+                                    && (state.params > 0 || flags & PARAMETER_COUNT_AVAILABLE != 0)
+                                {
+                                    params::ParamParser::with_query_attributes(params, state)?
+                                } else {
+                                    params::ParamParser::new(params, state)
+                                };
+                                let attrs = params.query_attributes()?;
                                 let w = QueryResultWriter::new(
                                     &mut self.writer,
                                     true,
                                     self.client_capabilities,
+                                    &self.session,
+                                )
+                                .with_query_attributes(attrs)
+                                .with_max_size(self.max_result_set_size)
+                                .with_strict_types(self.strict_types);
+                                let timer = self.query_observer.as_ref().map(|_| Timer::start());
+                                let res = timed(
+                                    self.query_timeout,
+                                    self.shim.on_execute(stmt, params, w),
+                                )
+                                .await;
+                                observe(
+                                    &self.query_observer,
+                                    &self.session,
+                                    ObservedCommand::Execute(stmt),
+                                    timer,
+                                    matches!(res, Some(Ok(()))),
                                 );
-                                self.shim.on_execute(stmt, params, w).await?;
+                                interrupt_if_timed_out(&mut self.writer, res).await?;
                             }
                             state.long_data.clear();
                         }
+                        Command::BulkExecute {
+                            stmt,
+                            flags,
+                            params,
+                        } => {
+                            let state = stmts.get_mut(&stmt).ok_or_else(|| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("asked to bulk execute unknown statement {}", stmt),
+                                )
+                            })?;
+                            let rows = params::bulk_rows(params, flags, state)?;
+                            let w = QueryResultWriter::new(
+                                &mut self.writer,
+                                true,
+                                self.client_capabilities,
+                                &self.session,
+                            )
+                            .with_max_size(self.max_result_set_size)
+                            .with_strict_types(self.strict_types);
+                            let timer = self.query_observer.as_ref().map(|_| Timer::start());
+                            let res =
+                                timed(self.query_timeout, self.shim.on_bulk_execute(stmt, rows, w))
+                                    .await;
+                            observe(
+                                &self.query_observer,
+                                &self.session,
+                                ObservedCommand::Execute(stmt),
+                                timer,
+                                matches!(res, Some(Ok(()))),
+                            );
+                            interrupt_if_timed_out(&mut self.writer, res).await?;
+                        }
                         Command::SendLongData { stmt, param, data } => {
                             stmts
                                 .get_mut(&stmt)
@@ -568,10 +1676,40 @@ where
                                 .extend(data);
                         }
                         Command::Close(stmt) => {
-                            self.shim.on_close(stmt).await;
-                            stmts.remove(&stmt);
+                            // a statement stays open while it is cached, or was prepared again
+                            let keep = match stmts.get_mut(&stmt) {
+                                Some(state) => {
+                                    state.handles = state.handles.saturating_sub(1);
+                                    state.handles > 0
+                                        || stmt_cache
+                                            .as_ref()
+                                            .map_or(false, |c| c.contains_id(stmt))
+                                }
+                                None => false,
+                            };
+                            if !keep {
+                                self.shim.on_close(stmt).await;
+                                stmts.remove(&stmt);
+                            }
                             // NOTE: spec dictates no response from server
                         }
+                        Command::ResetConnection => {
+                            // resetting the connection closes all of its statements
+                            for (stmt, _) in stmts.drain() {
+                                self.shim.on_close(stmt).await;
+                            }
+                            if let Some(cache) = stmt_cache.as_mut() {
+                                cache.clear();
+                            }
+                            self.session.clone_from(&defaults);
+                            self.shim.on_reset_connection().await;
+                            writers::write_ok_packet(
+                                &mut self.writer,
+                                self.client_capabilities,
+                                OkResponse::default(),
+                            )
+                            .await?;
+                        }
                         Command::ListFields(_) => {
                             // mysql_list_fields (CommandByte::COM_FIELD_LIST / 0x04) has been deprecated in mysql 5.7
                             // and will be removed in a future version.
@@ -590,19 +1728,46 @@ where
                             .await?;
                         }
                         Command::Init(schema) => {
+                            let schema = ::std::str::from_utf8(schema)
+                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                             let w = InitWriter {
                                 client_capabilities: self.client_capabilities,
                                 writer: &mut self.writer,
+                                session: &mut self.session,
+                                schema,
                             };
-                            self.shim
-                                .on_init(
-                                    ::std::str::from_utf8(schema).map_err(|e| {
-                                        io::Error::new(io::ErrorKind::InvalidData, e)
-                                    })?,
-                                    w,
+                            self.shim.on_init(schema, w).await?;
+                        }
+                        Command::ProcessInfo => {
+                            let rows = self.shim.on_process_list().await;
+                            QueryResultWriter::new(
+                                &mut self.writer,
+                                false,
+                                self.client_capabilities,
+                                &self.session,
+                            )
+                            .write_rows(rows)
+                            .await?;
+                        }
+                        Command::ProcessKill(id) => match self.shim.on_kill(id).await {
+                            Ok(()) => {
+                                writers::write_ok_packet(
+                                    &mut self.writer,
+                                    self.client_capabilities,
+                                    OkResponse::default(),
                                 )
                                 .await?;
-                        }
+                            }
+                            Err(kind) => {
+                                let msg = match kind {
+                                    ErrorKind::ER_NO_SUCH_THREAD => {
+                                        format!("Unknown thread id: {}", id)
+                                    }
+                                    _ => format!("Could not kill thread {}", id),
+                                };
+                                writers::write_err(kind, msg.as_bytes(), &mut self.writer).await?;
+                            }
+                        },
                         Command::Ping => {
                             writers::write_ok_packet(
                                 &mut self.writer,
@@ -615,15 +1780,19 @@ where
                             break;
                         }
                     }
+                    self.finish_response().await?;
                     self.writer.flush_all().await?;
                 }
                 Err(_) => {
                     // if parser err, we need also stay the conn,
                     // because we can not support all command.
-                    writers::write_ok_packet(
+                    if let Some(&command) = packet.first() {
+                        self.shim.on_unknown_command(command);
+                    }
+                    writers::write_err(
+                        ErrorKind::ER_UNKNOWN_COM_ERROR,
+                        b"Unknown command",
                         &mut self.writer,
-                        self.client_capabilities,
-                        OkResponse::default(),
                     )
                     .await?;
                     self.writer.flush_all().await?;
@@ -0,0 +1,76 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of the traffic of a single connection, kept when
+/// [`IntermediaryOptions::metrics`](struct.IntermediaryOptions.html#structfield.metrics) is set.
+///
+/// They are available to the shim through
+/// [`SessionContext::metrics`](struct.SessionContext.html#method.metrics), and may be read at any
+/// time, e.g. from another task exporting them.
+#[derive(Debug, Default)]
+pub struct ConnectionMetrics {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    rows_sent: AtomicU64,
+    queries: AtomicU64,
+}
+
+impl ConnectionMetrics {
+    /// The bytes received from the client, including packet headers.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// The bytes sent to the client, including packet headers.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// The rows of resultsets sent to the client.
+    pub fn rows_sent(&self) -> u64 {
+        self.rows_sent.load(Ordering::Relaxed)
+    }
+
+    /// The queries and prepared statement executions handed to the shim.
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add_bytes_read(&self, n: usize) {
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_bytes_written(&self, n: usize) {
+        self.bytes_written.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_row_sent(&self) {
+        self.rows_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_query(&self) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// the counters of a connection are only equal to themselves
+impl PartialEq for ConnectionMetrics {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for ConnectionMetrics {}
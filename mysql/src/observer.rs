@@ -0,0 +1,102 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The command a [`QueryEvent`](struct.QueryEvent.html) reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObservedCommand<'a> {
+    /// A text protocol query (`COM_QUERY`) with its SQL text.
+    Query(&'a str),
+    /// An execution of the prepared statement with the given id (`COM_STMT_EXECUTE`).
+    Execute(u32),
+}
+
+/// What a [`QueryObserver`](struct.QueryObserver.html) is told once the shim has handled a
+/// command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryEvent<'a> {
+    /// The command that was handled.
+    pub command: ObservedCommand<'a>,
+    /// When the intermediary handed the command to the shim.
+    pub started: SystemTime,
+    /// How long the shim took to handle the command.
+    pub elapsed: Duration,
+    /// Whether the shim returned `Ok`. Errors sent to the client through the writers still count
+    /// as a success, only an `Err` from the shim (which also closes the connection) does not.
+    pub succeeded: bool,
+}
+
+/// A callback invoked after every `on_query` and `on_execute`, e.g. for slow-query logging or
+/// metrics, set through
+/// [`IntermediaryOptions::query_observer`](struct.IntermediaryOptions.html#structfield.query_observer).
+///
+/// Commands are not timed at all when no observer is set.
+#[derive(Clone)]
+pub struct QueryObserver(Arc<dyn Fn(&QueryEvent<'_>) + Send + Sync>);
+
+impl QueryObserver {
+    /// Create an observer calling `f` with each handled command.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&QueryEvent<'_>) + Send + Sync + 'static,
+    {
+        QueryObserver(Arc::new(f))
+    }
+
+    pub(crate) fn notify(&self, event: &QueryEvent<'_>) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for QueryObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("QueryObserver").finish()
+    }
+}
+
+// two observers are equal if they are clones of each other
+impl PartialEq for QueryObserver {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::as_ptr(&self.0) as *const u8 == Arc::as_ptr(&other.0) as *const u8
+    }
+}
+
+impl Eq for QueryObserver {}
+
+/// The start of a command being timed for a `QueryObserver`.
+pub(crate) struct Timer {
+    started: SystemTime,
+    instant: Instant,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Timer {
+            started: SystemTime::now(),
+            instant: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self, command: ObservedCommand<'_>, succeeded: bool) -> QueryEvent<'_> {
+        QueryEvent {
+            command,
+            started: self.started,
+            elapsed: self.instant.elapsed(),
+            succeeded,
+        }
+    }
+}
@@ -14,15 +14,21 @@
 
 use std::io;
 use std::io::prelude::*;
+use std::sync::Arc;
 
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
 
+use crate::metrics::ConnectionMetrics;
+
+/// Reads packets from `r`, buffering whatever the reads return until a whole packet, including
+/// its header, is available.
 pub struct PacketReader<R> {
     bytes: Vec<u8>,
     start: usize,
     remaining: usize,
     pub r: R,
+    pub(crate) metrics: Option<Arc<ConnectionMetrics>>,
 }
 
 impl<R> PacketReader<R> {
@@ -32,6 +38,7 @@ impl<R> PacketReader<R> {
             start: 0,
             remaining: 0,
             r,
+            metrics: None,
         }
     }
 }
@@ -131,6 +138,9 @@ impl<R: AsyncRead + Unpin> PacketReader<R> {
                 let buf = &mut self.bytes[end..];
                 self.r.read(buf).await?
             };
+            if let Some(metrics) = &self.metrics {
+                metrics.add_bytes_read(read);
+            }
             self.bytes.truncate(end + read);
             self.remaining = self.bytes.len();
 
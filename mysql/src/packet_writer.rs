@@ -16,7 +16,11 @@ use byteorder::{ByteOrder, LittleEndian};
 use std::io;
 use std::io::prelude::*;
 use std::io::IoSlice;
+use std::sync::Arc;
 
+use crate::metrics::ConnectionMetrics;
+use crate::myc::constants::StatusFlags;
+use crate::resultset::Unfinished;
 use crate::U24_MAX;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
@@ -27,6 +31,15 @@ use tokio::io::{AsyncWrite, AsyncWriteExt};
 pub struct PacketWriter<W> {
     packet_builder: PacketBuilder,
     output_stream: W,
+    /// the response a dropped writer left unfinished, see `Unfinished`
+    pub(crate) unfinished: Option<Unfinished>,
+    pub(crate) metrics: Option<Arc<ConnectionMetrics>>,
+    // the status flags added to every OK and EOF packet
+    pub(crate) server_status: StatusFlags,
+    // leave complete responses in the output stream's buffer until the command is done
+    pub(crate) defer_flush: bool,
+    // a packet was only partly written out, as the future writing it was dropped
+    pub(crate) partial_write: bool,
 }
 
 // exports the internal builder as sync Write
@@ -45,11 +58,26 @@ impl<W> PacketWriter<W> {
         Self {
             packet_builder: PacketBuilder::new(),
             output_stream,
+            unfinished: None,
+            metrics: None,
+            server_status: StatusFlags::empty(),
+            defer_flush: false,
+            partial_write: false,
         }
     }
     pub fn set_seq(&mut self, seq: u8) {
         self.packet_builder.set_seq(seq)
     }
+
+    /// Drop the packet being built without sending it
+    pub fn discard_packet(&mut self) {
+        self.packet_builder.take_buffer();
+    }
+
+    /// Size of the packet being built so far
+    pub fn packet_len(&self) -> usize {
+        self.packet_builder.buffer.len()
+    }
 }
 
 const PACKET_HEADER_SIZE: usize = 4;
@@ -60,9 +88,13 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
         if !builder.is_empty() {
             let raw_packet = builder.take_buffer();
 
-            // split the rww buffer at the boundary of size U24_MAX
-            let chunks = raw_packet.chunks(U24_MAX);
+            // split the rww buffer at the boundary of size U24_MAX; a payload that is an
+            // exact multiple of U24_MAX must be terminated by an empty packet, otherwise
+            // the client keeps waiting for the rest of it
+            let terminator = (raw_packet.len() % U24_MAX == 0).then(|| &raw_packet[..0]);
+            let chunks = raw_packet.chunks(U24_MAX).chain(terminator);
             let mut header = [0; PACKET_HEADER_SIZE];
+            self.partial_write = true;
             for chunk in chunks {
                 // prepare the header
                 LittleEndian::write_u24(&mut header, chunk.len() as u32);
@@ -88,7 +120,11 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
                         .collect();
                     self.output_stream.write_all(&remaining).await?
                 }
+                if let Some(metrics) = &self.metrics {
+                    metrics.add_bytes_written(PACKET_HEADER_SIZE + chunk.len());
+                }
             }
+            self.partial_write = false;
             Ok(())
         } else {
             Ok(())
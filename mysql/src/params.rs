@@ -13,10 +13,171 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::io;
+
+use byteorder::ReadBytesExt;
 
 use crate::myc;
+use crate::myc::io::ReadMysqlExt;
 use crate::{StatementData, Value};
 
+/// A query attribute attached by a client to a `COM_QUERY` or `COM_STMT_EXECUTE` command.
+///
+/// Clients only send query attributes when `CLIENT_QUERY_ATTRIBUTES` was negotiated, see
+/// [`IntermediaryOptions::query_attributes`](struct.IntermediaryOptions.html).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct QueryAttribute<'a> {
+    /// The name of this attribute.
+    pub name: &'a str,
+    /// The value provided for this attribute.
+    pub value: Value<'a>,
+    /// The column type assigned to this attribute.
+    pub coltype: myc::constants::ColumnType,
+}
+
+fn read_slice<'a>(input: &mut &'a [u8], len: u64) -> io::Result<&'a [u8]> {
+    if len > input.len() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "EOF while reading query attributes",
+        ));
+    }
+    let (bytes, rest) = input.split_at(len as usize);
+    *input = rest;
+    Ok(bytes)
+}
+
+/// Read the type, signedness and name of `count` parameters or query attributes.
+fn read_named_types<'a>(
+    input: &mut &'a [u8],
+    count: u64,
+) -> io::Result<Vec<(myc::constants::ColumnType, bool, &'a str)>> {
+    let mut types = Vec::new();
+    for _ in 0..count {
+        let coltype = input.read_u8()?;
+        let coltype = myc::constants::ColumnType::try_from(coltype).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("bad column type 0x{:x}: {}", coltype, e),
+            )
+        })?;
+        let unsigned = (input.read_u8()? & 128) != 0;
+        let len = input.read_lenenc_int()?;
+        let name = std::str::from_utf8(read_slice(input, len)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        types.push((coltype, unsigned, name));
+    }
+    Ok(types)
+}
+
+/// Split the query attributes off the front of a `COM_QUERY` payload, returning them together
+/// with the query itself.
+///
+/// https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_query.html
+pub(crate) fn split_query_attributes(
+    mut input: &[u8],
+) -> io::Result<(Vec<QueryAttribute<'_>>, &[u8])> {
+    let count = input.read_lenenc_int()?;
+    // parameter_set_count, which is always 1
+    input.read_lenenc_int()?;
+
+    let mut attrs = Vec::new();
+    if count == 0 {
+        return Ok((attrs, input));
+    }
+
+    let nullmap = read_slice(&mut input, (count + 7) / 8)?;
+    if input.read_u8()? != 0x01 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "query attributes sent without their types",
+        ));
+    }
+
+    let types = read_named_types(&mut input, count)?;
+    for (i, (coltype, unsigned, name)) in types.into_iter().enumerate() {
+        let value = if (nullmap[i / 8] & 1u8 << (i % 8)) != 0 {
+            Value::null()
+        } else {
+            Value::parse_from(&mut input, coltype, unsigned)?
+        };
+        attrs.push(QueryAttribute {
+            name,
+            value,
+            coltype,
+        });
+    }
+
+    Ok((attrs, input))
+}
+
+// the bulk execution flag telling that the parameter types precede the rows
+const BULK_SEND_TYPES_TO_SERVER: u16 = 128;
+
+/// Read the rows of parameters of a MariaDB bulk execution, each of which holds a parameter
+/// indicator and, unless the indicator says otherwise, a value for every parameter.
+///
+/// https://mariadb.com/kb/en/com_stmt_bulk_execute/
+pub(crate) fn bulk_rows<'a>(
+    mut input: &'a [u8],
+    flags: u16,
+    stmt: &mut StatementData,
+) -> io::Result<Vec<Vec<BulkParam<'a>>>> {
+    if stmt.params == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "bulk execution of a statement without parameters",
+        ));
+    }
+    if flags & BULK_SEND_TYPES_TO_SERVER != 0 {
+        stmt.bound_types.clear();
+        for _ in 0..stmt.params {
+            let coltype = input.read_u8()?;
+            let coltype = myc::constants::ColumnType::try_from(coltype).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad column type 0x{:x}: {}", coltype, e),
+                )
+            })?;
+            let unsigned = (input.read_u8()? & 128) != 0;
+            stmt.bound_types.push((coltype, unsigned));
+        }
+    } else if stmt.bound_types.len() != usize::from(stmt.params) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "bulk execution sent without parameter types",
+        ));
+    }
+
+    let mut rows = Vec::new();
+    while !input.is_empty() {
+        let mut row = Vec::with_capacity(stmt.bound_types.len());
+        for &(coltype, unsigned) in &stmt.bound_types {
+            let param = match input.read_u8()? {
+                0 => BulkParam::Value(ParamValue {
+                    value: Value::parse_from(&mut input, coltype, unsigned)?,
+                    coltype,
+                }),
+                1 => BulkParam::Value(ParamValue {
+                    value: Value::null(),
+                    coltype,
+                }),
+                2 => BulkParam::Default,
+                3 => BulkParam::Ignore,
+                indicator => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("bad bulk parameter indicator {}", indicator),
+                    ))
+                }
+            };
+            row.push(param);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
 /// A `ParamParser` decodes query parameters included in a client's `EXECUTE` command given
 /// type information for the expected parameters.
 ///
@@ -24,6 +185,8 @@ use crate::{StatementData, Value};
 /// provided parameters.
 pub struct ParamParser<'a> {
     pub(crate) params: u16,
+    pub(crate) count: u16,
+    pub(crate) named: bool,
     pub(crate) bytes: &'a [u8],
     pub(crate) long_data: &'a HashMap<u16, Vec<u8>>,
     pub(crate) bound_types: &'a mut Vec<(myc::constants::ColumnType, bool)>,
@@ -33,11 +196,80 @@ impl<'a> ParamParser<'a> {
     pub(crate) fn new(input: &'a [u8], stmt: &'a mut StatementData) -> Self {
         ParamParser {
             params: stmt.params,
+            count: stmt.params,
+            named: false,
             bytes: input,
             long_data: &stmt.long_data,
             bound_types: &mut stmt.bound_types,
         }
     }
+
+    /// Create a parser for the parameters of a client that negotiated `CLIENT_QUERY_ATTRIBUTES`.
+    ///
+    /// Such clients prefix the parameters with their count, which includes any query attributes
+    /// following the statement's own parameters, and send a name along with every type. The
+    /// query attributes themselves are skipped.
+    pub(crate) fn with_query_attributes(
+        mut input: &'a [u8],
+        stmt: &'a mut StatementData,
+    ) -> io::Result<Self> {
+        let count = input.read_lenenc_int()?;
+        if count < u64::from(stmt.params) || count > u64::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "got {} parameters for a statement with {} parameters",
+                    count, stmt.params
+                ),
+            ));
+        }
+        Ok(ParamParser {
+            count: count as u16,
+            named: true,
+            ..ParamParser::new(input, stmt)
+        })
+    }
+
+    /// Read the query attributes that follow the statement's own parameters.
+    ///
+    /// Attributes can only be read when the client sends the parameter types along with this
+    /// execution; otherwise, and for clients that did not negotiate `CLIENT_QUERY_ATTRIBUTES`,
+    /// no attributes are returned.
+    pub(crate) fn query_attributes(&self) -> io::Result<Vec<QueryAttribute<'a>>> {
+        let mut attrs = Vec::new();
+        if !self.named || self.count == self.params {
+            return Ok(attrs);
+        }
+
+        let mut input = self.bytes;
+        let nullmap = read_slice(&mut input, (u64::from(self.count) + 7) / 8)?;
+        if input.read_u8()? != 0x01 {
+            return Ok(attrs);
+        }
+
+        let types = read_named_types(&mut input, u64::from(self.count))?;
+        for (i, (coltype, unsigned, name)) in types.into_iter().enumerate() {
+            let null = (nullmap[i / 8] & 1u8 << (i % 8)) != 0;
+            let param = i < usize::from(self.params);
+            if param && (null || self.long_data.contains_key(&(i as u16))) {
+                // no bytes are sent for this parameter
+                continue;
+            }
+            let value = if null {
+                Value::null()
+            } else {
+                Value::parse_from(&mut input, coltype, unsigned)?
+            };
+            if !param {
+                attrs.push(QueryAttribute {
+                    name,
+                    value,
+                    coltype,
+                });
+            }
+        }
+        Ok(attrs)
+    }
 }
 
 impl<'a> IntoIterator for ParamParser<'a> {
@@ -46,6 +278,8 @@ impl<'a> IntoIterator for ParamParser<'a> {
     fn into_iter(self) -> Params<'a> {
         Params {
             params: self.params,
+            count: self.count,
+            named: self.named,
             input: self.bytes,
             nullmap: None,
             col: 0,
@@ -58,6 +292,8 @@ impl<'a> IntoIterator for ParamParser<'a> {
 /// An iterator over parameters provided by a client in an `EXECUTE` command.
 pub struct Params<'a> {
     params: u16,
+    count: u16,
+    named: bool,
     input: &'a [u8],
     nullmap: Option<&'a [u8]>,
     col: u16,
@@ -76,23 +312,40 @@ pub struct ParamValue<'a> {
 impl<'a> Iterator for Params<'a> {
     type Item = ParamValue<'a>;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            // neither a NULL bitmap nor types are sent, though some clients still send the
+            // new-params-bound flag
+            return None;
+        }
         if self.nullmap.is_none() {
-            let nullmap_len = (self.params as usize + 7) / 8;
+            let nullmap_len = (self.count as usize + 7) / 8;
             let (nullmap, rest) = self.input.split_at(nullmap_len);
             self.nullmap = Some(nullmap);
             self.input = rest;
 
+            // the new-params-bound flag precedes the values even when no types are sent
+            if !rest.is_empty() {
+                self.input = &rest[1..];
+            }
             if !rest.is_empty() && rest[0] != 0x00 {
-                let (typmap, rest) = rest[1..].split_at(2 * self.params as usize);
+                let mut rest = &rest[1..];
                 self.bound_types.clear();
-                for i in 0..self.params as usize {
-                    self.bound_types.push((
-                        myc::constants::ColumnType::try_from(typmap[2 * i as usize])
-                            .unwrap_or_else(|e| {
-                                panic!("bad column type 0x{:x}: {}", typmap[2 * i as usize], e)
+                for i in 0..self.count {
+                    let (typ, r) = rest.split_at(2);
+                    rest = r;
+                    if self.named {
+                        let len = rest.read_lenenc_int().unwrap();
+                        rest = &rest[len as usize..];
+                    }
+                    // query attributes trail the statement's own parameters
+                    if i < self.params {
+                        self.bound_types.push((
+                            myc::constants::ColumnType::try_from(typ[0]).unwrap_or_else(|e| {
+                                panic!("bad column type 0x{:x}: {}", typ[0], e)
                             }),
-                        (typmap[2 * i as usize + 1] & 128) != 0,
-                    ));
+                            (typ[1] & 128) != 0,
+                        ));
+                    }
                 }
                 self.input = rest;
             }
@@ -106,6 +359,7 @@ impl<'a> Iterator for Params<'a> {
         // https://web.archive.org/web/20170404144156/https://dev.mysql.com/doc/internals/en/null-bitmap.html
         // NULL-bitmap-byte = ((field-pos + offset) / 8)
         // NULL-bitmap-bit  = ((field-pos + offset) % 8)
+        // unlike for binary result rows, the offset is 0 for COM_STMT_EXECUTE
         if let Some(nullmap) = self.nullmap {
             let byte = self.col as usize / 8;
             if byte >= nullmap.len() {
@@ -134,3 +388,14 @@ impl<'a> Iterator for Params<'a> {
         })
     }
 }
+
+/// A parameter of one of the rows of a MariaDB bulk execution, see
+/// [`AsyncMysqlShim::on_bulk_execute`](trait.AsyncMysqlShim.html#method.on_bulk_execute).
+pub enum BulkParam<'a> {
+    /// The value provided for this parameter, which may be NULL.
+    Value(ParamValue<'a>),
+    /// The column's default value should be used, as with `DEFAULT` in an `INSERT`.
+    Default,
+    /// No value is provided, and the column should be left as it is, e.g. by an `UPDATE`.
+    Ignore,
+}
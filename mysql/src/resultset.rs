@@ -17,11 +17,16 @@ use std::collections::HashMap;
 use std::io::{self, Write};
 
 use byteorder::WriteBytesExt;
+use futures_util::stream::{Stream, StreamExt};
 use mysql_common::constants::{CapabilityFlags, ColumnFlags, StatusFlags};
 use tokio::io::AsyncWrite;
 
 use crate::packet_writer::PacketWriter;
-use crate::value::ToMysqlValue;
+use crate::params::QueryAttribute;
+use crate::row::MysqlRow;
+use crate::session::SessionContext;
+use crate::statement_cache::{CachedStatement, StatementCache};
+use crate::value::{Null, ToMysqlValue};
 use crate::{writers, OkResponse};
 use crate::{Column, ErrorKind, StatementData};
 
@@ -29,11 +34,22 @@ use crate::{Column, ErrorKind, StatementData};
 pub struct InitWriter<'a, W> {
     pub(crate) client_capabilities: CapabilityFlags,
     pub(crate) writer: &'a mut PacketWriter<W>,
+    pub(crate) session: &'a mut SessionContext,
+    pub(crate) schema: &'a str,
 }
 
 impl<'a, W: 'a + AsyncWrite + Unpin> InitWriter<'a, W> {
+    /// The session of the client changing its database.
+    pub fn session(&self) -> &SessionContext {
+        self.session
+    }
+
     /// Tell client that database context has been changed
+    ///
+    /// The database then becomes the session's
+    /// [`database`](struct.SessionContext.html#method.database).
     pub async fn ok(self) -> io::Result<()> {
+        self.session.database = Some(self.schema.to_owned());
         writers::write_ok_packet(self.writer, self.client_capabilities, OkResponse::default()).await
     }
 
@@ -41,12 +57,22 @@ impl<'a, W: 'a + AsyncWrite + Unpin> InitWriter<'a, W> {
     /// Although you can return any valid MySQL error code you probably want
     /// to keep it similar to the MySQL server and issue either a
     /// `ErrorKind::ER_BAD_DB_ERROR` or a `ErrorKind::ER_DBACCESS_DENIED_ERROR`.
+    ///
+    /// See [`unknown_database`](struct.InitWriter.html#method.unknown_database) for the former.
     pub async fn error<E>(self, kind: ErrorKind, msg: &E) -> io::Result<()>
     where
         E: Borrow<[u8]> + ?Sized,
     {
         writers::write_err(kind, msg.borrow(), self.writer).await
     }
+
+    /// Tell client that the database `schema` does not exist, with the error a MySQL server
+    /// sends, i.e. `ER_BAD_DB_ERROR` (1049) with SQLSTATE `42000` and the message
+    /// `Unknown database '<schema>'`.
+    pub async fn unknown_database(self, schema: &str) -> io::Result<()> {
+        let msg = format!("Unknown database '{}'", schema);
+        self.error(ErrorKind::ER_BAD_DB_ERROR, msg.as_bytes()).await
+    }
 }
 
 /// Convenience type for responding to a client `PREPARE` command.
@@ -59,9 +85,17 @@ pub struct StatementMetaWriter<'a, W> {
     pub(crate) writer: &'a mut PacketWriter<W>,
     pub(crate) stmts: &'a mut HashMap<u32, StatementData>,
     pub(crate) client_capabilities: CapabilityFlags,
+    pub(crate) session: &'a SessionContext,
+    // where to remember the reply to the query being prepared, if statements are cached
+    pub(crate) cache: Option<(&'a mut StatementCache, &'a str)>,
 }
 
 impl<'a, W: AsyncWrite + Unpin + 'a> StatementMetaWriter<'a, W> {
+    /// The session of the client preparing the statement.
+    pub fn session(&self) -> &SessionContext {
+        self.session
+    }
+
     /// Reply to the client with the given meta-information.
     ///
     /// `id` is a statement identifier that the client should supply when it later wants to execute
@@ -69,6 +103,10 @@ impl<'a, W: AsyncWrite + Unpin + 'a> StatementMetaWriter<'a, W> {
     /// parameters the client must provide when executing the prepared statement. `columns` is a
     /// second set of [`Column`](struct.Column.html) descriptors for the values that will be
     /// returned in each row then the statement is later executed.
+    ///
+    /// Clients may only look at this metadata and never execute the statement. When the columns
+    /// depend on the parameters, reply with a best guess: each execution sends its own column
+    /// definitions along with its rows, which clients go by.
     pub async fn reply<PI, CI>(self, id: u32, params: PI, columns: CI) -> io::Result<()>
     where
         PI: IntoIterator<Item = &'a Column>,
@@ -81,10 +119,39 @@ impl<'a, W: AsyncWrite + Unpin + 'a> StatementMetaWriter<'a, W> {
             id,
             StatementData {
                 params: params.len() as u16,
+                handles: 1,
                 ..Default::default()
             },
         );
-        writers::write_prepare_ok(id, params, columns, self.writer, self.client_capabilities).await
+
+        if let Some((cache, query)) = self.cache {
+            let stmt = CachedStatement {
+                id,
+                params: params.cloned().collect(),
+                columns: columns.into_iter().cloned().collect(),
+            };
+            writers::write_prepare_ok(
+                id,
+                &stmt.params,
+                &stmt.columns,
+                self.writer,
+                self.client_capabilities,
+                self.session.charset,
+            )
+            .await?;
+            cache.insert(query, stmt);
+            return Ok(());
+        }
+
+        writers::write_prepare_ok(
+            id,
+            params,
+            columns,
+            self.writer,
+            self.client_capabilities,
+            self.session.charset,
+        )
+        .await
     }
 
     /// Reply to the client's `PREPARE` with an error.
@@ -96,17 +163,49 @@ impl<'a, W: AsyncWrite + Unpin + 'a> StatementMetaWriter<'a, W> {
     }
 }
 
-enum Finalizer {
+pub(crate) enum Finalizer {
     Ok(OkResponse),
-    Eof,
+    Eof { warnings: u16 },
+}
+
+/// A response left unfinished by a writer that was dropped, e.g. because the handler returned
+/// early with `?`. The intermediary completes it once the handler returns, so that the client does
+/// not desync on the next command.
+pub(crate) enum Unfinished {
+    // nothing at all was sent
+    Nothing,
+    // the end of the last resultset was not sent
+    End(Finalizer),
+}
+
+pub(crate) async fn write_finalizer<W: AsyncWrite + Unpin>(
+    writer: &mut PacketWriter<W>,
+    client_capabilities: CapabilityFlags,
+    end: Finalizer,
+    more_exists: bool,
+) -> io::Result<()> {
+    let mut status = StatusFlags::empty();
+    if more_exists {
+        status.set(StatusFlags::SERVER_MORE_RESULTS_EXISTS, true);
+    }
+    match end {
+        Finalizer::Ok(mut ok_packet) => {
+            ok_packet.status_flags |= status;
+            writers::write_ok_packet(writer, client_capabilities, ok_packet).await
+        }
+        Finalizer::Eof { warnings } => writers::write_eof_packet(writer, status, warnings).await,
+    }
 }
 
 /// Convenience type for providing query results to clients.
 ///
 /// This type should not be dropped without calling
 /// [`start`](struct.QueryResultWriter.html#method.start),
+/// [`empty`](struct.QueryResultWriter.html#method.empty),
 /// [`completed`](struct.QueryResultWriter.html#method.completed), or
-/// [`error`](struct.QueryResultWriter.html#method.error).
+/// [`error`](struct.QueryResultWriter.html#method.error). If it is, e.g. because the handler
+/// returned early, the intermediary ends the last resultset once the handler returns, or replies
+/// with an error if nothing was sent at all.
 ///
 /// To send multiple resultsets, use
 /// [`RowWriter::finish_one`](struct.RowWriter.html#method.finish_one) and
@@ -121,7 +220,13 @@ pub struct QueryResultWriter<'a, W> {
     pub(crate) is_bin: bool,
     pub(crate) client_capabilities: CapabilityFlags,
     pub(crate) writer: &'a mut PacketWriter<W>,
+    session: &'a SessionContext,
+    query_attributes: Vec<QueryAttribute<'a>>,
+    max_size: Option<usize>,
+    strict_types: bool,
     last_end: Option<Finalizer>,
+    // the response was ended, with `no_more_results` or an error
+    done: bool,
 }
 
 impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
@@ -129,32 +234,75 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
         writer: &'a mut PacketWriter<W>,
         is_bin: bool,
         client_capabilities: CapabilityFlags,
+        session: &'a SessionContext,
     ) -> Self {
         QueryResultWriter {
             is_bin,
             client_capabilities,
             writer,
+            session,
+            query_attributes: Vec::new(),
+            max_size: None,
+            strict_types: false,
             last_end: None,
+            done: false,
         }
     }
 
+    pub(crate) fn with_query_attributes(
+        mut self,
+        query_attributes: Vec<QueryAttribute<'a>>,
+    ) -> Self {
+        self.query_attributes = query_attributes;
+        self
+    }
+
+    pub(crate) fn with_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub(crate) fn with_strict_types(mut self, strict_types: bool) -> Self {
+        self.strict_types = strict_types;
+        self
+    }
+
+    /// The session of the client issuing this query.
+    pub fn session(&self) -> &SessionContext {
+        self.session
+    }
+
+    /// The query attributes the client attached to this query.
+    ///
+    /// For executions of prepared statements these are the attributes following the bound
+    /// parameters, which are only available when the client sends the parameter types with the
+    /// execution. This is always empty unless
+    /// [`IntermediaryOptions::query_attributes`](struct.IntermediaryOptions.html) is set.
+    pub fn query_attributes(&self) -> &[QueryAttribute<'a>] {
+        &self.query_attributes
+    }
+
+    /// Whether rows are sent with the binary protocol, i.e. this is the response to the execution
+    /// of a prepared statement rather than to a text query.
+    pub fn is_binary(&self) -> bool {
+        self.is_bin
+    }
+
     async fn finalize(&mut self, more_exists: bool) -> io::Result<()> {
-        let mut status = StatusFlags::empty();
-        if more_exists {
-            status.set(StatusFlags::SERVER_MORE_RESULTS_EXISTS, true);
-        }
         match self.last_end.take() {
             None => Ok(()),
-            Some(Finalizer::Ok(ok_packet)) => {
-                writers::write_ok_packet(self.writer, self.client_capabilities, ok_packet).await
+            Some(end) => {
+                write_finalizer(self.writer, self.client_capabilities, end, more_exists).await
             }
-            Some(Finalizer::Eof) => writers::write_eof_packet(self.writer, status).await,
         }
     }
 
     /// Start a resultset response to the client that conforms to the given `columns`.
     ///
-    /// Note that if no columns are emitted, any written rows are ignored.
+    /// Note that if no columns are emitted, no resultset is sent at all: the response ends with an
+    /// OK packet, as it does for statements such as `CREATE TABLE`, and any written rows are
+    /// ignored. [`completed`](struct.QueryResultWriter.html#method.completed) sends such a
+    /// response more directly.
     ///
     /// See [`RowWriter`](struct.RowWriter.html).
     pub async fn start(mut self, columns: &'a [Column]) -> io::Result<RowWriter<'a, W>> {
@@ -162,6 +310,56 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
         RowWriter::new(self, columns).await
     }
 
+    /// Send a resultset response to the client that conforms to the given `columns`, but that
+    /// holds no rows.
+    ///
+    /// This is equivalent to calling [`start`](struct.QueryResultWriter.html#method.start)
+    /// followed immediately by [`RowWriter::finish`](struct.RowWriter.html#method.finish), and
+    /// terminates the resultset with either an EOF or an OK packet depending on whether the
+    /// client negotiated `CLIENT_DEPRECATE_EOF`.
+    pub async fn empty(self, columns: &'a [Column]) -> io::Result<()> {
+        self.start(columns).await?.finish().await
+    }
+
+    /// Send a resultset response to the client made of the given typed `rows`.
+    ///
+    /// The column definitions are taken from [`MysqlRow::columns`](trait.MysqlRow.html).
+    pub async fn write_rows<R, I>(self, rows: I) -> io::Result<()>
+    where
+        R: MysqlRow,
+        I: IntoIterator<Item = R>,
+    {
+        let columns = R::columns();
+        let mut w = self.start(&columns).await?;
+        for row in rows {
+            row.write_to(&mut w)?;
+            w.end_row().await?;
+        }
+        w.finish().await
+    }
+
+    /// Send a resultset response to the client that conforms to the given `columns` and holds all
+    /// of the given `rows`, e.g. a `Vec<Vec<_>>`.
+    ///
+    /// Each row must have exactly one value per column, or an error is returned. Values are
+    /// encoded with the protocol of the command being answered, as with
+    /// [`RowWriter::write_row`](struct.RowWriter.html#method.write_row).
+    pub async fn write_table<R, E>(
+        self,
+        columns: &'a [Column],
+        rows: impl IntoIterator<Item = R>,
+    ) -> io::Result<()>
+    where
+        R: IntoIterator<Item = E>,
+        E: ToMysqlValue,
+    {
+        let mut w = self.start(columns).await?;
+        for row in rows {
+            w.write_row(row).await?;
+        }
+        w.finish().await
+    }
+
     /// Send an empty resultset response to the client indicating that `rows` rows were affected by
     /// the query in this resultset. `last_insert_id` may be given to communiate an identifier for
     /// a client's most recent insertion.
@@ -182,18 +380,79 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
     }
 
     /// Reply to the client's query with an error.
+    ///
+    /// This may be called before anything else is sent, e.g. to deny a query the user is not
+    /// authorized to run with `ER_TABLEACCESS_DENIED_ERROR` (1142), in which case the client gets
+    /// nothing but the error.
     pub async fn error<E>(mut self, kind: ErrorKind, msg: &E) -> io::Result<()>
     where
         E: Borrow<[u8]> + ?Sized,
     {
+        self.done = true;
         self.finalize(true).await?;
-        writers::write_err(kind, msg.borrow(), self.writer).await
+        writers::write_err(kind, msg.borrow(), self.writer).await?;
+        self.flush().await
+    }
+
+    /// Reply to the client's query with the error a MySQL server would report for `err`, and the
+    /// message of `err`; see the conversion of `&io::Error` into
+    /// [`ErrorKind`](enum.ErrorKind.html).
+    pub async fn error_from_io(self, err: &io::Error) -> io::Result<()> {
+        self.error(ErrorKind::from(err), err.to_string().as_bytes())
+            .await
     }
 
     /// Send the last bits of the last resultset to the client, and indicate that there are no more
     /// resultsets coming.
+    ///
+    /// The response is then flushed to the client right away, unless
+    /// [`IntermediaryOptions::defer_flush`](struct.IntermediaryOptions.html#structfield.defer_flush)
+    /// is set.
     pub async fn no_more_results(mut self) -> io::Result<()> {
-        self.finalize(false).await
+        self.done = true;
+        self.finalize(false).await?;
+        self.flush().await
+    }
+
+    // the response is complete, so it is sent right away, unless flushes are deferred
+    async fn flush(&mut self) -> io::Result<()> {
+        if self.writer.defer_flush {
+            Ok(())
+        } else {
+            self.writer.flush_all().await
+        }
+    }
+}
+
+/// The error a MySQL server would report for an I/O error, e.g. one met while talking to an
+/// upstream: `ER_QUERY_INTERRUPTED` (1317) for a timeout, `ER_CONNECT_TO_FOREIGN_DATA_SOURCE`
+/// (1429) when the upstream cannot be reached, and `ER_UNKNOWN_ERROR` (1105) by default.
+impl From<&std::io::Error> for ErrorKind {
+    fn from(err: &std::io::Error) -> Self {
+        use std::io::ErrorKind as IoErrorKind;
+        match err.kind() {
+            IoErrorKind::TimedOut | IoErrorKind::Interrupted => ErrorKind::ER_QUERY_INTERRUPTED,
+            IoErrorKind::ConnectionRefused
+            | IoErrorKind::ConnectionReset
+            | IoErrorKind::ConnectionAborted
+            | IoErrorKind::NotConnected
+            | IoErrorKind::AddrNotAvailable => ErrorKind::ER_CONNECT_TO_FOREIGN_DATA_SOURCE,
+            IoErrorKind::BrokenPipe => ErrorKind::ER_NET_ERROR_ON_WRITE,
+            IoErrorKind::UnexpectedEof => ErrorKind::ER_NET_READ_ERROR,
+            IoErrorKind::NotFound => ErrorKind::ER_FILE_NOT_FOUND,
+            _ => ErrorKind::ER_UNKNOWN_ERROR,
+        }
+    }
+}
+
+impl<'a, W> Drop for QueryResultWriter<'a, W> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.writer.unfinished = Some(match self.last_end.take() {
+                Some(end) => Unfinished::End(end),
+                None => Unfinished::Nothing,
+            });
+        }
     }
 }
 
@@ -205,13 +464,13 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
 /// [`write_row`](struct.RowWriter.html#method.write_row)).
 ///
 /// This type *may* be dropped without calling
-/// [`write_row`](struct.RowWriter.html#method.write_row) or
-/// [`finish`](struct.RowWriter.html#method.finish). However, in this case, the program may panic
-/// if an I/O error occurs when sending the end-of-records marker to the client. To avoid this,
-/// call [`finish`](struct.RowWriter.html#method.finish) explicitly.
+/// [`finish`](struct.RowWriter.html#method.finish), e.g. when the handler returns early with `?`.
+/// In this case, a partially written row is discarded, and the intermediary sends the
+/// end-of-records marker to the client once the handler returns.
 #[must_use]
 pub struct RowWriter<'a, W: AsyncWrite + Unpin> {
     client_capabilities: CapabilityFlags,
+    is_bin: bool,
     result: Option<QueryResultWriter<'a, W>>,
     bitmap_len: usize,
     data: Vec<u8>,
@@ -220,6 +479,9 @@ pub struct RowWriter<'a, W: AsyncWrite + Unpin> {
     // next column to write for the current row
     // NOTE: (ab)used to track number of *rows* for a zero-column resultset
     col: usize,
+    warnings: u16,
+    // bytes of rows sent so far, only counted when the resultset size is limited
+    size: usize,
     finished: bool,
 }
 
@@ -233,14 +495,18 @@ where
     ) -> io::Result<RowWriter<'a, W>> {
         let bitmap_len = (columns.len() + 7 + 2) / 8;
         let client_capabilities = result.client_capabilities;
+        let is_bin = result.is_bin;
         let mut rw = RowWriter {
             client_capabilities,
+            is_bin,
             result: Some(result),
             columns,
             bitmap_len,
             data: Vec::new(),
 
             col: 0,
+            warnings: 0,
+            size: 0,
 
             finished: false,
         };
@@ -251,10 +517,12 @@ where
     #[inline]
     async fn start(&mut self) -> io::Result<()> {
         if !self.columns.is_empty() {
+            let result = self.result.as_mut().unwrap();
             writers::column_definitions(
                 self.columns,
-                self.result.as_mut().unwrap().writer,
+                result.writer,
                 self.client_capabilities,
+                result.session.charset,
             )
             .await?;
         }
@@ -266,13 +534,16 @@ where
     ///
     /// If you do not call [`end_row`](struct.RowWriter.html#method.end_row) after the last row,
     /// any errors that occur when writing out the last row will be returned by
-    /// [`finish`](struct.RowWriter.html#method.finish). If you do not call `finish` either, any
-    /// errors will cause a panic when the `RowWriter` is dropped.
+    /// [`finish`](struct.RowWriter.html#method.finish). If you do not call `finish` either, the
+    /// last row is discarded when the `RowWriter` is dropped.
     ///
     /// Note that the row *must* conform to the column specification provided to
     /// [`QueryResultWriter::start`](struct.QueryResultWriter.html#method.start). If it does not,
     /// this method will return an error indicating that an invalid value type or specification was
     /// provided.
+    ///
+    /// Writing NULL to a column flagged with `ColumnFlags::NOT_NULL_FLAG` is an error when using
+    /// the binary protocol.
     pub fn write_col<T>(&mut self, v: T) -> io::Result<()>
     where
         T: ToMysqlValue,
@@ -280,8 +551,23 @@ where
         if self.columns.is_empty() {
             return Ok(());
         }
+        if self.result.is_none() {
+            return Err(too_large());
+        }
+
+        let c = self
+            .columns
+            .get(self.col)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "row has more columns than specification",
+                )
+            })?
+            .borrow();
 
         if self.result.as_mut().unwrap().is_bin {
+            let strict = self.result.as_ref().unwrap().strict_types;
             if self.col == 0 {
                 self.result.as_mut().unwrap().writer.write_u8(0x00)?;
 
@@ -289,44 +575,60 @@ where
                 self.data.resize(self.bitmap_len, 0);
             }
 
-            let c = self
-                .columns
-                .get(self.col)
-                .ok_or_else(|| {
-                    io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        "row has more columns than specification",
-                    )
-                })?
-                .borrow();
             if v.is_null() {
                 if c.colflags.contains(ColumnFlags::NOT_NULL_FLAG) {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
-                        "given NULL value for NOT NULL column",
+                        format!("tried to write NULL to NOT NULL column {}", c.column),
                     ));
-                } else {
-                    // https://web.archive.org/web/20170404144156/https://dev.mysql.com/doc/internals/en/null-bitmap.html
-                    // NULL-bitmap-byte = ((field-pos + offset) / 8)
-                    // NULL-bitmap-bit  = ((field-pos + offset) % 8)
-                    self.data[(self.col + 2) / 8] |= 1u8 << ((self.col + 2) % 8);
                 }
+                // https://web.archive.org/web/20170404144156/https://dev.mysql.com/doc/internals/en/null-bitmap.html
+                // NULL-bitmap-byte = ((field-pos + offset) / 8)
+                // NULL-bitmap-bit  = ((field-pos + offset) % 8)
+                self.data[(self.col + 2) / 8] |= 1u8 << ((self.col + 2) % 8);
+            } else if strict {
+                v.to_mysql_bin_strict(&mut self.data, c)?;
             } else {
                 v.to_mysql_bin(&mut self.data, c)?;
             }
         } else {
-            v.to_mysql_text(self.result.as_mut().unwrap().writer)?;
+            v.to_mysql_text_with_column(self.result.as_mut().unwrap().writer, c)?;
         }
         self.col += 1;
         Ok(())
     }
 
+    /// Write NULL to the next column of the current row.
+    ///
+    /// This is the same as `write_col(Null)`, for rows built without a value at hand. With the
+    /// binary protocol, only the column's bit in the NULL bitmap is set and no value bytes are
+    /// written. Returns an error for columns flagged with `ColumnFlags::NOT_NULL_FLAG`.
+    pub fn write_null(&mut self) -> io::Result<()> {
+        if let Some(c) = self.columns.get(self.col) {
+            if c.colflags.contains(ColumnFlags::NOT_NULL_FLAG) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("tried to write NULL to NOT NULL column {}", c.column),
+                ));
+            }
+        }
+        self.write_col(Null)
+    }
+
     /// Indicate that no more column data will be written for the current row.
+    ///
+    /// If the row takes the resultset over
+    /// [`IntermediaryOptions::max_result_set_size`](struct.IntermediaryOptions.html#structfield.max_result_set_size),
+    /// it is dropped, the client is sent an error instead, and this and any further call on the
+    /// `RowWriter` return an error.
     pub async fn end_row(&mut self) -> io::Result<()> {
         if self.columns.is_empty() {
             self.col += 1;
             return Ok(());
         }
+        if self.result.is_none() {
+            return Err(too_large());
+        }
 
         if self.col != self.columns.len() {
             return Err(io::Error::new(
@@ -343,12 +645,81 @@ where
                 .write_all(&self.data[..])?;
             self.data.clear();
         }
-        self.result.as_mut().unwrap().writer.end_packet().await?;
+
+        let result = self.result.as_mut().unwrap();
+        if let Some(max_size) = result.max_size {
+            self.size += result.writer.packet_len();
+            if self.size > max_size {
+                return self.abort_too_large(max_size).await;
+            }
+        }
+
+        let result = self.result.as_mut().unwrap();
+        result.writer.end_packet().await?;
+        if let Some(metrics) = &result.session.metrics {
+            metrics.add_row_sent();
+        }
         self.col = 0;
 
         Ok(())
     }
 
+    /// Write `row`, the complete payload of a row packet already encoded for the protocol in use,
+    /// as the next row of this resultset.
+    ///
+    /// Only the packet header is added, so `row` must hold every column as the client expects
+    /// it: length-encoded strings with the text protocol, or the `0x00` header, the NULL bitmap and
+    /// the values with the binary protocol, see
+    /// [`is_binary`](struct.RowWriter.html#method.is_binary). This saves encoding each column of
+    /// rows received already encoded, e.g. from an upstream MySQL server.
+    pub async fn write_raw_row(&mut self, row: &[u8]) -> io::Result<()> {
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        if self.result.is_none() {
+            return Err(too_large());
+        }
+        if self.col != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cannot write a raw row while a row is being written",
+            ));
+        }
+
+        self.result.as_mut().unwrap().writer.write_all(row)?;
+        self.col = self.columns.len();
+        self.end_row().await
+    }
+
+    // Drop the row that went over the limit and fail the query. The error is flushed right away,
+    // as the caller is expected to give up on the connection.
+    async fn abort_too_large(&mut self, max_size: usize) -> io::Result<()> {
+        self.finished = true;
+        self.col = 0;
+
+        let mut result = self.result.take().unwrap();
+        result.done = true;
+        result.writer.discard_packet();
+        let msg = format!("Resultset exceeds the limit of {} bytes", max_size);
+        writers::write_err(ErrorKind::ER_OUTOFMEMORY, msg.as_bytes(), result.writer).await?;
+        result.writer.flush_all().await?;
+        Err(too_large())
+    }
+
+    /// Whether rows are sent with the binary protocol, see
+    /// [`QueryResultWriter::is_binary`](struct.QueryResultWriter.html#method.is_binary).
+    pub fn is_binary(&self) -> bool {
+        self.is_bin
+    }
+
+    /// Set the number of warnings reported to the client once this resultset is finished.
+    ///
+    /// This defaults to 0. To report warnings for a response without rows, set
+    /// [`OkResponse::warnings`](struct.OkResponse.html#structfield.warnings) instead.
+    pub fn set_warnings(&mut self, warnings: u16) {
+        self.warnings = warnings;
+    }
+
     /// Write a single row as a part of this resultset.
     ///
     /// Note that the row *must* conform to the column specification provided to
@@ -370,6 +741,25 @@ where
 }
 
 impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
+    /// Write every row yielded by `rows` as a part of this resultset.
+    ///
+    /// Rows are pulled from the stream one at a time, and each is sent to the client before the
+    /// next is polled, so that large results need not be buffered and a slow client slows down
+    /// the stream rather than letting rows pile up. The encoding follows the command being
+    /// answered, i.e., the binary protocol for prepared statements. Stops at the first error.
+    pub async fn write_row_stream<S, R, E>(&mut self, rows: S) -> io::Result<()>
+    where
+        S: Stream<Item = io::Result<R>>,
+        R: IntoIterator<Item = E>,
+        E: ToMysqlValue,
+    {
+        futures_util::pin_mut!(rows);
+        while let Some(row) = rows.next().await {
+            self.write_row(row?).await?;
+        }
+        Ok(())
+    }
+
     async fn finish_inner(&mut self, extra_info: &str, complete: bool) -> io::Result<()> {
         if self.finished {
             return Ok(());
@@ -382,39 +772,53 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
         }
 
         if complete {
-            if self.columns.is_empty() {
-                // response to no column query is always an OK packet
-                let resp = OkResponse {
-                    info: extra_info.to_string(),
-                    ..Default::default()
-                };
-                self.result.as_mut().unwrap().last_end = Some(Finalizer::Ok(resp));
-            } else if self
-                .client_capabilities
-                .contains(CapabilityFlags::CLIENT_DEPRECATE_EOF)
-            {
-                // response to no column query is always an OK packet
-                let resp = OkResponse {
-                    info: extra_info.to_string(),
-                    header: 0xfe,
-                    ..Default::default()
-                };
-                self.result.as_mut().unwrap().last_end = Some(Finalizer::Ok(resp));
-            } else {
-                // we wrote out at least one row
-                self.result.as_mut().unwrap().last_end = Some(Finalizer::Eof);
-            }
+            let end = self.end_marker(extra_info);
+            self.result.as_mut().unwrap().last_end = Some(end);
         }
 
         Ok(())
     }
 
+    fn end_marker(&self, extra_info: &str) -> Finalizer {
+        if self.columns.is_empty() {
+            // response to no column query is always an OK packet
+            let resp = OkResponse {
+                info: extra_info.to_string(),
+                warnings: self.warnings,
+                ..Default::default()
+            };
+            Finalizer::Ok(resp)
+        } else if self
+            .client_capabilities
+            .contains(CapabilityFlags::CLIENT_DEPRECATE_EOF)
+        {
+            // response to no column query is always an OK packet
+            let resp = OkResponse {
+                info: extra_info.to_string(),
+                header: 0xfe,
+                warnings: self.warnings,
+                ..Default::default()
+            };
+            Finalizer::Ok(resp)
+        } else {
+            // we wrote out at least one row
+            Finalizer::Eof {
+                warnings: self.warnings,
+            }
+        }
+    }
+
     /// Indicate to the client that no more rows are coming.
     pub async fn finish(self) -> io::Result<()> {
         self.finish_with_info("").await
     }
 
     /// End this resultset response, and indicate to the client that no more rows are coming.
+    ///
+    /// The returned writer may start another resultset. The terminator of this one is held back
+    /// until then, so that it carries `SERVER_MORE_RESULTS_EXISTS` if another resultset is started
+    /// and not if [`no_more_results`](struct.QueryResultWriter.html#method.no_more_results) is
+    /// called or the writer is dropped instead.
     pub async fn finish_one(self) -> io::Result<QueryResultWriter<'a, W>> {
         self.finish_one_with_info("").await
     }
@@ -436,7 +840,29 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
 
         // we know that dropping self will see self.finished == true,
         // and so Drop won't try to use self.result.
-        Ok(self.result.take().unwrap())
+        self.result.take().ok_or_else(too_large)
+    }
+
+    /// Abort this resultset response with an error, e.g. when the source of the rows fails after
+    /// some of them were already sent.
+    ///
+    /// Clients see the rows written so far followed by the error. Unlike
+    /// [`finish_error`](struct.RowWriter.html#method.finish_error), a partially written row is
+    /// discarded rather than sent.
+    pub async fn error<E>(mut self, kind: ErrorKind, msg: &E) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        self.finished = true;
+        let result = self.result.take().ok_or_else(too_large)?;
+
+        if !self.columns.is_empty() && self.col != 0 {
+            result.writer.discard_packet();
+            self.data.clear();
+            self.col = 0;
+        }
+
+        result.error(kind, msg).await
     }
 
     /// End this resultset response, and indicate to the client there was an error.
@@ -446,6 +872,70 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
     {
         self.finish_inner("", false).await?;
 
-        self.result.take().unwrap().error(kind, msg).await
+        self.result
+            .take()
+            .ok_or_else(too_large)?
+            .error(kind, msg)
+            .await
     }
 }
+
+/// Send a resultset response to the client that conforms to the given `columns` and holds every
+/// row yielded by `rows`.
+///
+/// The stream is driven to completion, with each row sent to the client as it arrives, as with
+/// [`RowWriter::write_row_stream`](struct.RowWriter.html#method.write_row_stream). If the stream
+/// yields an error, the rows sent so far are kept and the resultset is terminated with an
+/// `ER_UNKNOWN_ERROR` packet carrying the error's message; the `Result` returned only reflects
+/// failures to talk to the client.
+pub async fn write_stream<'a, W, S, R, V, E>(
+    writer: QueryResultWriter<'a, W>,
+    columns: &'a [Column],
+    rows: S,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + 'a,
+    S: Stream<Item = Result<R, E>>,
+    R: IntoIterator<Item = V>,
+    V: ToMysqlValue,
+    E: std::fmt::Display,
+{
+    let mut w = writer.start(columns).await?;
+    futures_util::pin_mut!(rows);
+    while let Some(row) = rows.next().await {
+        match row {
+            Ok(row) => w.write_row(row).await?,
+            Err(e) => {
+                return w
+                    .finish_error(ErrorKind::ER_UNKNOWN_ERROR, &e.to_string().into_bytes())
+                    .await
+            }
+        }
+    }
+    w.finish().await
+}
+
+impl<'a, W: AsyncWrite + Unpin> Drop for RowWriter<'a, W> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if !self.columns.is_empty() && self.col != 0 {
+            if let Some(result) = self.result.as_mut() {
+                result.writer.discard_packet();
+            }
+        }
+        let end = self.end_marker("");
+        if let Some(result) = self.result.as_mut() {
+            // handed on to the intermediary when the result is dropped in turn
+            result.last_end = Some(end);
+        }
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        "resultset was aborted for exceeding its size limit",
+    )
+}
@@ -0,0 +1,205 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use tokio::io::AsyncWrite;
+
+use crate::myc::constants::{ColumnFlags, ColumnType};
+use crate::value::ToMysqlValue;
+use crate::{Column, RowWriter};
+
+/// A typed row that knows both its column definitions and how to encode itself.
+///
+/// This is usually derived with `#[derive(MysqlRow)]` (behind the `derive` feature), which maps
+/// each named struct field to a column. A field's column type and flags are inferred through
+/// [`MysqlColumnType`](trait.MysqlColumnType.html), and can be overridden with
+/// `#[mysql(rename = "...", column_type = "...", unsigned)]`.
+///
+/// It is also implemented for tuples of up to 12 values, whose columns are named after their
+/// positions, i.e. `0`, `1` and so on.
+///
+/// Rows are sent with
+/// [`QueryResultWriter::write_rows`](struct.QueryResultWriter.html#method.write_rows).
+pub trait MysqlRow {
+    /// The column definitions for a resultset made of rows of this type.
+    fn columns() -> Vec<Column>;
+
+    /// Write the values of this row to `w`, one column at a time.
+    ///
+    /// This does not end the row; call [`RowWriter::end_row`](struct.RowWriter.html#method.end_row)
+    /// afterwards.
+    fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut RowWriter<'_, W>) -> io::Result<()>;
+}
+
+/// The MySQL column type that a Rust type is naturally encoded as.
+///
+/// Non-`Option` types are reported as `NOT NULL`, and unsigned integers carry the
+/// `UNSIGNED_FLAG`.
+pub trait MysqlColumnType {
+    /// The column type values of this type are encoded as.
+    fn column_type() -> ColumnType;
+
+    /// The column flags values of this type are encoded with.
+    fn column_flags() -> ColumnFlags {
+        ColumnFlags::NOT_NULL_FLAG
+    }
+}
+
+macro_rules! column_type {
+    ($t:ty, $ct:ident) => {
+        impl MysqlColumnType for $t {
+            fn column_type() -> ColumnType {
+                ColumnType::$ct
+            }
+        }
+    };
+    ($t:ty, $ct:ident, unsigned) => {
+        impl MysqlColumnType for $t {
+            fn column_type() -> ColumnType {
+                ColumnType::$ct
+            }
+            fn column_flags() -> ColumnFlags {
+                ColumnFlags::NOT_NULL_FLAG | ColumnFlags::UNSIGNED_FLAG
+            }
+        }
+    };
+}
+
+column_type!(i8, MYSQL_TYPE_TINY);
+column_type!(u8, MYSQL_TYPE_TINY, unsigned);
+column_type!(i16, MYSQL_TYPE_SHORT);
+column_type!(u16, MYSQL_TYPE_SHORT, unsigned);
+column_type!(i32, MYSQL_TYPE_LONG);
+column_type!(u32, MYSQL_TYPE_LONG, unsigned);
+column_type!(i64, MYSQL_TYPE_LONGLONG);
+column_type!(u64, MYSQL_TYPE_LONGLONG, unsigned);
+column_type!(f32, MYSQL_TYPE_FLOAT);
+column_type!(f64, MYSQL_TYPE_DOUBLE);
+column_type!(str, MYSQL_TYPE_VAR_STRING);
+column_type!(String, MYSQL_TYPE_VAR_STRING);
+column_type!([u8], MYSQL_TYPE_BLOB);
+column_type!(Vec<u8>, MYSQL_TYPE_BLOB);
+column_type!(NaiveDate, MYSQL_TYPE_DATE);
+column_type!(NaiveDateTime, MYSQL_TYPE_DATETIME);
+column_type!(NaiveTime, MYSQL_TYPE_TIME);
+column_type!(Duration, MYSQL_TYPE_TIME);
+
+impl<T: MysqlColumnType> MysqlColumnType for Option<T> {
+    fn column_type() -> ColumnType {
+        T::column_type()
+    }
+    fn column_flags() -> ColumnFlags {
+        T::column_flags() - ColumnFlags::NOT_NULL_FLAG
+    }
+}
+
+impl<'a, T: MysqlColumnType + ?Sized> MysqlColumnType for &'a T {
+    fn column_type() -> ColumnType {
+        T::column_type()
+    }
+    fn column_flags() -> ColumnFlags {
+        T::column_flags()
+    }
+}
+
+/// A row of the process list returned for `COM_PROCESS_INFO`, with the columns of
+/// `SHOW PROCESSLIST`.
+///
+/// See [`AsyncMysqlShim::on_process_list`](trait.AsyncMysqlShim.html#method.on_process_list).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProcessRow {
+    /// The connection id.
+    pub id: u64,
+    /// The user the connection authenticated as.
+    pub user: String,
+    /// The host the client connected from.
+    pub host: String,
+    /// The current database, if any.
+    pub db: Option<String>,
+    /// The command being executed, e.g. `Query` or `Sleep`.
+    pub command: String,
+    /// The number of seconds the connection has been in its current state.
+    pub time: u32,
+    /// What the connection is doing, if anything.
+    pub state: Option<String>,
+    /// The statement being executed, if any.
+    pub info: Option<String>,
+}
+
+fn typed_column<T: MysqlColumnType + ?Sized>(name: &str) -> Column {
+    Column {
+        table: String::new(),
+        column: name.to_owned(),
+        coltype: T::column_type(),
+        colflags: T::column_flags(),
+        ..Default::default()
+    }
+}
+
+impl MysqlRow for ProcessRow {
+    fn columns() -> Vec<Column> {
+        vec![
+            typed_column::<u64>("Id"),
+            typed_column::<String>("User"),
+            typed_column::<String>("Host"),
+            typed_column::<Option<String>>("db"),
+            typed_column::<String>("Command"),
+            typed_column::<u32>("Time"),
+            typed_column::<Option<String>>("State"),
+            typed_column::<Option<String>>("Info"),
+        ]
+    }
+
+    fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut RowWriter<'_, W>) -> io::Result<()> {
+        w.write_col(self.id)?;
+        w.write_col(&self.user)?;
+        w.write_col(&self.host)?;
+        w.write_col(self.db.as_deref())?;
+        w.write_col(&self.command)?;
+        w.write_col(self.time)?;
+        w.write_col(self.state.as_deref())?;
+        w.write_col(self.info.as_deref())
+    }
+}
+
+macro_rules! tuple_row {
+    ($($t:ident $i:tt),+) => {
+        impl<$($t: MysqlColumnType + ToMysqlValue),+> MysqlRow for ($($t,)+) {
+            fn columns() -> Vec<Column> {
+                vec![$(typed_column::<$t>(stringify!($i))),+]
+            }
+
+            fn write_to<W: AsyncWrite + Unpin>(&self, w: &mut RowWriter<'_, W>) -> io::Result<()> {
+                $(w.write_col(&self.$i)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_row!(A 0);
+tuple_row!(A 0, B 1);
+tuple_row!(A 0, B 1, C 2);
+tuple_row!(A 0, B 1, C 2, D 3);
+tuple_row!(A 0, B 1, C 2, D 3, E 4);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_row!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
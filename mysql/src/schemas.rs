@@ -0,0 +1,102 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::myc::constants::ColumnType;
+use crate::variables::{like, like_clause, strip_keyword};
+use crate::Column;
+
+/// What a `SHOW DATABASES` or `SHOW TABLES` query lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Listing<'a> {
+    Databases,
+    Tables { schema: &'a str, full: bool },
+}
+
+/// A `SHOW DATABASES` or `SHOW TABLES` query, answered from
+/// [`AsyncMysqlShim::on_show_databases`](trait.AsyncMysqlShim.html#method.on_show_databases) and
+/// [`AsyncMysqlShim::on_show_tables`](trait.AsyncMysqlShim.html#method.on_show_tables).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Show<'a> {
+    pub(crate) listing: Listing<'a>,
+    pattern: Option<&'a str>,
+}
+
+impl<'a> Show<'a> {
+    /// Recognizes `SHOW {DATABASES | SCHEMAS} [LIKE 'pattern']` and
+    /// `SHOW [FULL] TABLES {FROM | IN} schema [LIKE 'pattern']`.
+    pub(crate) fn parse(query: &'a str) -> Option<Self> {
+        let query = query.trim().trim_end_matches(';').trim_end();
+        let rest = strip_keyword(query, "SHOW")?;
+        if let Some(rest) =
+            strip_keyword(rest, "DATABASES").or_else(|| strip_keyword(rest, "SCHEMAS"))
+        {
+            return Some(Show {
+                listing: Listing::Databases,
+                pattern: like_clause(rest)?,
+            });
+        }
+
+        let (rest, full) = match strip_keyword(rest, "FULL") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let rest = strip_keyword(rest, "TABLES")?;
+        let rest = strip_keyword(rest, "FROM").or_else(|| strip_keyword(rest, "IN"))?;
+        let (schema, rest) = match rest.find(|c: char| c.is_ascii_whitespace()) {
+            Some(at) => (&rest[..at], rest[at..].trim_start()),
+            None => (rest, ""),
+        };
+        let schema = schema.trim_matches('`');
+        if schema.is_empty() {
+            return None;
+        }
+        Some(Show {
+            listing: Listing::Tables { schema, full },
+            pattern: like_clause(rest)?,
+        })
+    }
+
+    /// The columns and rows listing those of `names` that match the pattern, named as MySQL
+    /// names them, e.g. `Tables_in_db (a%)`.
+    pub(crate) fn answer(&self, names: Vec<String>) -> (Vec<Column>, Vec<Vec<String>>) {
+        let mut name = match self.listing {
+            Listing::Databases => "Database".to_owned(),
+            Listing::Tables { schema, .. } => format!("Tables_in_{}", schema),
+        };
+        if let Some(pattern) = self.pattern {
+            name = format!("{} ({})", name, pattern);
+        }
+
+        let mut cols = vec![Column::new(&name, ColumnType::MYSQL_TYPE_VAR_STRING).not_null()];
+        let full = matches!(self.listing, Listing::Tables { full: true, .. });
+        if full {
+            cols.push(Column::new("Table_type", ColumnType::MYSQL_TYPE_VAR_STRING).not_null());
+        }
+        let rows = names
+            .into_iter()
+            .filter(|n| {
+                self.pattern
+                    .map_or(true, |p| like(p.as_bytes(), n.as_bytes()))
+            })
+            .map(|n| {
+                if full {
+                    vec![n, "BASE TABLE".to_owned()]
+                } else {
+                    vec![n]
+                }
+            })
+            .collect();
+        (cols, rows)
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use byteorder::WriteBytesExt;
+
+use crate::metrics::ConnectionMetrics;
+use crate::myc::constants::{CapabilityFlags, SessionStateType, UTF8_GENERAL_CI};
+use crate::myc::io::WriteMysqlExt;
+use crate::statements;
+
+/// Information about the session of a connected client.
+///
+/// A reference to the session is available from the writers handed to the
+/// [`AsyncMysqlShim`](trait.AsyncMysqlShim.html) callbacks, e.g.
+/// [`QueryResultWriter::session`](struct.QueryResultWriter.html#method.session), so that new
+/// session-scoped information is added here rather than as new callback arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionContext {
+    pub(crate) connection_id: u32,
+    pub(crate) client_capabilities: CapabilityFlags,
+    pub(crate) charset: u16,
+    pub(crate) peer_addr: Option<SocketAddr>,
+    pub(crate) metrics: Option<Arc<ConnectionMetrics>>,
+    pub(crate) sql_mode: String,
+    pub(crate) database: Option<String>,
+}
+
+impl Default for SessionContext {
+    fn default() -> Self {
+        SessionContext {
+            connection_id: 0,
+            client_capabilities: CapabilityFlags::empty(),
+            charset: UTF8_GENERAL_CI,
+            peer_addr: None,
+            metrics: None,
+            sql_mode: String::new(),
+            database: None,
+        }
+    }
+}
+
+impl SessionContext {
+    /// The id of this connection, as given by
+    /// [`AsyncMysqlShim::connect_id`](trait.AsyncMysqlShim.html#method.connect_id) and sent to the
+    /// client in the handshake.
+    pub fn connection_id(&self) -> u32 {
+        self.connection_id
+    }
+
+    /// The capabilities negotiated with the client in its handshake response, e.g. to check for
+    /// `CLIENT_MULTI_STATEMENTS`.
+    pub fn client_capabilities(&self) -> CapabilityFlags {
+        self.client_capabilities
+    }
+
+    /// The collation id the client chose for this session in its handshake response, e.g.
+    /// `UTF8MB4_GENERAL_CI` (45).
+    ///
+    /// Columns whose [`charset`](struct.Column.html#structfield.charset) is left at 0 are sent
+    /// with this collation.
+    pub fn charset(&self) -> u16 {
+        self.charset
+    }
+
+    /// The remote address of the connected client, e.g. for audit logging or to limit
+    /// connections by IP.
+    ///
+    /// This is only known when the server was started with
+    /// [`AsyncMysqlIntermediary::run_with_peer_addr`](struct.AsyncMysqlIntermediary.html#method.run_with_peer_addr),
+    /// and is `None` otherwise, e.g. for Unix sockets.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// The counters of this connection, if
+    /// [`IntermediaryOptions::metrics`](struct.IntermediaryOptions.html#structfield.metrics) is
+    /// set.
+    pub fn metrics(&self) -> Option<&Arc<ConnectionMetrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// The SQL mode of this session, e.g. `ANSI_QUOTES,NO_BACKSLASH_ESCAPES`.
+    ///
+    /// This starts out as the `sql_mode` of
+    /// [`IntermediaryOptions::system_variables`](struct.IntermediaryOptions.html#structfield.system_variables),
+    /// if any, and follows the `SET [SESSION] sql_mode = '...'` queries that
+    /// [`AsyncMysqlShim::on_query`](trait.AsyncMysqlShim.html#tymethod.on_query) answers without
+    /// returning an error.
+    pub fn sql_mode(&self) -> &str {
+        &self.sql_mode
+    }
+
+    /// The current database of this session, as `DATABASE()` returns it, or `None` if there is
+    /// none.
+    ///
+    /// This is the last database the client switched to, whether by naming it when connecting,
+    /// with `COM_INIT_DB` or with `USE`, that
+    /// [`AsyncMysqlShim::on_init`](trait.AsyncMysqlShim.html#method.on_init) accepted with
+    /// [`InitWriter::ok`](struct.InitWriter.html#method.ok).
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    /// Whether the `NO_BACKSLASH_ESCAPES` SQL mode is set, making backslashes in string literals
+    /// plain characters rather than escapes.
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.sql_mode
+            .split(',')
+            .any(|mode| mode.trim().eq_ignore_ascii_case("NO_BACKSLASH_ESCAPES"))
+    }
+
+    /// Split `query` into its `;`-separated statements, trimmed, leaving out empty ones, e.g. to
+    /// run the queries of clients that set `CLIENT_MULTI_STATEMENTS` one by one.
+    ///
+    /// Semicolons within strings, quoted identifiers and comments do not end a statement, and
+    /// whether backslashes escape quotes in strings follows
+    /// [`no_backslash_escapes`](struct.SessionContext.html#method.no_backslash_escapes).
+    pub fn split_statements<'a>(&self, query: &'a str) -> Vec<&'a str> {
+        statements::split(query, self.no_backslash_escapes())
+    }
+}
+
+/// A change of the session state, reported to the client in an OK packet through
+/// [`OkResponse::session_state_changes`](struct.OkResponse.html#structfield.session_state_changes).
+///
+/// Changes are only sent to clients that negotiated `CLIENT_SESSION_TRACK`, see
+/// [`IntermediaryOptions::session_track`](struct.IntermediaryOptions.html#structfield.session_track).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStateChange {
+    /// A tracked system variable was set to a new value.
+    SystemVariable {
+        /// The name of the variable, e.g. `autocommit`.
+        name: String,
+        /// The new value of the variable.
+        value: String,
+    },
+    /// The current schema changed.
+    Schema(String),
+    /// Some other part of the session state changed, e.g. a user variable or a temporary table.
+    StateChange,
+    /// The GTIDs of the transactions committed by the statement, e.g.
+    /// `3E11FA47-71CA-11E1-9E33-C80AA9429562:23`.
+    Gtids(String),
+    /// The statements that restart the current transaction with the same characteristics, e.g.
+    /// `SET TRANSACTION READ ONLY; START TRANSACTION;`.
+    TransactionCharacteristics(String),
+    /// The 8 character state of the current transaction, e.g. `T_______`.
+    TransactionState(String),
+}
+
+impl SessionStateChange {
+    /// Write this change as a `type, lenenc data` entry of the session state info.
+    pub(crate) fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut data = Vec::new();
+        let ty = match self {
+            SessionStateChange::SystemVariable { name, value } => {
+                data.write_lenenc_str(name.as_bytes())?;
+                data.write_lenenc_str(value.as_bytes())?;
+                SessionStateType::SESSION_TRACK_SYSTEM_VARIABLES
+            }
+            SessionStateChange::Schema(schema) => {
+                data.write_lenenc_str(schema.as_bytes())?;
+                SessionStateType::SESSION_TRACK_SCHEMA
+            }
+            SessionStateChange::StateChange => {
+                data.write_lenenc_str(b"1")?;
+                SessionStateType::SESSION_TRACK_STATE_CHANGE
+            }
+            SessionStateChange::Gtids(gtids) => {
+                // encoding specification, 0 is the only one defined
+                data.write_u8(0)?;
+                data.write_lenenc_str(gtids.as_bytes())?;
+                SessionStateType::SESSION_TRACK_GTIDS
+            }
+            SessionStateChange::TransactionCharacteristics(chistics) => {
+                data.write_lenenc_str(chistics.as_bytes())?;
+                SessionStateType::SESSION_TRACK_TRANSACTION_CHARACTERISTICS
+            }
+            SessionStateChange::TransactionState(state) => {
+                data.write_lenenc_str(state.as_bytes())?;
+                SessionStateType::SESSION_TRACK_TRANSACTION_STATE
+            }
+        };
+        w.write_u8(ty as u8)?;
+        w.write_lenenc_str(&data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::Column;
+
+/// The metadata `on_prepare` replied with for a statement.
+pub(crate) struct CachedStatement {
+    pub(crate) id: u32,
+    pub(crate) params: Vec<Column>,
+    pub(crate) columns: Vec<Column>,
+}
+
+/// A least recently used cache of prepared statements keyed by their SQL, so that preparing the
+/// same SQL again reuses the open statement instead of calling `on_prepare`.
+///
+/// Cached statements are kept open even once the client closed them, until they are evicted.
+pub(crate) struct StatementCache {
+    capacity: usize,
+    // incremented on every use, the entry with the lowest tick is the least recently used
+    tick: u64,
+    entries: HashMap<String, (u64, CachedStatement)>,
+    // ids of the statements evicted since the last `take_evicted`
+    evicted: Vec<u32>,
+}
+
+impl StatementCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        StatementCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            evicted: Vec::new(),
+        }
+    }
+
+    fn key(query: &str) -> &str {
+        query.trim()
+    }
+
+    pub(crate) fn get(&mut self, query: &str) -> Option<&CachedStatement> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(Self::key(query)).map(|(used, stmt)| {
+            *used = tick;
+            &*stmt
+        })
+    }
+
+    pub(crate) fn insert(&mut self, query: &str, stmt: CachedStatement) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(Self::key(query)) {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(query, _)| query.clone());
+            if let Some((_, stmt)) = lru.and_then(|lru| self.entries.remove(&lru)) {
+                self.evicted.push(stmt.id);
+            }
+        }
+
+        self.tick += 1;
+        self.entries
+            .insert(Self::key(query).to_owned(), (self.tick, stmt));
+    }
+
+    pub(crate) fn contains_id(&self, id: u32) -> bool {
+        self.entries.values().any(|(_, stmt)| stmt.id == id)
+    }
+
+    pub(crate) fn take_evicted(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.evicted.clear();
+    }
+}
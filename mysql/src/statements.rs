@@ -0,0 +1,77 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Split `query` into its `;`-separated statements, trimmed, leaving out empty ones.
+///
+/// Semicolons within quoted strings and identifiers, and within comments, do not end a statement.
+/// Unless `no_backslash_escapes` is set, as it is by the `NO_BACKSLASH_ESCAPES` SQL mode, a
+/// backslash escapes the character following it within strings.
+pub(crate) fn split(query: &str, no_backslash_escapes: bool) -> Vec<&str> {
+    let bytes = query.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' if quote != b'`' && !no_backslash_escapes => i += 1,
+                        // a doubled quote stands for the quote itself
+                        c if c == quote && bytes.get(i + 1) == Some(&quote) => i += 1,
+                        c if c == quote => break,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            b'#' => {
+                i = line_end(bytes, i);
+            }
+            b'-' if bytes[i..].starts_with(b"--")
+                && bytes.get(i + 2).map_or(true, |c| c.is_ascii_whitespace()) =>
+            {
+                i = line_end(bytes, i);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = match query[i + 2..].find("*/") {
+                    Some(end) => i + 2 + end + 1,
+                    None => bytes.len(),
+                };
+            }
+            b';' => {
+                statements.push(&query[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    statements.push(&query[start..]);
+
+    statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// the index of the newline ending the line `i` is on, or the end of `bytes`
+fn line_end(bytes: &[u8], i: usize) -> usize {
+    bytes[i..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(bytes.len(), |end| i + end)
+}
@@ -0,0 +1,98 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{columns, Column, ColumnFlags, ColumnType};
+
+#[test]
+fn columns_macro_matches_manual_construction() {
+    let cols = columns![
+        ("id", LONG, unsigned, primary_key, auto_increment),
+        ("name", VAR_STRING, table("users"), not_null),
+        ("note", BLOB),
+    ];
+    assert_eq!(
+        cols,
+        vec![
+            Column {
+                table: String::new(),
+                column: "id".to_owned(),
+                coltype: ColumnType::MYSQL_TYPE_LONG,
+                colflags: ColumnFlags::UNSIGNED_FLAG
+                    | ColumnFlags::PRI_KEY_FLAG
+                    | ColumnFlags::NOT_NULL_FLAG
+                    | ColumnFlags::AUTO_INCREMENT_FLAG,
+                ..Default::default()
+            },
+            Column {
+                table: "users".to_owned(),
+                column: "name".to_owned(),
+                coltype: ColumnType::MYSQL_TYPE_VAR_STRING,
+                colflags: ColumnFlags::NOT_NULL_FLAG,
+                ..Default::default()
+            },
+            Column {
+                table: String::new(),
+                column: "note".to_owned(),
+                coltype: ColumnType::MYSQL_TYPE_BLOB,
+                colflags: ColumnFlags::empty(),
+                ..Default::default()
+            },
+        ]
+    );
+}
+
+#[test]
+fn columns_macro_empty() {
+    let cols: Vec<Column> = columns![];
+    assert!(cols.is_empty());
+}
+
+#[test]
+fn column_length_bytes_per_charset() {
+    let col = |charset| Column {
+        coltype: ColumnType::MYSQL_TYPE_VAR_STRING,
+        column_length: Some(10),
+        charset,
+        ..Default::default()
+    };
+    // binary, utf8, utf8mb4, utf32_general_ci, utf32_bin, utf32_unicode_520_ci,
+    // utf32_vietnamese_ci and ucs2_general_mysql500_ci
+    for (charset, bytes) in [
+        (63, 10),
+        (33, 30),
+        (45, 40),
+        (60, 40),
+        (61, 40),
+        (182, 40),
+        (183, 40),
+        (159, 20),
+    ] {
+        assert_eq!(col(charset).column_length_bytes(), bytes, "{}", charset);
+    }
+}
+
+#[test]
+fn column_length_bytes_of_decimal() {
+    let col = |decimals, colflags| Column {
+        coltype: ColumnType::MYSQL_TYPE_NEWDECIMAL,
+        column_length: Some(10),
+        decimals,
+        colflags,
+        ..Default::default()
+    };
+    assert_eq!(col(2, ColumnFlags::empty()).column_length_bytes(), 12);
+    assert_eq!(col(2, ColumnFlags::UNSIGNED_FLAG).column_length_bytes(), 11);
+    assert_eq!(col(0, ColumnFlags::empty()).column_length_bytes(), 11);
+    assert_eq!(col(0, ColumnFlags::UNSIGNED_FLAG).column_length_bytes(), 10);
+}
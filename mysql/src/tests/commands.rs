@@ -88,3 +88,92 @@ fn it_handles_list_fields() {
         Command::ListFields(&b"select @@version_comment limit 1"[..])
     );
 }
+
+#[test]
+fn it_parses_process_commands() {
+    assert_eq!(parse(&[0x0a]).unwrap().1, Command::ProcessInfo);
+    assert_eq!(
+        parse(&[0x0c, 0x2a, 0x00, 0x00, 0x00]).unwrap().1,
+        Command::ProcessKill(42)
+    );
+}
+
+#[test]
+fn it_parses_bulk_execute() {
+    assert_eq!(
+        parse(&[0xfa, 0x01, 0x00, 0x00, 0x00, 0x80, 0x00, 0x08, 0x00])
+            .unwrap()
+            .1,
+        Command::BulkExecute {
+            stmt: 1,
+            flags: 0x80,
+            params: &[0x08, 0x00],
+        }
+    );
+}
+
+#[test]
+fn it_rejects_truncated_handshakes() {
+    let with_db = CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH
+        | CapabilityFlags::CLIENT_CONNECT_WITH_DB;
+    let mut response41 = with_db.bits().to_le_bytes().to_vec();
+    response41.extend(16_777_216u32.to_le_bytes());
+    response41.push(0x21);
+    response41.extend([0u8; 23]);
+    response41.extend(b"root\0");
+    response41.push(4);
+    response41.extend(b"auth");
+    response41.extend(b"db\0");
+    response41.extend(b"mysql_native_password\0");
+
+    let lenenc = with_db | CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA;
+    let mut response41_lenenc = lenenc.bits().to_le_bytes().to_vec();
+    response41_lenenc.extend(16_777_216u32.to_le_bytes());
+    response41_lenenc.push(0x21);
+    response41_lenenc.extend([0u8; 23]);
+    response41_lenenc.extend(b"root\0");
+    // a length of 2^64 - 1 bytes
+    response41_lenenc.push(0xfe);
+    response41_lenenc.extend([0xff; 8]);
+
+    let mut response320 = (CapabilityFlags::CLIENT_CONNECT_WITH_DB.bits() as u16)
+        .to_le_bytes()
+        .to_vec();
+    response320.extend([0xff, 0xff, 0xff]);
+    response320.extend(b"root\0auth\0db\0");
+
+    let (_, handshake) = client_handshake(&response41).unwrap();
+    assert_eq!(handshake.username, b"root");
+    assert_eq!(handshake.auth_response, b"auth");
+    assert_eq!(handshake.db.as_deref(), Some(&b"db"[..]));
+    assert_eq!(handshake.auth_plugin, b"mysql_native_password");
+
+    let (_, handshake) = client_handshake(&response320).unwrap();
+    assert_eq!(handshake.username, b"root");
+    assert_eq!(handshake.auth_response, b"auth");
+    assert_eq!(handshake.db.as_deref(), Some(&b"db"[..]));
+
+    assert!(client_handshake(&response41_lenenc).is_err());
+
+    // a response may end after any of the optional trailing fields, but never inside a field
+    let ends = |response: &[u8]| {
+        (0..response.len())
+            .filter(|&len| client_handshake(&response[..len]).is_ok())
+            .collect::<Vec<_>>()
+    };
+    // after the auth response, and after the database name
+    assert_eq!(ends(&response41), vec![42, 45]);
+    assert_eq!(ends(&response320), Vec::<usize>::new());
+
+    for garbage in [
+        &[][..],
+        &[0xff][..],
+        &[0xff, 0xff, 0xff, 0xff][..],
+        &[0xff; 36][..],
+        &[0x00, 0xa2, 0xff, 0xff][..],
+    ] {
+        assert!(client_handshake(garbage).is_err(), "accepted {:?}", garbage);
+    }
+}
@@ -12,6 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod column;
 mod commands;
 mod packet;
+mod params;
+mod resultset;
+mod schemas;
+mod session;
+mod statement_cache;
+mod statements;
 mod value;
+mod variables;
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use crate::packet_reader::*;
+use crate::packet_writer::PacketWriter;
 use crate::U24_MAX;
 
 #[test]
@@ -63,3 +64,71 @@ fn test_long_more() {
     assert_eq!(&p.1[..U24_MAX], &[0; U24_MAX][..]);
     assert_eq!(&p.1[U24_MAX..], &[0x10]);
 }
+
+#[tokio::test]
+async fn test_write_long_exact() {
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    std::io::Write::write_all(&mut w, &vec![0; U24_MAX]).unwrap();
+    w.end_packet().await.unwrap();
+
+    let (rest, p) = packet(&out[..]).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(p.0, 1);
+    assert_eq!(p.1.len(), U24_MAX);
+    assert_eq!(&out[out.len() - 4..], &[0x00, 0x00, 0x00, 1]);
+}
+
+#[tokio::test]
+async fn test_write_long_more() {
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    std::io::Write::write_all(&mut w, &vec![0; U24_MAX]).unwrap();
+    std::io::Write::write_all(&mut w, &[0x10]).unwrap();
+    w.end_packet().await.unwrap();
+
+    let (rest, p) = packet(&out[..]).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(p.0, 1);
+    assert_eq!(p.1.len(), U24_MAX + 1);
+    assert_eq!(&p.1[U24_MAX..], &[0x10]);
+}
+
+// delivers a single byte per read, as a slow link or a TLS record boundary may
+struct OneByteAtATime<'a>(&'a [u8]);
+
+impl<'a> tokio::io::AsyncRead for OneByteAtATime<'a> {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        if let Some((&b, rest)) = self.0.split_first() {
+            buf.put_slice(&[b]);
+            self.0 = rest;
+        }
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_read_one_byte_at_a_time() {
+    let query = b"SELECT a, b FROM t WHERE c = 'split across reads'";
+    let mut data = vec![query.len() as u8 + 1, 0, 0, 0, 0x03];
+    data.extend(query);
+    data.extend([0x01, 0, 0, 0, 0x0e]);
+
+    let mut r = PacketReader::new(OneByteAtATime(&data));
+    {
+        let (seq, p) = r.next_async().await.unwrap().unwrap();
+        assert_eq!(seq, 0);
+        assert_eq!(p[0], 0x03);
+        assert_eq!(&p[1..], &query[..]);
+    }
+    {
+        let (seq, p) = r.next_async().await.unwrap().unwrap();
+        assert_eq!(seq, 0);
+        assert_eq!(&*p, &[0x0e]);
+    }
+    assert!(r.next_async().await.unwrap().is_none());
+}
@@ -0,0 +1,236 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::myc::constants::ColumnType;
+use crate::params::{bulk_rows, split_query_attributes, BulkParam, ParamParser};
+use crate::{StatementData, ValueInner};
+
+#[test]
+fn it_splits_query_attributes() {
+    let mut data = vec![0x01, 0x01, 0x00, 0x01, 0xfd, 0x00, 0x0b];
+    data.extend(b"traceparent");
+    data.push(0x06);
+    data.extend(b"00-abc");
+    data.extend(b"SELECT 1");
+
+    let (attrs, query) = split_query_attributes(&data).unwrap();
+    assert_eq!(query, b"SELECT 1");
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].name, "traceparent");
+    assert_eq!(attrs[0].coltype, ColumnType::MYSQL_TYPE_VAR_STRING);
+    assert_eq!(
+        attrs[0].value.into_inner(),
+        ValueInner::Bytes(&b"00-abc"[..])
+    );
+}
+
+#[test]
+fn it_splits_null_query_attributes() {
+    let mut data = vec![0x01, 0x01, 0x01, 0x01, 0xfd, 0x00, 0x01, b'a'];
+    data.extend(b"SELECT 1");
+
+    let (attrs, query) = split_query_attributes(&data).unwrap();
+    assert_eq!(query, b"SELECT 1");
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].name, "a");
+    assert!(attrs[0].value.is_null());
+}
+
+#[test]
+fn it_splits_no_query_attributes() {
+    let mut data = vec![0x00, 0x01];
+    data.extend(b"SELECT 1");
+
+    let (attrs, query) = split_query_attributes(&data).unwrap();
+    assert!(attrs.is_empty());
+    assert_eq!(query, b"SELECT 1");
+}
+
+#[test]
+fn it_rejects_truncated_query_attributes() {
+    let data = [0x01, 0x01, 0x00, 0x01, 0xfd, 0x00, 0x0b, b't'];
+    assert!(split_query_attributes(&data).is_err());
+}
+
+#[test]
+fn it_skips_execute_query_attributes() {
+    let mut stmt = StatementData {
+        params: 1,
+        ..Default::default()
+    };
+    // one parameter and one query attribute, both typed and named
+    let mut data = vec![0x02, 0x00, 0x01, 0x08, 0x00, 0x00, 0xfd, 0x00, 0x02];
+    data.extend(b"id");
+    data.extend(42i64.to_le_bytes());
+    data.extend([0x01, b'x']);
+
+    let params: Vec<_> = ParamParser::with_query_attributes(&data, &mut stmt)
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].coltype, ColumnType::MYSQL_TYPE_LONGLONG);
+    assert_eq!(params[0].value.into_inner(), ValueInner::Int(42));
+    assert_eq!(
+        stmt.bound_types,
+        vec![(ColumnType::MYSQL_TYPE_LONGLONG, false)]
+    );
+}
+
+#[test]
+fn it_parses_no_params() {
+    let mut stmt = StatementData::default();
+    for data in [&[][..], &[0x01][..], &[0x00][..]] {
+        let params: Vec<_> = ParamParser::new(data, &mut stmt).into_iter().collect();
+        assert!(params.is_empty());
+    }
+    assert!(stmt.bound_types.is_empty());
+
+    // a client that negotiated CLIENT_QUERY_ATTRIBUTES sends a parameter count of 0
+    let params: Vec<_> = ParamParser::with_query_attributes(&[0x00], &mut stmt)
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert!(params.is_empty());
+}
+
+#[test]
+fn it_reads_nulls_across_bitmap_bytes() {
+    let mut stmt = StatementData {
+        params: 9,
+        ..Default::default()
+    };
+    // the 1st, 8th and 9th parameters are NULL: bits 0 and 7 of the first byte, bit 0 of the
+    // second, with no reserved bits in front as there are for result rows
+    let mut data = vec![0x81, 0x01, 0x01];
+    for _ in 0..9 {
+        data.extend([0x08, 0x00]);
+    }
+    for i in 1..7i64 {
+        data.extend(i.to_le_bytes());
+    }
+
+    let params: Vec<_> = ParamParser::new(&data, &mut stmt).into_iter().collect();
+    assert_eq!(params.len(), 9);
+    assert!(params[0].value.is_null());
+    for (i, param) in params.iter().enumerate().take(7).skip(1) {
+        assert_eq!(param.value.into_inner(), ValueInner::Int(i as i64));
+    }
+    assert!(params[7].value.is_null());
+    assert!(params[8].value.is_null());
+}
+
+#[test]
+fn it_reads_execute_query_attributes() {
+    let mut stmt = StatementData {
+        params: 2,
+        ..Default::default()
+    };
+    // two parameters, the second of which is NULL, followed by one query attribute
+    let mut data = vec![0x03, 0x02, 0x01, 0x08, 0x00, 0x00, 0xfd, 0x00, 0x00];
+    data.extend([0xfd, 0x00, 0x0b]);
+    data.extend(b"traceparent");
+    data.extend(42i64.to_le_bytes());
+    data.push(0x06);
+    data.extend(b"00-abc");
+
+    let parser = ParamParser::with_query_attributes(&data, &mut stmt).unwrap();
+    let attrs = parser.query_attributes().unwrap();
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].name, "traceparent");
+    assert_eq!(attrs[0].coltype, ColumnType::MYSQL_TYPE_VAR_STRING);
+    assert_eq!(
+        attrs[0].value.into_inner(),
+        ValueInner::Bytes(&b"00-abc"[..])
+    );
+
+    let params: Vec<_> = parser.into_iter().collect();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0].value.into_inner(), ValueInner::Int(42));
+    assert!(params[1].value.is_null());
+}
+
+#[test]
+fn it_reads_no_execute_query_attributes_without_types() {
+    let mut stmt = StatementData {
+        params: 1,
+        bound_types: vec![(ColumnType::MYSQL_TYPE_LONGLONG, false)],
+        ..Default::default()
+    };
+    let mut data = vec![0x02, 0x00, 0x00];
+    data.extend(42i64.to_le_bytes());
+
+    let parser = ParamParser::with_query_attributes(&data, &mut stmt).unwrap();
+    assert!(parser.query_attributes().unwrap().is_empty());
+    let params: Vec<_> = parser.into_iter().collect();
+    assert_eq!(params[0].value.into_inner(), ValueInner::Int(42));
+}
+
+#[test]
+fn it_reads_bulk_rows() {
+    let mut stmt = StatementData {
+        params: 2,
+        ..Default::default()
+    };
+    // the types of both parameters, then two rows
+    let mut data = vec![0x08, 0x80, 0xfd, 0x00];
+    data.push(0x00);
+    data.extend(7u64.to_le_bytes());
+    data.extend([0x00, 0x01, b'a']);
+    data.extend([0x01, 0x02]);
+
+    let rows = bulk_rows(&data, 0x80, &mut stmt).unwrap();
+    assert_eq!(
+        stmt.bound_types,
+        vec![
+            (ColumnType::MYSQL_TYPE_LONGLONG, true),
+            (ColumnType::MYSQL_TYPE_VAR_STRING, false)
+        ]
+    );
+    assert_eq!(rows.len(), 2);
+    match &rows[0][..] {
+        [BulkParam::Value(a), BulkParam::Value(b)] => {
+            assert_eq!(a.value.into_inner(), ValueInner::UInt(7));
+            assert_eq!(b.value.into_inner(), ValueInner::Bytes(&b"a"[..]));
+        }
+        _ => panic!("expected two values"),
+    }
+    match &rows[1][..] {
+        [BulkParam::Value(a), BulkParam::Default] => assert!(a.value.is_null()),
+        _ => panic!("expected NULL and DEFAULT"),
+    }
+
+    // the types are remembered for the next batch
+    let rows = bulk_rows(&[0x03, 0x03], 0, &mut stmt).unwrap();
+    assert!(matches!(
+        &rows[0][..],
+        [BulkParam::Ignore, BulkParam::Ignore]
+    ));
+}
+
+#[test]
+fn it_rejects_bad_bulk_rows() {
+    let mut stmt = StatementData {
+        params: 1,
+        ..Default::default()
+    };
+    // no types were ever sent
+    assert!(bulk_rows(&[0x01], 0, &mut stmt).is_err());
+    // unknown indicator
+    assert!(bulk_rows(&[0x08, 0x00, 0x04], 0x80, &mut stmt).is_err());
+    // truncated value
+    assert!(bulk_rows(&[0x08, 0x00, 0x00, 0x01], 0x80, &mut stmt).is_err());
+    // nothing to bind a row to
+    assert!(bulk_rows(&[], 0x80, &mut StatementData::default()).is_err());
+}
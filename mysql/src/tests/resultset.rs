@@ -0,0 +1,301 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::myc::constants::{CapabilityFlags, ColumnType, StatusFlags};
+use crate::packet_reader::packet;
+use crate::packet_writer::PacketWriter;
+use crate::resultset::QueryResultWriter;
+use crate::{Column, SessionContext};
+
+fn columns() -> Vec<Column> {
+    vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: ColumnType::MYSQL_TYPE_LONG,
+        ..Default::default()
+    }]
+}
+
+// writes `resultsets` text resultsets, each made of the given number of `(1,)` rows, and
+// returns every packet the client receives as (seq, payload)
+async fn resultset(
+    client_capabilities: CapabilityFlags,
+    rows: usize,
+    resultsets: usize,
+) -> Vec<(u8, Vec<u8>)> {
+    let cols = columns();
+    let session = SessionContext::default();
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    let mut qw = QueryResultWriter::new(&mut w, false, client_capabilities, &session);
+    for _ in 0..resultsets {
+        let mut rw = qw.start(&cols).await.unwrap();
+        for _ in 0..rows {
+            rw.write_row(vec![1i32]).await.unwrap();
+        }
+        qw = rw.finish_one().await.unwrap();
+    }
+    qw.no_more_results().await.unwrap();
+    w.flush_all().await.unwrap();
+
+    let mut packets = Vec::new();
+    let mut rest = &out[..];
+    while !rest.is_empty() {
+        let (r, p) = packet(rest).unwrap();
+        packets.push((p.0, p.1.to_vec()));
+        rest = r;
+    }
+    packets
+}
+
+fn is_column_definition(payload: &[u8]) -> bool {
+    payload.starts_with(b"\x03def")
+}
+
+#[tokio::test]
+async fn eof_after_column_definitions() {
+    let packets = resultset(CapabilityFlags::CLIENT_PROTOCOL_41, 1, 1).await;
+    assert_eq!(packets.len(), 5);
+    assert_eq!(packets[0], (0, vec![0x01]));
+    assert!(is_column_definition(&packets[1].1));
+    assert_eq!(packets[2], (2, vec![0xfe, 0x00, 0x00, 0x00, 0x00]));
+    assert_eq!(packets[3], (3, b"\x011".to_vec()));
+    assert_eq!(packets[4], (4, vec![0xfe, 0x00, 0x00, 0x00, 0x00]));
+}
+
+#[tokio::test]
+async fn no_marker_after_column_definitions_with_deprecate_eof() {
+    let packets = resultset(
+        CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_DEPRECATE_EOF,
+        1,
+        1,
+    )
+    .await;
+    assert_eq!(packets.len(), 4);
+    assert_eq!(packets[0], (0, vec![0x01]));
+    assert!(is_column_definition(&packets[1].1));
+    assert_eq!(packets[2], (2, b"\x011".to_vec()));
+    assert_eq!(
+        packets[3],
+        (3, vec![0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+    );
+}
+
+#[tokio::test]
+async fn empty_resultset_markers() {
+    let packets = resultset(CapabilityFlags::CLIENT_PROTOCOL_41, 0, 1).await;
+    assert_eq!(packets.len(), 4);
+    assert_eq!(packets[2].1[0], 0xfe);
+    assert_eq!(packets[3].1[0], 0xfe);
+
+    let packets = resultset(
+        CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_DEPRECATE_EOF,
+        0,
+        1,
+    )
+    .await;
+    assert_eq!(packets.len(), 3);
+    assert!(is_column_definition(&packets[1].1));
+    assert_eq!(packets[2].1[0], 0xfe);
+}
+
+#[tokio::test]
+async fn more_results_flag_on_final_ok_with_deprecate_eof() {
+    let packets = resultset(
+        CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_DEPRECATE_EOF,
+        1,
+        2,
+    )
+    .await;
+    assert_eq!(packets.len(), 8);
+    let more = StatusFlags::SERVER_MORE_RESULTS_EXISTS.bits().to_le_bytes();
+    assert_eq!(
+        packets[3].1,
+        [&[0xfe, 0x00, 0x00][..], &more[..], &[0x00, 0x00][..]].concat()
+    );
+    assert_eq!(packets[4], (4, vec![0x01]));
+    assert_eq!(packets[7].1, vec![0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[tokio::test]
+async fn more_results_flag_on_all_but_the_last_eof() {
+    let packets = resultset(CapabilityFlags::CLIENT_PROTOCOL_41, 1, 3).await;
+    // the column count, its definition, an EOF, the row and the terminating EOF, three times
+    assert_eq!(packets.len(), 15);
+    let more = StatusFlags::SERVER_MORE_RESULTS_EXISTS.bits().to_le_bytes();
+    for (i, end) in [4, 9, 14].into_iter().enumerate() {
+        let status = if i < 2 { more } else { [0x00, 0x00] };
+        assert_eq!(
+            packets[end].1,
+            [&[0xfe, 0x00, 0x00][..], &status[..]].concat(),
+            "resultset {}",
+            i
+        );
+    }
+    // the sequence ids go on across resultsets
+    assert_eq!(packets[5], (5, vec![0x01]));
+    assert_eq!(packets[10], (10, vec![0x01]));
+}
+
+#[tokio::test]
+async fn more_results_flag_on_all_but_the_last_ok_with_deprecate_eof() {
+    let packets = resultset(
+        CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_DEPRECATE_EOF,
+        1,
+        3,
+    )
+    .await;
+    // the column count, its definition, the row and the terminating OK, three times
+    assert_eq!(packets.len(), 12);
+    let more = StatusFlags::SERVER_MORE_RESULTS_EXISTS.bits().to_le_bytes();
+    for (i, end) in [3, 7, 11].into_iter().enumerate() {
+        let status = if i < 2 { more } else { [0x00, 0x00] };
+        assert_eq!(
+            packets[end].1,
+            [&[0xfe, 0x00, 0x00][..], &status[..], &[0x00, 0x00][..]].concat(),
+            "resultset {}",
+            i
+        );
+    }
+}
+
+#[tokio::test]
+async fn write_null_sets_the_bitmap_bit() {
+    let cols = vec![
+        Column::new("a", ColumnType::MYSQL_TYPE_LONG),
+        Column::new("b", ColumnType::MYSQL_TYPE_VAR_STRING),
+        Column::new("c", ColumnType::MYSQL_TYPE_LONG),
+        Column::new("d", ColumnType::MYSQL_TYPE_LONG).not_null(),
+    ];
+    let session = SessionContext::default();
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    let qw = QueryResultWriter::new(&mut w, true, CapabilityFlags::CLIENT_PROTOCOL_41, &session);
+    let mut rw = qw.start(&cols).await.unwrap();
+    rw.write_col(1i32).unwrap();
+    rw.write_null().unwrap();
+    rw.write_col(3i32).unwrap();
+    assert!(rw.write_null().is_err());
+    rw.write_col(4i32).unwrap();
+    rw.finish().await.unwrap();
+    w.flush_all().await.unwrap();
+
+    let mut packets = Vec::new();
+    let mut rest = &out[..];
+    while !rest.is_empty() {
+        let (r, p) = packet(rest).unwrap();
+        packets.push(p.1.to_vec());
+        rest = r;
+    }
+    // the column count, four definitions and their EOF come first
+    let row = &packets[6];
+    assert_eq!(row[0], 0x00);
+    // the NULL bitmap is offset by two bits, so `b` is the fourth bit
+    assert_eq!(row[1], 0b0000_1000);
+    // and no bytes are sent for `b` itself
+    let values: Vec<_> = row[2..]
+        .chunks(4)
+        .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    assert_eq!(values, vec![1, 3, 4]);
+}
+
+#[tokio::test]
+async fn write_col_refuses_null_for_not_null() {
+    let cols = vec![
+        Column::new("a", ColumnType::MYSQL_TYPE_LONG).not_null(),
+        Column::new("b", ColumnType::MYSQL_TYPE_LONG).not_null(),
+    ];
+    let session = SessionContext::default();
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    let qw = QueryResultWriter::new(&mut w, true, CapabilityFlags::CLIENT_PROTOCOL_41, &session);
+    let mut rw = qw.start(&cols).await.unwrap();
+    rw.write_col(1i32).unwrap();
+    let err = rw.write_col(None::<i32>).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+fn raw_row_columns() -> Vec<Column> {
+    vec![
+        Column::new("a", ColumnType::MYSQL_TYPE_LONG),
+        Column::new("b", ColumnType::MYSQL_TYPE_LONG),
+        Column::new("c", ColumnType::MYSQL_TYPE_VAR_STRING),
+    ]
+}
+
+fn split_packets(mut rest: &[u8]) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    while !rest.is_empty() {
+        let (r, p) = packet(rest).unwrap();
+        packets.push(p.1.to_vec());
+        rest = r;
+    }
+    packets
+}
+
+#[tokio::test]
+async fn raw_rows_match_written_rows() {
+    let cols = raw_row_columns();
+    let session = SessionContext::default();
+    for is_bin in [false, true] {
+        let mut written = Vec::new();
+        let mut w = PacketWriter::new(&mut written);
+        let qw = QueryResultWriter::new(
+            &mut w,
+            is_bin,
+            CapabilityFlags::CLIENT_PROTOCOL_41,
+            &session,
+        );
+        let mut rw = qw.start(&cols).await.unwrap();
+        rw.write_col(1i32).unwrap();
+        rw.write_null().unwrap();
+        rw.write_col("x").unwrap();
+        rw.end_row().await.unwrap();
+        rw.finish().await.unwrap();
+        w.flush_all().await.unwrap();
+        let written = split_packets(&written);
+
+        // the column count, three definitions and their EOF come first
+        let mut raw = Vec::new();
+        let mut w = PacketWriter::new(&mut raw);
+        let qw = QueryResultWriter::new(
+            &mut w,
+            is_bin,
+            CapabilityFlags::CLIENT_PROTOCOL_41,
+            &session,
+        );
+        let mut rw = qw.start(&cols).await.unwrap();
+        rw.write_raw_row(&written[5]).await.unwrap();
+        rw.finish().await.unwrap();
+        w.flush_all().await.unwrap();
+
+        assert_eq!(split_packets(&raw), written);
+    }
+}
+
+#[tokio::test]
+async fn raw_rows_need_a_complete_row() {
+    let cols = raw_row_columns();
+    let session = SessionContext::default();
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    let qw = QueryResultWriter::new(&mut w, false, CapabilityFlags::CLIENT_PROTOCOL_41, &session);
+    let mut rw = qw.start(&cols).await.unwrap();
+    rw.write_col(1i32).unwrap();
+    assert!(rw.write_raw_row(b"\x011").await.is_err());
+    rw.write_null().unwrap();
+    rw.write_col("x").unwrap();
+    rw.finish().await.unwrap();
+}
@@ -0,0 +1,82 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::schemas::{Listing, Show};
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|n| n.to_string()).collect()
+}
+
+#[test]
+fn show_databases() {
+    for query in ["SHOW DATABASES", "show schemas;", "  Show Databases  "] {
+        let show = Show::parse(query).unwrap();
+        assert_eq!(show.listing, Listing::Databases);
+        let (cols, rows) = show.answer(names(&["a", "b"]));
+        assert_eq!(cols.len(), 1);
+        assert_eq!(cols[0].column, "Database");
+        assert_eq!(rows, vec![vec!["a".to_owned()], vec!["b".to_owned()]]);
+    }
+}
+
+#[test]
+fn show_tables() {
+    let show = Show::parse("SHOW TABLES FROM `db`").unwrap();
+    assert_eq!(
+        show.listing,
+        Listing::Tables {
+            schema: "db",
+            full: false
+        }
+    );
+    let (cols, rows) = show.answer(names(&["t"]));
+    assert_eq!(cols[0].column, "Tables_in_db");
+    assert_eq!(rows, vec![vec!["t".to_owned()]]);
+
+    let show = Show::parse("show full tables in db").unwrap();
+    let (cols, rows) = show.answer(names(&["t"]));
+    assert_eq!(cols[1].column, "Table_type");
+    assert_eq!(rows, vec![vec!["t".to_owned(), "BASE TABLE".to_owned()]]);
+}
+
+#[test]
+fn wildcards() {
+    let show = Show::parse("SHOW TABLES FROM db LIKE 'user_'").unwrap();
+    let (cols, rows) = show.answer(names(&["users", "user", "userss", "Users"]));
+    assert_eq!(cols[0].column, "Tables_in_db (user_)");
+    assert_eq!(
+        rows,
+        vec![vec!["users".to_owned()], vec!["Users".to_owned()]]
+    );
+
+    let show = Show::parse("SHOW DATABASES LIKE \"%a%\"").unwrap();
+    let (cols, rows) = show.answer(names(&["foo", "bar", "baz"]));
+    assert_eq!(cols[0].column, "Database (%a%)");
+    assert_eq!(rows, vec![vec!["bar".to_owned()], vec!["baz".to_owned()]]);
+}
+
+#[test]
+fn other_queries() {
+    for query in [
+        "SHOW TABLES",
+        "SHOW TABLES FROM",
+        "SHOW TABLES FROM db WHERE x",
+        "SHOW DATABASES LIKE",
+        "SHOW VARIABLES",
+        "SHOW TABLESPACES",
+        "SELECT 1",
+    ] {
+        assert_eq!(Show::parse(query), None, "{}", query);
+    }
+}
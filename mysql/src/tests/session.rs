@@ -0,0 +1,169 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::myc::constants::{CapabilityFlags, SessionStateType, StatusFlags};
+use crate::myc::io::ParseBuf;
+use crate::myc::packets::{session_state_change, CommonOkPacket, OkPacketDeserializer};
+use crate::myc::proto::MyDeserialize;
+use crate::packet_writer::PacketWriter;
+use crate::{writers, OkResponse, SessionStateChange};
+
+fn caps() -> CapabilityFlags {
+    CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_SESSION_TRACK
+}
+
+async fn ok_packet(
+    client_capabilities: CapabilityFlags,
+    changes: Vec<SessionStateChange>,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut w = PacketWriter::new(&mut out);
+    let ok = OkResponse {
+        session_state_changes: changes,
+        ..Default::default()
+    };
+    writers::write_ok_packet(&mut w, client_capabilities, ok)
+        .await
+        .unwrap();
+    w.flush_all().await.unwrap();
+    // strip the packet header
+    out.split_off(4)
+}
+
+// decodes the OK packet carrying `change` the way a client does, and checks the decoded change
+async fn decoded(
+    change: SessionStateChange,
+    expected: SessionStateType,
+    check: fn(&session_state_change::SessionStateChange<'_>) -> bool,
+) {
+    let payload = ok_packet(caps(), vec![change]).await;
+    let ok = OkPacketDeserializer::<CommonOkPacket>::deserialize(caps(), &mut ParseBuf(&payload))
+        .unwrap()
+        .into_inner();
+    assert!(ok
+        .status_flags()
+        .contains(StatusFlags::SERVER_SESSION_STATE_CHANGED));
+    let info = ok.session_state_info().unwrap();
+    assert_eq!(info.len(), 1);
+    assert_eq!(info[0].data_type(), expected);
+    assert!(check(&info[0].decode().unwrap()));
+}
+
+#[tokio::test]
+async fn system_variable() {
+    let payload = ok_packet(
+        caps(),
+        vec![SessionStateChange::SystemVariable {
+            name: "autocommit".to_owned(),
+            value: "OFF".to_owned(),
+        }],
+    )
+    .await;
+    // header, affected rows, last insert id, status, warnings, info
+    assert_eq!(&payload[..7], &[0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00]);
+    assert_eq!(&payload[7..], b"\x00\x11\x00\x0f\x0aautocommit\x03OFF");
+
+    decoded(
+        SessionStateChange::SystemVariable {
+            name: "autocommit".to_owned(),
+            value: "OFF".to_owned(),
+        },
+        SessionStateType::SESSION_TRACK_SYSTEM_VARIABLES,
+        |c| matches!(c, session_state_change::SessionStateChange::SystemVariables(v) if v.len() == 1),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn schema() {
+    decoded(
+        SessionStateChange::Schema("db".to_owned()),
+        SessionStateType::SESSION_TRACK_SCHEMA,
+        |c| matches!(c, session_state_change::SessionStateChange::Schema(_)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn state_change() {
+    decoded(
+        SessionStateChange::StateChange,
+        SessionStateType::SESSION_TRACK_STATE_CHANGE,
+        |c| matches!(c, session_state_change::SessionStateChange::IsTracked(true)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn gtids() {
+    let payload = ok_packet(caps(), vec![SessionStateChange::Gtids("uuid:1".to_owned())]).await;
+    assert_eq!(&payload[7..], b"\x00\x0a\x03\x08\x00\x06uuid:1");
+
+    decoded(
+        SessionStateChange::Gtids("uuid:1".to_owned()),
+        SessionStateType::SESSION_TRACK_GTIDS,
+        |c| matches!(c, session_state_change::SessionStateChange::Gtids(_)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn transaction_characteristics() {
+    decoded(
+        SessionStateChange::TransactionCharacteristics("START TRANSACTION;".to_owned()),
+        SessionStateType::SESSION_TRACK_TRANSACTION_CHARACTERISTICS,
+        |c| {
+            matches!(
+                c,
+                session_state_change::SessionStateChange::TransactionCharacteristics(_)
+            )
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn transaction_state() {
+    decoded(
+        SessionStateChange::TransactionState("T_______".to_owned()),
+        SessionStateType::SESSION_TRACK_TRANSACTION_STATE,
+        |c| {
+            matches!(
+                c,
+                session_state_change::SessionStateChange::TransactionState(_)
+            )
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn many_changes() {
+    let changes = vec![
+        SessionStateChange::Schema("db".to_owned()),
+        SessionStateChange::StateChange,
+    ];
+    let payload = ok_packet(caps(), changes).await;
+    assert_eq!(&payload[7..], b"\x00\x09\x01\x03\x02db\x02\x02\x011");
+}
+
+#[tokio::test]
+async fn not_sent_without_session_track() {
+    let payload = ok_packet(
+        CapabilityFlags::CLIENT_PROTOCOL_41,
+        vec![SessionStateChange::Schema("db".to_owned())],
+    )
+    .await;
+    assert_eq!(payload, vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+}
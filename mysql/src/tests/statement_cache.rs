@@ -0,0 +1,46 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::statement_cache::{CachedStatement, StatementCache};
+
+fn stmt(id: u32) -> CachedStatement {
+    CachedStatement {
+        id,
+        params: Vec::new(),
+        columns: Vec::new(),
+    }
+}
+
+#[test]
+fn it_reuses_trimmed_sql() {
+    let mut cache = StatementCache::new(2);
+    cache.insert("SELECT 1", stmt(1));
+    assert_eq!(cache.get("  SELECT 1\n").map(|s| s.id), Some(1));
+    assert!(cache.get("SELECT 2").is_none());
+}
+
+#[test]
+fn it_evicts_least_recently_used() {
+    let mut cache = StatementCache::new(2);
+    cache.insert("SELECT 1", stmt(1));
+    cache.insert("SELECT 2", stmt(2));
+    cache.get("SELECT 1");
+    cache.insert("SELECT 3", stmt(3));
+
+    assert_eq!(cache.take_evicted(), vec![2]);
+    assert!(cache.take_evicted().is_empty());
+    assert!(cache.contains_id(1));
+    assert!(!cache.contains_id(2));
+    assert!(cache.contains_id(3));
+}
@@ -0,0 +1,53 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::statements::split;
+
+#[test]
+fn splits_on_semicolons() {
+    assert_eq!(
+        split("SELECT 1; SELECT 2;;\n", false),
+        vec!["SELECT 1", "SELECT 2"]
+    );
+    assert!(split(" ; ", false).is_empty());
+}
+
+#[test]
+fn keeps_quoted_semicolons() {
+    assert_eq!(
+        split(r#"SELECT 'a;b', "c;d", `e;f`; SELECT 2"#, false),
+        vec![r#"SELECT 'a;b', "c;d", `e;f`"#, "SELECT 2"]
+    );
+    assert_eq!(
+        split("SELECT 'it''s;'; SELECT 2", false),
+        vec!["SELECT 'it''s;'", "SELECT 2"]
+    );
+}
+
+#[test]
+fn keeps_commented_semicolons() {
+    assert_eq!(
+        split("SELECT 1 -- a;b\n; SELECT /* c;d */ 2 # e;f", false),
+        vec!["SELECT 1 -- a;b", "SELECT /* c;d */ 2 # e;f"]
+    );
+}
+
+#[test]
+fn follows_backslash_escapes() {
+    let query = r"SELECT 'a\'; SELECT 'b'";
+    // the backslash escapes the quote, so the string runs on to the last quote
+    assert_eq!(split(query, false), vec![query]);
+    // with NO_BACKSLASH_ESCAPES, the backslash is a plain character ending the first string
+    assert_eq!(split(query, true), vec![r"SELECT 'a\'", "SELECT 'b'"]);
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::myc;
-use crate::value::Value;
+use crate::value::{MysqlTime, Value};
 use crate::{Column, ColumnFlags, ColumnType};
 use chrono::{self, TimeZone};
 use myc::proto::MySerialize;
@@ -32,6 +32,7 @@ macro_rules! rt {
                 column: String::new(),
                 coltype: $ct,
                 colflags: ColumnFlags::empty(),
+                ..Default::default()
             };
 
             if !$sig {
@@ -196,3 +197,251 @@ rt!(
     ColumnType::MYSQL_TYPE_BLOB
 );
 rt!(string, &str, "foobar", ColumnType::MYSQL_TYPE_STRING);
+
+mod chrono_duration {
+    use super::*;
+
+    fn parse_time(v: myc::value::Value) -> chrono::Duration {
+        let mut data = Vec::new();
+        v.serialize(&mut data);
+        let v = Value::parse_from(&mut &data[..], ColumnType::MYSQL_TYPE_TIME, false).unwrap();
+        chrono::Duration::try_from(&v).unwrap()
+    }
+
+    #[test]
+    fn positive() {
+        assert_eq!(
+            parse_time(myc::value::Value::Time(false, 1, 2, 3, 4, 5)),
+            chrono::Duration::seconds(86_400 + 2 * 3_600 + 3 * 60 + 4)
+                + chrono::Duration::microseconds(5)
+        );
+    }
+
+    #[test]
+    fn negative() {
+        assert_eq!(
+            parse_time(myc::value::Value::Time(true, 0, 12, 30, 0, 0)),
+            -chrono::Duration::minutes(12 * 60 + 30)
+        );
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(
+            parse_time(myc::value::Value::Time(false, 0, 0, 0, 0, 0)),
+            chrono::Duration::zero()
+        );
+    }
+
+    #[test]
+    fn rejects_non_time() {
+        let mut data = Vec::new();
+        myc::value::Value::Int(42).serialize(&mut data);
+        let v = Value::parse_from(&mut &data[..], ColumnType::MYSQL_TYPE_LONGLONG, false).unwrap();
+        assert!(chrono::Duration::try_from(&v).is_err());
+    }
+}
+
+mod mysql_time {
+    use super::*;
+
+    fn parse_time(data: &[u8]) -> std::io::Result<MysqlTime> {
+        let mut packet = vec![data.len() as u8];
+        packet.extend(data);
+        let v = Value::parse_from(&mut &packet[..], ColumnType::MYSQL_TYPE_TIME, false).unwrap();
+        MysqlTime::try_from(&v)
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(parse_time(&[]).unwrap(), MysqlTime::default());
+    }
+
+    #[test]
+    fn without_microseconds() {
+        // -1 day 12:30:05
+        assert_eq!(
+            parse_time(&[1, 1, 0, 0, 0, 12, 30, 5]).unwrap(),
+            MysqlTime {
+                negative: true,
+                days: 1,
+                hours: 12,
+                minutes: 30,
+                seconds: 5,
+                microseconds: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn with_microseconds() {
+        let mut data = vec![0, 34, 0, 0, 0, 22, 59, 59];
+        data.extend(999_999u32.to_le_bytes());
+        assert_eq!(
+            parse_time(&data).unwrap(),
+            MysqlTime {
+                negative: false,
+                days: 34,
+                hours: 22,
+                minutes: 59,
+                seconds: 59,
+                microseconds: 999_999,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_other_lengths() {
+        assert!(parse_time(&[0, 1, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn rejects_non_time() {
+        let mut data = Vec::new();
+        myc::value::Value::Int(42).serialize(&mut data);
+        let v = Value::parse_from(&mut &data[..], ColumnType::MYSQL_TYPE_LONGLONG, false).unwrap();
+        assert!(MysqlTime::try_from(&v).is_err());
+    }
+}
+
+#[cfg(feature = "geo")]
+mod geometry {
+    use super::*;
+    use geo_types::{line_string, point, polygon, Geometry, GeometryCollection, MultiPoint};
+
+    fn decode(data: &[u8]) -> std::io::Result<(u32, Geometry<f64>)> {
+        let v = Value::bytes(data);
+        TryFrom::try_from(&v)
+    }
+
+    fn wkb_point(le: bool, x: f64, y: f64) -> Vec<u8> {
+        let mut data = vec![le as u8];
+        if le {
+            data.extend(1u32.to_le_bytes());
+            data.extend(x.to_le_bytes());
+            data.extend(y.to_le_bytes());
+        } else {
+            data.extend(1u32.to_be_bytes());
+            data.extend(x.to_be_bytes());
+            data.extend(y.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn point() {
+        let mut data = 4326u32.to_le_bytes().to_vec();
+        data.extend(wkb_point(true, 1.5, -2.0));
+        assert_eq!(
+            decode(&data).unwrap(),
+            (4326, Geometry::Point(point!(x: 1.5, y: -2.0)))
+        );
+    }
+
+    #[test]
+    fn big_endian_point() {
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend(wkb_point(false, 3.0, 4.0));
+        assert_eq!(
+            decode(&data).unwrap(),
+            (0, Geometry::Point(point!(x: 3.0, y: 4.0)))
+        );
+    }
+
+    #[test]
+    fn line_string() {
+        let mut data = vec![0, 0, 0, 0, 1, 2, 0, 0, 0, 2, 0, 0, 0];
+        for c in [0.0f64, 0.0, 1.0, 1.0] {
+            data.extend(c.to_le_bytes());
+        }
+        assert_eq!(
+            decode(&data).unwrap(),
+            (
+                0,
+                Geometry::LineString(line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 1.0)])
+            )
+        );
+    }
+
+    #[test]
+    fn polygon() {
+        let mut data = vec![0, 0, 0, 0, 1, 3, 0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0];
+        for c in [0.0f64, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0] {
+            data.extend(c.to_le_bytes());
+        }
+        assert_eq!(
+            decode(&data).unwrap(),
+            (
+                0,
+                Geometry::Polygon(polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)])
+            )
+        );
+    }
+
+    #[test]
+    fn collection() {
+        let mut multi = vec![1, 4, 0, 0, 0, 2, 0, 0, 0];
+        multi.extend(wkb_point(true, 1.0, 2.0));
+        multi.extend(wkb_point(false, 3.0, 4.0));
+
+        let mut data = vec![0, 0, 0, 0, 1, 7, 0, 0, 0, 2, 0, 0, 0];
+        data.extend(wkb_point(true, 5.0, 6.0));
+        data.extend(multi);
+        assert_eq!(
+            decode(&data).unwrap(),
+            (
+                0,
+                Geometry::GeometryCollection(GeometryCollection(vec![
+                    Geometry::Point(point!(x: 5.0, y: 6.0)),
+                    Geometry::MultiPoint(MultiPoint(vec![
+                        point!(x: 1.0, y: 2.0),
+                        point!(x: 3.0, y: 4.0)
+                    ])),
+                ]))
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_member_type() {
+        // a MULTIPOINT containing a LINESTRING
+        let data = [
+            0, 0, 0, 0, 1, 4, 0, 0, 0, 1, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend(wkb_point(true, 1.0, 2.0));
+        data.pop();
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend(wkb_point(true, 1.0, 2.0));
+        data.push(0);
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let mut data = 0u32.to_le_bytes().to_vec();
+        for _ in 0..100 {
+            data.extend([1, 7, 0, 0, 0, 1, 0, 0, 0]);
+        }
+        data.extend(wkb_point(true, 0.0, 0.0));
+        assert!(decode(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_non_bytes() {
+        let mut data = Vec::new();
+        myc::value::Value::Int(42).serialize(&mut data);
+        let v = Value::parse_from(&mut &data[..], ColumnType::MYSQL_TYPE_LONGLONG, false).unwrap();
+        assert!(<(u32, Geometry<f64>)>::try_from(&v).is_err());
+    }
+}
@@ -12,11 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::value::ToMysqlValue;
+use crate::value::{
+    CommaJoined, FiniteOrNull, JsonArray, Lossy, NullIfEmpty, ToMysqlValue, Utf8Bytes,
+};
 use crate::{Column, ColumnFlags, ColumnType};
 use chrono::{self, TimeZone};
 use std::time;
 
+fn col(coltype: ColumnType, colflags: ColumnFlags) -> Column {
+    Column {
+        coltype,
+        colflags,
+        ..Default::default()
+    }
+}
+
 mod roundtrip_text {
     use super::*;
 
@@ -108,6 +118,7 @@ mod roundtrip_bin {
                     column: String::new(),
                     coltype: $ct,
                     colflags: ColumnFlags::empty(),
+                    ..Default::default()
                 };
 
                 if !$sig {
@@ -195,6 +206,50 @@ mod roundtrip_bin {
     );
     rt!(u64_one, u64, 1, ColumnType::MYSQL_TYPE_LONGLONG, false);
     rt!(i64_one, i64, 1, ColumnType::MYSQL_TYPE_LONGLONG, true);
+    rt!(i64_one_long, i64, 1, ColumnType::MYSQL_TYPE_LONG, true);
+    rt!(i64_one_int24, i64, 1, ColumnType::MYSQL_TYPE_INT24, true);
+    rt!(i64_one_short, i64, 1, ColumnType::MYSQL_TYPE_SHORT, true);
+    rt!(i64_one_tiny, i64, 1, ColumnType::MYSQL_TYPE_TINY, true);
+    rt!(i64_min_tiny, i64, -128, ColumnType::MYSQL_TYPE_TINY, true);
+    rt!(
+        i64_max_short,
+        i64,
+        32767,
+        ColumnType::MYSQL_TYPE_SHORT,
+        true
+    );
+    rt!(
+        i64_min_long,
+        i64,
+        -2147483648,
+        ColumnType::MYSQL_TYPE_LONG,
+        true
+    );
+    rt!(
+        i64_one_unsigned,
+        i64,
+        1,
+        ColumnType::MYSQL_TYPE_LONGLONG,
+        false
+    );
+    rt!(i64_max_utiny, i64, 255, ColumnType::MYSQL_TYPE_TINY, false);
+    rt!(u64_one_long, u64, 1, ColumnType::MYSQL_TYPE_LONG, false);
+    rt!(u64_one_short, u64, 1, ColumnType::MYSQL_TYPE_SHORT, false);
+    rt!(u64_one_tiny, u64, 1, ColumnType::MYSQL_TYPE_TINY, false);
+    rt!(
+        u64_one_signed,
+        u64,
+        1,
+        ColumnType::MYSQL_TYPE_LONGLONG,
+        true
+    );
+    rt!(
+        u64_max_ulong,
+        u64,
+        4294967295,
+        ColumnType::MYSQL_TYPE_LONG,
+        false
+    );
 
     rt!(f32_one, f32, 1.0, ColumnType::MYSQL_TYPE_FLOAT, false);
     rt!(f64_one, f64, 1.0, ColumnType::MYSQL_TYPE_DOUBLE, false);
@@ -289,3 +344,1476 @@ mod roundtrip_bin {
         ColumnType::MYSQL_TYPE_STRING
     );
 }
+
+mod naive_time {
+    use super::*;
+
+    #[test]
+    fn bin_truncates_to_fsp() {
+        let column = Column {
+            decimals: 3,
+            ..col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        chrono::NaiveTime::from_hms_micro(12, 34, 56, 789_123)
+            .to_mysql_bin(&mut data, &column)
+            .unwrap();
+
+        let mut expected = vec![12u8, 0, 0, 0, 0, 0, 12, 34, 56];
+        expected.extend_from_slice(&789_000u32.to_le_bytes());
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn bin_without_fsp_drops_micros() {
+        let column = Column {
+            decimals: 0,
+            ..col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        chrono::NaiveTime::from_hms_micro(12, 34, 56, 789_123)
+            .to_mysql_bin(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, vec![8u8, 0, 0, 0, 0, 0, 12, 34, 56]);
+    }
+
+    #[test]
+    fn bin_midnight() {
+        let column = Column {
+            decimals: 6,
+            ..col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        chrono::NaiveTime::from_hms(0, 0, 0)
+            .to_mysql_bin(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, vec![0u8]);
+    }
+
+    #[test]
+    fn text_truncates_to_fsp() {
+        let column = Column {
+            decimals: 3,
+            ..col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        chrono::NaiveTime::from_hms_micro(12, 34, 56, 789_123)
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x0c12:34:56.789");
+    }
+
+    #[test]
+    fn text_pads_to_fsp() {
+        let column = Column {
+            decimals: 3,
+            ..col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        chrono::NaiveTime::from_hms(12, 34, 56)
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x0c12:34:56.000");
+    }
+}
+
+mod duration_days {
+    use super::*;
+
+    use myc::{
+        io::ParseBuf,
+        proto::MyDeserialize,
+        value::{convert::FromValue, BinValue, TextValue, ValueDeserializer},
+    };
+
+    #[test]
+    fn text_and_bin_agree() {
+        let dur = time::Duration::from_secs(50 * 3600);
+
+        let mut text = Vec::new();
+        dur.to_mysql_text(&mut text).unwrap();
+        assert_eq!(text, b"\x0850:00:00");
+
+        let mut bin = Vec::new();
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_TIME,
+            ..Default::default()
+        };
+        dur.to_mysql_bin(&mut bin, &col).unwrap();
+        // 2 days and 2 hours
+        assert_eq!(bin, vec![8u8, 0, 2, 0, 0, 0, 2, 0, 0]);
+
+        let from_text = time::Duration::from_value(
+            ValueDeserializer::<TextValue>::deserialize((), &mut ParseBuf(&text[..]))
+                .unwrap()
+                .0,
+        );
+        let from_bin = time::Duration::from_value(
+            ValueDeserializer::<BinValue>::deserialize(
+                (ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+                &mut ParseBuf(&bin[..]),
+            )
+            .unwrap()
+            .0,
+        );
+        assert_eq!(from_text, dur);
+        assert_eq!(from_bin, dur);
+    }
+
+    #[test]
+    fn zero_value_time() {
+        let mut bin = Vec::new();
+        myc::value::Value::Time(false, 0, 0, 0, 0, 0)
+            .to_mysql_bin(
+                &mut bin,
+                &col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(bin, vec![0u8]);
+
+        let mut text = Vec::new();
+        myc::value::Value::Time(false, 0, 0, 0, 0, 0)
+            .to_mysql_text(&mut text)
+            .unwrap();
+        assert_eq!(text, b"\x0800:00:00");
+    }
+
+    #[test]
+    fn huge_value_time_is_an_error() {
+        let v = myc::value::Value::Time(false, u32::MAX, 23, 59, 59, 999_999);
+        let err = v
+            .to_mysql_bin(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let dur = time::Duration::from_secs(35 * 86_400);
+        assert!(dur
+            .to_mysql_bin(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty())
+            )
+            .is_err());
+    }
+}
+
+mod negative_time {
+    use super::*;
+
+    fn text(v: myc::value::Value) -> String {
+        let mut data = Vec::new();
+        v.to_mysql_text(&mut data).unwrap();
+        String::from_utf8(data[1..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn min() {
+        assert_eq!(
+            text(myc::value::Value::Time(true, 34, 22, 59, 59, 0)),
+            "-838:59:59"
+        );
+    }
+
+    #[test]
+    fn sub_second() {
+        assert_eq!(
+            text(myc::value::Value::Time(true, 0, 0, 0, 0, 500_000)),
+            "-00:00:00.500000"
+        );
+    }
+
+    #[test]
+    fn days_collapse_into_hours() {
+        assert_eq!(
+            text(myc::value::Value::Time(true, 1, 11, 2, 3, 0)),
+            "-35:02:03"
+        );
+    }
+
+    #[test]
+    fn padded() {
+        assert_eq!(
+            text(myc::value::Value::Time(true, 0, 1, 2, 3, 4)),
+            "-01:02:03.000004"
+        );
+    }
+
+    #[test]
+    fn zero_has_no_sign() {
+        assert_eq!(
+            text(myc::value::Value::Time(true, 0, 0, 0, 0, 0)),
+            "00:00:00"
+        );
+    }
+
+    #[test]
+    fn bin() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_TIME,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        myc::value::Value::Time(true, 1, 11, 2, 3, 0)
+            .to_mysql_bin(&mut data, &col)
+            .unwrap();
+        assert_eq!(data, vec![8, 1, 1, 0, 0, 0, 11, 2, 3]);
+    }
+}
+
+mod chrono_duration {
+    use super::*;
+
+    fn text(d: chrono::Duration) -> Vec<u8> {
+        let mut data = Vec::new();
+        d.to_mysql_text(&mut data).unwrap();
+        data
+    }
+
+    fn bin(d: chrono::Duration) -> Vec<u8> {
+        let mut data = Vec::new();
+        d.to_mysql_bin(
+            &mut data,
+            &col(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+        )
+        .unwrap();
+        data
+    }
+
+    #[test]
+    fn positive() {
+        let d = chrono::Duration::hours(26) + chrono::Duration::milliseconds(1_500);
+        assert_eq!(text(d), b"\x0f26:00:01.500000");
+        assert_eq!(
+            bin(d),
+            vec![12, 0, 1, 0, 0, 0, 2, 0, 1, 0x20, 0xa1, 0x07, 0x00]
+        );
+    }
+
+    #[test]
+    fn negative() {
+        let d = -chrono::Duration::minutes(90);
+        assert_eq!(text(d), b"\x09-01:30:00");
+        assert_eq!(bin(d), vec![8, 1, 0, 0, 0, 0, 1, 30, 0]);
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!(text(chrono::Duration::zero()), b"\x0800:00:00");
+        assert_eq!(bin(chrono::Duration::zero()), vec![0]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let d = -(chrono::Duration::hours(838) + chrono::Duration::seconds(3599));
+        let data = bin(d);
+        let v = crate::value::Value::parse_from(&mut &data[..], ColumnType::MYSQL_TYPE_TIME, false)
+            .unwrap();
+        assert_eq!(chrono::Duration::try_from(&v).unwrap(), d);
+    }
+
+    #[test]
+    fn wrong_type() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_LONG,
+            ..Default::default()
+        };
+        assert!(chrono::Duration::zero()
+            .to_mysql_bin(&mut Vec::new(), &col)
+            .is_err());
+    }
+}
+
+mod typed_array {
+    use super::*;
+
+    #[test]
+    fn forwarded_like_json() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_TYPED_ARRAY,
+            ..Default::default()
+        };
+        let bytes: &[u8] = b"[1, 2, 3]";
+        let mut data = Vec::new();
+        bytes.to_mysql_bin(&mut data, &col).unwrap();
+        assert_eq!(data, b"\x09[1, 2, 3]");
+
+        let v = crate::value::Value::parse_from(
+            &mut &data[..],
+            ColumnType::MYSQL_TYPE_TYPED_ARRAY,
+            false,
+        )
+        .unwrap();
+        assert_eq!(<&[u8]>::from(v), bytes);
+    }
+}
+
+#[cfg(feature = "memmap2")]
+mod mmap {
+    use super::*;
+
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn blob_from_mmap() {
+        let blob: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+
+        let path = std::env::temp_dir().join(format!("opensrv-mmap-{}", std::process::id()));
+        fs::File::create(&path).unwrap().write_all(&blob).unwrap();
+        let file = fs::File::open(&path).unwrap();
+        let mmap = unsafe { memmap2::Mmap::map(&file).unwrap() };
+
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_LONG_BLOB,
+            ..Default::default()
+        };
+        let mut from_mmap = Vec::new();
+        mmap.to_mysql_bin(&mut from_mmap, &col).unwrap();
+        let mut from_vec = Vec::new();
+        blob.to_mysql_bin(&mut from_vec, &col).unwrap();
+        assert_eq!(from_mmap, from_vec);
+
+        let mut from_mmap = Vec::new();
+        mmap.to_mysql_text(&mut from_mmap).unwrap();
+        let mut from_vec = Vec::new();
+        blob.to_mysql_text(&mut from_vec).unwrap();
+        assert_eq!(from_mmap, from_vec);
+
+        drop(mmap);
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_crate {
+    use super::*;
+
+    fn assert_same<T: ToMysqlValue, C: ToMysqlValue>(v: T, c: C, ct: ColumnType) {
+        let col = Column {
+            coltype: ct,
+            ..Default::default()
+        };
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        v.to_mysql_text(&mut a).unwrap();
+        c.to_mysql_text(&mut b).unwrap();
+        assert_eq!(a, b);
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        v.to_mysql_bin(&mut a, &col).unwrap();
+        c.to_mysql_bin(&mut b, &col).unwrap();
+        assert_eq!(a, b);
+    }
+
+    fn date() -> ::time::Date {
+        ::time::Date::from_calendar_date(2024, ::time::Month::March, 5).unwrap()
+    }
+
+    fn time_of_day() -> ::time::Time {
+        ::time::Time::from_hms_micro(12, 34, 56, 789).unwrap()
+    }
+
+    fn chrono_date_time() -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2024, 3, 5).and_hms_micro(12, 34, 56, 789)
+    }
+
+    #[test]
+    fn date_like_chrono() {
+        assert_same(
+            date(),
+            chrono::NaiveDate::from_ymd(2024, 3, 5),
+            ColumnType::MYSQL_TYPE_DATE,
+        );
+    }
+
+    #[test]
+    fn time_like_chrono() {
+        assert_same(
+            time_of_day(),
+            chrono::NaiveTime::from_hms_micro(12, 34, 56, 789),
+            ColumnType::MYSQL_TYPE_TIME,
+        );
+    }
+
+    #[test]
+    fn primitive_date_time_like_chrono() {
+        assert_same(
+            ::time::PrimitiveDateTime::new(date(), time_of_day()),
+            chrono_date_time(),
+            ColumnType::MYSQL_TYPE_DATETIME,
+        );
+    }
+
+    #[test]
+    fn offset_date_time_as_utc() {
+        let offset = ::time::UtcOffset::from_hms(2, 0, 0).unwrap();
+        let local = ::time::PrimitiveDateTime::new(date(), time_of_day()).assume_offset(offset);
+        assert_same(
+            local,
+            chrono_date_time() - chrono::Duration::hours(2),
+            ColumnType::MYSQL_TYPE_DATETIME,
+        );
+    }
+}
+
+mod unsigned_decimal {
+    use super::*;
+    use crate::Clamped;
+
+    #[test]
+    fn negative_rejected() {
+        let column = Column {
+            decimals: 2,
+            ..col(
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+                ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        assert!("-1.50".to_mysql_bin(&mut data, &column).is_err());
+        assert!("-1.50"
+            .to_mysql_text_with_column(&mut data, &column)
+            .is_err());
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn negative_zero_accepted() {
+        let column = Column {
+            decimals: 2,
+            ..col(
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+                ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        "-0.00".to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x05-0.00");
+    }
+
+    #[test]
+    fn negative_signed_accepted() {
+        let column = Column {
+            decimals: 2,
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        "-1.50".to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x05-1.50");
+    }
+
+    #[test]
+    fn negative_clamped() {
+        let column = Column {
+            decimals: 2,
+            ..col(
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+                ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        Clamped("-1.50").to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x040.00");
+
+        let mut data = Vec::new();
+        Clamped("-1.50")
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x040.00");
+    }
+
+    #[test]
+    fn positive_clamped() {
+        let column = Column {
+            decimals: 2,
+            ..col(
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+                ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        Clamped("1.50".to_owned())
+            .to_mysql_bin(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x041.50");
+    }
+}
+
+mod float_decimal {
+    use super::*;
+
+    #[test]
+    fn text_rounds_to_decimals() {
+        let column = Column {
+            decimals: 2,
+            column_length: Some(10),
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        3.14159f64
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x043.14");
+    }
+
+    #[test]
+    fn bin_rounds_to_decimals() {
+        let mut column = Column {
+            decimals: 2,
+            column_length: Some(10),
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        3.14159f64.to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x043.14");
+
+        column.decimals = 3;
+        let mut data = Vec::new();
+        2.5f32.to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x052.500");
+    }
+
+    #[test]
+    fn not_fixed_decimals() {
+        let column = Column {
+            decimals: 31,
+            column_length: Some(10),
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        let mut data = Vec::new();
+        3.14159f64.to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x073.14159");
+    }
+
+    #[test]
+    fn rejects_non_finite() {
+        let column = Column {
+            decimals: 2,
+            column_length: Some(10),
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        for v in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(v.to_mysql_bin(&mut Vec::new(), &column).is_err());
+            assert!(v
+                .to_mysql_text_with_column(&mut Vec::new(), &column)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_negative_unsigned() {
+        let column = Column {
+            decimals: 2,
+            column_length: Some(10),
+            ..col(
+                ColumnType::MYSQL_TYPE_NEWDECIMAL,
+                ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        assert!((-1.5f64).to_mysql_bin(&mut Vec::new(), &column).is_err());
+    }
+}
+
+mod zerofill {
+    use super::*;
+
+    #[test]
+    fn text_pads_to_width() {
+        let column = Column {
+            column_length: Some(3),
+            ..col(
+                ColumnType::MYSQL_TYPE_LONG,
+                ColumnFlags::ZEROFILL_FLAG | ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        7u32.to_mysql_text_with_column(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x03007");
+    }
+
+    #[test]
+    fn text_wider_than_width() {
+        let column = Column {
+            column_length: Some(3),
+            ..col(
+                ColumnType::MYSQL_TYPE_LONG,
+                ColumnFlags::ZEROFILL_FLAG | ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        12345u32
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x0512345");
+    }
+
+    #[test]
+    fn text_without_width() {
+        let column = Column {
+            column_length: None,
+            ..col(
+                ColumnType::MYSQL_TYPE_LONG,
+                ColumnFlags::ZEROFILL_FLAG | ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        7u32.to_mysql_text_with_column(&mut data, &column).unwrap();
+        assert_eq!(data, b"\x017");
+    }
+
+    #[test]
+    fn bin_unaffected() {
+        let column = Column {
+            column_length: Some(3),
+            ..col(
+                ColumnType::MYSQL_TYPE_LONG,
+                ColumnFlags::ZEROFILL_FLAG | ColumnFlags::UNSIGNED_FLAG,
+            )
+        };
+        let mut data = Vec::new();
+        7u32.to_mysql_bin(&mut data, &column).unwrap();
+        assert_eq!(data, 7u32.to_le_bytes());
+    }
+}
+
+mod strict_types {
+    use super::*;
+
+    #[test]
+    fn exact_type_is_accepted() {
+        let mut data = Vec::new();
+        7u8.to_mysql_bin_strict(
+            &mut data,
+            &col(ColumnType::MYSQL_TYPE_TINY, ColumnFlags::UNSIGNED_FLAG),
+        )
+        .unwrap();
+        assert_eq!(data, [7]);
+
+        let mut data = Vec::new();
+        7u64.to_mysql_bin_strict(
+            &mut data,
+            &col(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::UNSIGNED_FLAG),
+        )
+        .unwrap();
+        assert_eq!(data, 7u64.to_le_bytes());
+    }
+
+    #[test]
+    fn widening_is_rejected() {
+        let c = col(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::empty());
+        assert!(7u8.to_mysql_bin(&mut Vec::new(), &c).is_ok());
+        assert!(7u8.to_mysql_bin_strict(&mut Vec::new(), &c).is_err());
+    }
+
+    #[test]
+    fn narrowing_is_rejected() {
+        let c = col(ColumnType::MYSQL_TYPE_LONG, ColumnFlags::empty());
+        assert!(7u64.to_mysql_bin(&mut Vec::new(), &c).is_ok());
+        assert!(7u64.to_mysql_bin_strict(&mut Vec::new(), &c).is_err());
+    }
+
+    #[test]
+    fn forwarded_through_option_and_ref() {
+        let c = col(ColumnType::MYSQL_TYPE_LONG, ColumnFlags::UNSIGNED_FLAG);
+        assert!(Some(7u64).to_mysql_bin_strict(&mut Vec::new(), &c).is_err());
+        assert!((&7u32).to_mysql_bin_strict(&mut Vec::new(), &c).is_ok());
+    }
+
+    #[test]
+    fn sign_mismatch_is_rejected() {
+        let signed = col(ColumnType::MYSQL_TYPE_TINY, ColumnFlags::empty());
+        let unsigned = col(ColumnType::MYSQL_TYPE_TINY, ColumnFlags::UNSIGNED_FLAG);
+        assert!(7u8.to_mysql_bin(&mut Vec::new(), &signed).is_err());
+        assert!(7i8.to_mysql_bin(&mut Vec::new(), &unsigned).is_err());
+        assert!(7u8.to_mysql_bin_strict(&mut Vec::new(), &signed).is_err());
+
+        let signed = col(ColumnType::MYSQL_TYPE_LONG, ColumnFlags::empty());
+        assert!(7u32.to_mysql_bin(&mut Vec::new(), &signed).is_err());
+        assert!(7u16
+            .to_mysql_bin(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_SHORT, ColumnFlags::empty())
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn non_integers_unaffected() {
+        let c = col(ColumnType::MYSQL_TYPE_VAR_STRING, ColumnFlags::empty());
+        assert!("foo".to_mysql_bin_strict(&mut Vec::new(), &c).is_ok());
+    }
+}
+
+mod comma_joined {
+    use super::*;
+
+    #[test]
+    fn text() {
+        let mut data = Vec::new();
+        CommaJoined(&["a", "b"]).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x03a,b");
+    }
+
+    #[test]
+    fn bin() {
+        let mut data = Vec::new();
+        CommaJoined(&["a".to_owned(), "b".to_owned()])
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_SET, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, b"\x03a,b");
+    }
+
+    #[test]
+    fn numbers() {
+        let mut data = Vec::new();
+        CommaJoined(&[1u8, 20, 3])
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_SET, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, b"\x061,20,3");
+    }
+
+    #[test]
+    fn empty() {
+        let mut data = Vec::new();
+        CommaJoined::<&str>(&[]).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x00");
+    }
+
+    #[test]
+    fn rejects_commas_and_nulls() {
+        assert!(CommaJoined(&["a,b"])
+            .to_mysql_text(&mut Vec::new())
+            .is_err());
+        assert!(CommaJoined(&[Some("a"), None])
+            .to_mysql_text(&mut Vec::new())
+            .is_err());
+    }
+}
+
+mod naive_date_time {
+    use super::*;
+
+    fn dt() -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd(2022, 8, 17).and_hms_micro(12, 34, 56, 789)
+    }
+
+    #[test]
+    fn bin_truncates_to_date() {
+        let mut data = Vec::new();
+        dt().to_mysql_bin(
+            &mut data,
+            &col(ColumnType::MYSQL_TYPE_DATE, ColumnFlags::empty()),
+        )
+        .unwrap();
+        assert_eq!(data, vec![4u8, 0xe6, 0x07, 8, 17]);
+
+        let mut date = Vec::new();
+        dt().date()
+            .to_mysql_bin(
+                &mut date,
+                &col(ColumnType::MYSQL_TYPE_DATE, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, date);
+    }
+
+    #[test]
+    fn text_truncates_to_date() {
+        let mut data = Vec::new();
+        dt().to_mysql_text_with_column(
+            &mut data,
+            &col(ColumnType::MYSQL_TYPE_DATE, ColumnFlags::empty()),
+        )
+        .unwrap();
+        assert_eq!(data, b"\x0a2022-08-17");
+    }
+
+    #[test]
+    fn datetime_keeps_time() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_DATETIME,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        dt().to_mysql_bin(&mut data, &col).unwrap();
+        assert_eq!(data[0], 11);
+    }
+}
+
+mod null {
+    use super::*;
+    use crate::Null;
+
+    #[test]
+    fn text() {
+        let mut data = Vec::new();
+        Null.to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, vec![0xFB]);
+        assert!(Null.is_null());
+    }
+}
+
+mod scaled {
+    use super::*;
+    use crate::Scaled;
+
+    fn text(value: i64, scale: u8) -> String {
+        Scaled { value, scale }.to_string()
+    }
+
+    #[test]
+    fn cents() {
+        assert_eq!(text(12345, 2), "123.45");
+    }
+
+    #[test]
+    fn negative() {
+        assert_eq!(text(-12345, 2), "-123.45");
+    }
+
+    #[test]
+    fn smaller_than_scale() {
+        assert_eq!(text(5, 2), "0.05");
+        assert_eq!(text(-5, 2), "-0.05");
+        assert_eq!(text(0, 2), "0.00");
+    }
+
+    #[test]
+    fn no_scale() {
+        assert_eq!(text(42, 0), "42");
+        assert_eq!(text(-42, 0), "-42");
+    }
+
+    #[test]
+    fn min() {
+        assert_eq!(text(i64::MIN, 4), "-922337203685477.5808");
+    }
+
+    #[test]
+    fn decimal_column() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_NEWDECIMAL,
+            decimals: 2,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        Scaled {
+            value: 12345,
+            scale: 2,
+        }
+        .to_mysql_bin(&mut data, &col)
+        .unwrap();
+        assert_eq!(data, b"\x06123.45");
+
+        let mut data = Vec::new();
+        Scaled { value: 5, scale: 2 }
+            .to_mysql_text_with_column(&mut data, &col)
+            .unwrap();
+        assert_eq!(data, b"\x040.05");
+    }
+
+    #[test]
+    fn negative_unsigned_decimal_column() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_NEWDECIMAL,
+            colflags: ColumnFlags::UNSIGNED_FLAG,
+            decimals: 2,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        assert!(Scaled {
+            value: -5,
+            scale: 2
+        }
+        .to_mysql_bin(&mut data, &col)
+        .is_err());
+    }
+}
+
+mod decimal_range {
+    use super::*;
+
+    fn decimal_4_2() -> Column {
+        Column {
+            coltype: ColumnType::MYSQL_TYPE_NEWDECIMAL,
+            decimals: 2,
+            column_length: Some(4),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fits() {
+        for v in ["12.34", "-99.99", "0099.50", "0.5"] {
+            let mut data = Vec::new();
+            v.to_mysql_bin(&mut data, &decimal_4_2()).unwrap();
+            v.to_mysql_text_with_column(&mut data, &decimal_4_2())
+                .unwrap();
+        }
+        let mut data = Vec::new();
+        12.3456f64.to_mysql_bin(&mut data, &decimal_4_2()).unwrap();
+        assert_eq!(data, b"\x0512.35");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let err = 123.45f64
+            .to_mysql_bin(&mut Vec::new(), &decimal_4_2())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decimal \"123.45\" is out of range for MYSQL_TYPE_NEWDECIMAL(4,2)"
+        );
+
+        let err = "-100"
+            .to_mysql_text_with_column(&mut Vec::new(), &decimal_4_2())
+            .unwrap_err();
+        assert!(err.to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_DATE,
+            ..Default::default()
+        };
+        let err = 12.34f64.to_mysql_bin(&mut Vec::new(), &col).unwrap_err();
+        assert_eq!(err.to_string(), "tried to use 12.34 as MYSQL_TYPE_DATE");
+    }
+
+    #[test]
+    fn no_declared_precision() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_NEWDECIMAL,
+            decimals: 2,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        123456.78f64.to_mysql_bin(&mut data, &col).unwrap();
+        assert_eq!(data, b"\x09123456.78");
+    }
+}
+
+mod decimal_syntax {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        let column = Column {
+            decimals: 2,
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        for v in ["12.34", "-0.5", "+7", ".5", "7.", "0"] {
+            let mut data = Vec::new();
+            v.to_mysql_bin(&mut data, &column).unwrap();
+            v.to_mysql_text_with_column(&mut data, &column).unwrap();
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let column = Column {
+            decimals: 2,
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        for v in [
+            "", "-", ".", "abc", "1.2.3", "1e5", " 1", "12,5", "--1", "NaN",
+        ] {
+            assert!(v.to_mysql_bin(&mut Vec::new(), &column).is_err());
+            assert!(v
+                .to_mysql_text_with_column(&mut Vec::new(), &column)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn value_bytes() {
+        let column = Column {
+            decimals: 2,
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        let v = myc::value::Value::Bytes(b"12.x4".to_vec());
+        let err = v.to_mysql_bin(&mut Vec::new(), &column).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "tried to use \"12.x4\", which is not a decimal number, as MYSQL_TYPE_NEWDECIMAL"
+        );
+
+        let mut data = Vec::new();
+        myc::value::Value::Bytes(b"12.34".to_vec())
+            .to_mysql_bin(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x0512.34");
+    }
+
+    #[test]
+    fn owned_bytes_text() {
+        let column = Column {
+            decimals: 2,
+            ..col(ColumnType::MYSQL_TYPE_NEWDECIMAL, ColumnFlags::empty())
+        };
+        for v in [&b"12.x4"[..], b"-", b""] {
+            assert!(v
+                .to_vec()
+                .to_mysql_text_with_column(&mut Vec::new(), &column)
+                .is_err());
+            assert!(myc::value::Value::Bytes(v.to_vec())
+                .to_mysql_text_with_column(&mut Vec::new(), &column)
+                .is_err());
+        }
+
+        let unsigned = Column {
+            colflags: ColumnFlags::UNSIGNED_FLAG,
+            ..column.clone()
+        };
+        assert!(b"-1.50"
+            .to_vec()
+            .to_mysql_text_with_column(&mut Vec::new(), &unsigned)
+            .is_err());
+        assert!(myc::value::Value::Bytes(b"-1.50".to_vec())
+            .to_mysql_text_with_column(&mut Vec::new(), &unsigned)
+            .is_err());
+
+        let mut data = Vec::new();
+        myc::value::Value::Bytes(b"12.34".to_vec())
+            .to_mysql_text_with_column(&mut data, &column)
+            .unwrap();
+        assert_eq!(data, b"\x0512.34");
+    }
+
+    #[test]
+    fn other_columns() {
+        let mut data = Vec::new();
+        "abc".to_mysql_bin(&mut data, &Column::default()).unwrap();
+    }
+}
+
+mod lossy {
+    use super::*;
+
+    #[test]
+    fn integers_to_floats_fail_by_default() {
+        let double = col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty());
+        assert!(42u64.to_mysql_bin(&mut Vec::new(), &double).is_err());
+        assert!(42i32
+            .to_mysql_bin(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_FLOAT, ColumnFlags::empty())
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn double() {
+        let mut data = Vec::new();
+        Lossy(42u64)
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, 42f64.to_le_bytes());
+
+        let mut data = Vec::new();
+        Lossy(u64::MAX)
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, 18446744073709551616f64.to_le_bytes());
+    }
+
+    #[test]
+    fn float() {
+        let mut data = Vec::new();
+        Lossy(-7i64)
+            .to_mysql_bin_strict(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_FLOAT, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, (-7f32).to_le_bytes());
+    }
+
+    #[test]
+    fn integer_columns() {
+        let unsigned = Column {
+            coltype: ColumnType::MYSQL_TYPE_LONGLONG,
+            colflags: ColumnFlags::UNSIGNED_FLAG,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        Lossy(42u64).to_mysql_bin(&mut data, &unsigned).unwrap();
+        assert_eq!(data, 42u64.to_le_bytes());
+
+        let mut data = Vec::new();
+        Lossy(42u64).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x0242");
+
+        // strict mode still only sends integers as their own type
+        assert!(Lossy(42u8)
+            .to_mysql_bin_strict(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::empty())
+            )
+            .is_err());
+    }
+}
+
+mod utf8_bytes {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        let col = Column::default();
+        let mut data = Vec::new();
+        Utf8Bytes("héllo".as_bytes().to_vec())
+            .to_mysql_bin(&mut data, &col)
+            .unwrap();
+        assert_eq!(data, b"\x06h\xc3\xa9llo");
+
+        let mut data = Vec::new();
+        Utf8Bytes(&b"ok"[..])
+            .to_mysql_text_with_column(&mut data, &col)
+            .unwrap();
+        assert_eq!(data, b"\x02ok");
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = b"ab\xc3(cd".to_vec();
+        let err = Utf8Bytes(&bytes)
+            .to_mysql_text(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "invalid UTF-8 at byte 2 of \"ab\u{fffd}(cd\""
+        );
+        assert!(Utf8Bytes(&bytes)
+            .to_mysql_bin(&mut Vec::new(), &Column::default())
+            .is_err());
+        // unchecked, the same bytes are sent as they are
+        bytes.to_mysql_text(&mut Vec::new()).unwrap();
+    }
+}
+
+mod raw_cells {
+    use super::*;
+    use crate::{RawBinCell, RawTextCell};
+    use myc::proto::MySerialize;
+
+    #[test]
+    fn text_is_forwarded() {
+        let mut upstream = Vec::new();
+        "héllo".to_mysql_text(&mut upstream).unwrap();
+
+        let mut data = Vec::new();
+        RawTextCell(&upstream[..]).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, upstream);
+    }
+
+    #[test]
+    fn text_null_is_forwarded() {
+        let mut data = Vec::new();
+        RawTextCell(vec![0xFB])
+            .to_mysql_text_with_column(&mut data, &Column::default())
+            .unwrap();
+        assert_eq!(data, vec![0xFB]);
+    }
+
+    #[test]
+    fn bin_is_forwarded() {
+        let col = Column {
+            coltype: ColumnType::MYSQL_TYPE_LONGLONG,
+            ..Default::default()
+        };
+        let mut upstream = Vec::new();
+        myc::value::Value::Int(-42).serialize(&mut upstream);
+
+        let mut data = Vec::new();
+        RawBinCell(upstream.clone())
+            .to_mysql_bin(&mut data, &col)
+            .unwrap();
+        assert_eq!(data, upstream);
+    }
+
+    #[test]
+    fn wrong_protocol() {
+        assert!(RawTextCell(b"\x01a")
+            .to_mysql_bin(&mut Vec::new(), &Column::default())
+            .is_err());
+        assert!(RawBinCell(b"\x01a").to_mysql_text(&mut Vec::new()).is_err());
+    }
+}
+
+mod json_array {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        let mut data = Vec::new();
+        JsonArray(vec![1, 2, 3])
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_JSON, ColumnFlags::empty()),
+            )
+            .unwrap();
+        assert_eq!(data, b"\x07[1,2,3]");
+    }
+
+    #[test]
+    fn strings() {
+        let mut data = Vec::new();
+        JsonArray(vec!["a", "say \"hi\"\n", "\u{1}"])
+            .to_mysql_text(&mut data)
+            .unwrap();
+        assert_eq!(&data[1..], br#"["a","say \"hi\"\n","\u0001"]"#);
+    }
+
+    #[test]
+    fn floats_and_bools() {
+        let mut data = Vec::new();
+        JsonArray(vec![1.5f64, -2.0])
+            .to_mysql_text(&mut data)
+            .unwrap();
+        assert_eq!(data, b"\x08[1.5,-2]");
+
+        data.clear();
+        JsonArray(vec![true, false])
+            .to_mysql_text(&mut data)
+            .unwrap();
+        assert_eq!(data, b"\x0c[true,false]");
+    }
+
+    #[test]
+    fn empty() {
+        let mut data = Vec::new();
+        JsonArray::<i64>(vec![]).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x02[]");
+    }
+
+    #[test]
+    fn non_finite() {
+        let mut data = Vec::new();
+        assert!(JsonArray(vec![f64::NAN]).to_mysql_text(&mut data).is_err());
+    }
+}
+
+mod non_finite {
+    use super::*;
+
+    #[test]
+    fn rejected() {
+        let double = col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty());
+        let float = col(ColumnType::MYSQL_TYPE_FLOAT, ColumnFlags::empty());
+        for v in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(v.to_mysql_text(&mut Vec::new()).is_err());
+            assert!(v
+                .to_mysql_text_with_column(&mut Vec::new(), &double)
+                .is_err());
+            assert!(v.to_mysql_bin(&mut Vec::new(), &double).is_err());
+
+            let v = v as f32;
+            assert!(v.to_mysql_text(&mut Vec::new()).is_err());
+            assert!(v
+                .to_mysql_text_with_column(&mut Vec::new(), &float)
+                .is_err());
+            assert!(v.to_mysql_bin(&mut Vec::new(), &float).is_err());
+        }
+    }
+
+    #[test]
+    fn as_null() {
+        let double = col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty());
+        for v in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(FiniteOrNull(v).is_null());
+            assert!(FiniteOrNull(v as f32).is_null());
+
+            let mut data = Vec::new();
+            FiniteOrNull(v)
+                .to_mysql_text_with_column(&mut data, &double)
+                .unwrap();
+            assert_eq!(data, [0xfb]);
+        }
+    }
+
+    #[test]
+    fn finite() {
+        let double = col(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty());
+        assert!(!FiniteOrNull(1.5f64).is_null());
+
+        let mut data = Vec::new();
+        FiniteOrNull(1.5f64).to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x031.5");
+
+        let mut data = Vec::new();
+        FiniteOrNull(1.5f64)
+            .to_mysql_bin(&mut data, &double)
+            .unwrap();
+        assert_eq!(data, 1.5f64.to_le_bytes());
+    }
+}
+
+mod null_if_empty {
+    use super::*;
+
+    fn blob() -> Column {
+        Column {
+            coltype: ColumnType::MYSQL_TYPE_BLOB,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn empty_bytes_are_not_null_by_default() {
+        let v: Vec<u8> = Vec::new();
+        assert!(!v.is_null());
+
+        let mut data = Vec::new();
+        v.to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, [0x00]);
+    }
+
+    #[test]
+    fn empty() {
+        let v = NullIfEmpty(Vec::<u8>::new());
+        assert!(v.is_null());
+
+        let mut data = Vec::new();
+        v.to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, [0xfb]);
+
+        let mut data = Vec::new();
+        v.to_mysql_text_with_column(&mut data, &blob()).unwrap();
+        assert_eq!(data, [0xfb]);
+    }
+
+    #[test]
+    fn not_empty() {
+        let v = NullIfEmpty(b"abc".to_vec());
+        assert!(!v.is_null());
+
+        let mut data = Vec::new();
+        v.to_mysql_text(&mut data).unwrap();
+        assert_eq!(data, b"\x03abc");
+
+        let mut data = Vec::new();
+        v.to_mysql_bin(&mut data, &blob()).unwrap();
+        assert_eq!(data, b"\x03abc");
+    }
+}
+
+mod downcast {
+    use super::*;
+
+    const SIGNED: ColumnFlags = ColumnFlags::empty();
+    const UNSIGNED: ColumnFlags = ColumnFlags::UNSIGNED_FLAG;
+
+    #[test]
+    fn i64_out_of_range() {
+        let cases = [
+            (128i64, ColumnType::MYSQL_TYPE_TINY, SIGNED),
+            (-129, ColumnType::MYSQL_TYPE_TINY, SIGNED),
+            (256, ColumnType::MYSQL_TYPE_TINY, UNSIGNED),
+            (-1, ColumnType::MYSQL_TYPE_TINY, UNSIGNED),
+            (32768, ColumnType::MYSQL_TYPE_SHORT, SIGNED),
+            (65536, ColumnType::MYSQL_TYPE_SHORT, UNSIGNED),
+            (2147483648, ColumnType::MYSQL_TYPE_LONG, SIGNED),
+            (-1, ColumnType::MYSQL_TYPE_LONGLONG, UNSIGNED),
+        ];
+        for (v, coltype, colflags) in cases {
+            let err = v
+                .to_mysql_bin(&mut Vec::new(), &col(coltype, colflags))
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn u64_out_of_range() {
+        let cases = [
+            (128u64, ColumnType::MYSQL_TYPE_TINY, SIGNED),
+            (256, ColumnType::MYSQL_TYPE_TINY, UNSIGNED),
+            (65536, ColumnType::MYSQL_TYPE_SHORT, UNSIGNED),
+            (4294967296, ColumnType::MYSQL_TYPE_LONG, UNSIGNED),
+            (u64::MAX, ColumnType::MYSQL_TYPE_LONGLONG, SIGNED),
+        ];
+        for (v, coltype, colflags) in cases {
+            assert!(v
+                .to_mysql_bin(&mut Vec::new(), &col(coltype, colflags))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn narrowed_bytes() {
+        let mut data = Vec::new();
+        (-2i64)
+            .to_mysql_bin(&mut data, &col(ColumnType::MYSQL_TYPE_SHORT, SIGNED))
+            .unwrap();
+        assert_eq!(data, (-2i16).to_le_bytes());
+
+        let mut data = Vec::new();
+        300u64
+            .to_mysql_bin(&mut data, &col(ColumnType::MYSQL_TYPE_LONG, UNSIGNED))
+            .unwrap();
+        assert_eq!(data, 300u32.to_le_bytes());
+    }
+
+    #[test]
+    fn strict_types_still_need_longlong() {
+        assert!(1i64
+            .to_mysql_bin_strict(&mut Vec::new(), &col(ColumnType::MYSQL_TYPE_LONG, SIGNED))
+            .is_err());
+    }
+}
+
+mod huge_unsigned {
+    use super::*;
+
+    #[test]
+    fn text_has_every_digit() {
+        let mut data = Vec::new();
+        myc::value::Value::UInt(u64::MAX)
+            .to_mysql_text(&mut data)
+            .unwrap();
+        assert_eq!(data[0], 20);
+        assert_eq!(&data[1..], b"18446744073709551615");
+
+        let mut data = Vec::new();
+        myc::value::Value::UInt(i64::MAX as u64 + 1)
+            .to_mysql_text(&mut data)
+            .unwrap();
+        assert_eq!(&data[1..], b"9223372036854775808");
+    }
+
+    #[test]
+    fn bin_is_not_signed() {
+        let mut data = Vec::new();
+        myc::value::Value::UInt(u64::MAX)
+            .to_mysql_bin(
+                &mut data,
+                &col(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::UNSIGNED_FLAG),
+            )
+            .unwrap();
+        assert_eq!(data, u64::MAX.to_le_bytes());
+
+        // it would read as -1 in a signed column
+        assert!(myc::value::Value::UInt(u64::MAX)
+            .to_mysql_bin(
+                &mut Vec::new(),
+                &col(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::empty())
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::myc::constants::ColumnType;
+use crate::variables::{is_set, like, sql_mode_set_by};
+use crate::SystemVariables;
+
+fn vars() -> SystemVariables {
+    SystemVariables::default().with_default_version("8.0.26")
+}
+
+#[test]
+fn select_one() {
+    let (cols, rows) = vars().answer("SELECT @@version").unwrap();
+    assert_eq!(cols.len(), 1);
+    assert_eq!(cols[0].column, "@@version");
+    assert_eq!(cols[0].coltype, ColumnType::MYSQL_TYPE_VAR_STRING);
+    assert_eq!(rows, vec![vec!["8.0.26".to_owned()]]);
+}
+
+#[test]
+fn select_many() {
+    let (cols, rows) = vars()
+        .answer("select @@session.max_allowed_packet, @@GLOBAL.Wait_Timeout AS t limit 1;")
+        .unwrap();
+    assert_eq!(cols[0].column, "@@session.max_allowed_packet");
+    assert_eq!(cols[0].coltype, ColumnType::MYSQL_TYPE_LONGLONG);
+    assert_eq!(cols[1].column, "t");
+    assert_eq!(rows, vec![vec!["67108864".to_owned(), "28800".to_owned()]]);
+}
+
+#[test]
+fn select_unknown() {
+    assert!(vars().answer("SELECT @@version, @@nope").is_none());
+    assert!(vars().answer("SELECT 1").is_none());
+    assert!(vars().answer("SELECT @@version FROM t").is_none());
+}
+
+#[test]
+fn show_variables_like() {
+    let (cols, rows) = vars().answer("SHOW VARIABLES LIKE 'version'").unwrap();
+    assert_eq!(cols[0].column, "Variable_name");
+    assert_eq!(cols[1].column, "Value");
+    assert_eq!(rows, vec![vec!["version".to_owned(), "8.0.26".to_owned()]]);
+
+    let (_, rows) = vars()
+        .answer("show session variables like 'character\\_set\\_%'")
+        .unwrap();
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|r| r[0].starts_with("character_set_")));
+}
+
+#[test]
+fn show_status() {
+    let vars = vars().with_status("Uptime", "42");
+    let (_, rows) = vars.answer("SHOW GLOBAL STATUS").unwrap();
+    assert_eq!(rows, vec![vec!["Uptime".to_owned(), "42".to_owned()]]);
+    let (_, rows) = vars.answer("SHOW STATUS LIKE 'threads%'").unwrap();
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn version_override() {
+    let vars = SystemVariables::empty()
+        .with_variable("VERSION", "5.7.0")
+        .with_default_version("8.0.26");
+    assert_eq!(vars.variable("version"), Some("5.7.0"));
+}
+
+#[test]
+fn like_patterns() {
+    assert!(like(b"ver%", b"version"));
+    assert!(like(b"%SION", b"version"));
+    assert!(like(b"v_rsion", b"version"));
+    assert!(!like(b"v_rsion", b"vrsion"));
+    assert!(like(b"a\\_b", b"a_b"));
+    assert!(!like(b"a\\_b", b"axb"));
+    assert!(like(b"%", b""));
+}
+
+#[test]
+fn sql_mode_is_set() {
+    for query in [
+        "SET sql_mode = 'NO_BACKSLASH_ESCAPES'",
+        "set SESSION sql_mode='NO_BACKSLASH_ESCAPES';",
+        "SET @@sql_mode := \"NO_BACKSLASH_ESCAPES\"",
+        "SET @@local.SQL_MODE = 'NO_BACKSLASH_ESCAPES'",
+    ] {
+        assert_eq!(
+            sql_mode_set_by(query).as_deref(),
+            Some("NO_BACKSLASH_ESCAPES"),
+            "{}",
+            query
+        );
+    }
+    assert_eq!(
+        sql_mode_set_by("SET sql_mode = DEFAULT").as_deref(),
+        Some("")
+    );
+}
+
+#[test]
+fn sql_mode_is_not_set() {
+    for query in [
+        "SELECT @@sql_mode",
+        "SET GLOBAL sql_mode = 'NO_BACKSLASH_ESCAPES'",
+        "SET sql_mode_x = 'NO_BACKSLASH_ESCAPES'",
+        "SET sql_mode = 'ANSI', autocommit = 'ON'",
+        "SET sql_mode = CONCAT(@@sql_mode, ',ANSI')",
+    ] {
+        assert_eq!(sql_mode_set_by(query), None, "{}", query);
+    }
+}
+
+#[test]
+fn set_statements() {
+    for query in [
+        "SET autocommit=1",
+        "SET NAMES utf8mb4",
+        "  set SESSION sql_mode = 'ANSI';",
+        "SET\tcharacter_set_results = NULL",
+    ] {
+        assert!(is_set(query), "{}", query);
+    }
+    for query in [
+        "SELECT 1",
+        "SETTINGS",
+        "SHOW VARIABLES",
+        "RESET QUERY CACHE",
+    ] {
+        assert!(!is_set(query), "{}", query);
+    }
+}
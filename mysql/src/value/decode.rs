@@ -112,7 +112,8 @@ impl<'a> ValueInner<'a> {
             | ColumnType::MYSQL_TYPE_BIT
             | ColumnType::MYSQL_TYPE_NEWDECIMAL
             | ColumnType::MYSQL_TYPE_GEOMETRY
-            | ColumnType::MYSQL_TYPE_JSON => {
+            | ColumnType::MYSQL_TYPE_JSON
+            | ColumnType::MYSQL_TYPE_TYPED_ARRAY => {
                 let len = input.read_lenenc_int()?;
                 Ok(ValueInner::Bytes(read_bytes!(input, len)?))
             }
@@ -293,3 +294,219 @@ impl<'a> From<Value<'a>> for Duration {
         }
     }
 }
+
+/// A `MYSQL_TYPE_TIME` value split into its fields, as it is sent in the binary protocol.
+///
+/// Times range from `-838:59:59` to `838:59:59`, so they may be negative, and span days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MysqlTime {
+    /// Whether the time is negative.
+    pub negative: bool,
+    /// The number of whole days.
+    pub days: u32,
+    /// The hours past the days, from 0 to 23.
+    pub hours: u8,
+    /// The minutes past the hours, from 0 to 59.
+    pub minutes: u8,
+    /// The seconds past the minutes, from 0 to 59.
+    pub seconds: u8,
+    /// The microseconds past the seconds.
+    pub microseconds: u32,
+}
+
+/// Decodes any of the 0-byte (zero), 8-byte and 12-byte (with microseconds) binary forms of a
+/// `MYSQL_TYPE_TIME`, and returns an error for values of any other type.
+impl<'a, 'b> TryFrom<&'b Value<'a>> for MysqlTime {
+    type Error = io::Error;
+
+    fn try_from(val: &'b Value<'a>) -> io::Result<Self> {
+        let mut v = match val.0 {
+            ValueInner::Time(v) if v.is_empty() || v.len() == 8 || v.len() == 12 => v,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid type conversion from {:?} to time", val),
+                ))
+            }
+        };
+
+        if v.is_empty() {
+            return Ok(MysqlTime::default());
+        }
+
+        Ok(MysqlTime {
+            negative: v.read_u8()? != 0,
+            days: v.read_u32::<LittleEndian>()?,
+            hours: v.read_u8()?,
+            minutes: v.read_u8()?,
+            seconds: v.read_u8()?,
+            microseconds: if v.is_empty() {
+                0
+            } else {
+                v.read_u32::<LittleEndian>()?
+            },
+        })
+    }
+}
+
+/// Unlike the conversion to `std::time::Duration`, this also accepts negative times, and returns
+/// an error rather than panicking for values that are not a `MYSQL_TYPE_TIME`.
+impl<'a, 'b> TryFrom<&'b Value<'a>> for chrono::Duration {
+    type Error = io::Error;
+
+    fn try_from(val: &'b Value<'a>) -> io::Result<Self> {
+        let t = MysqlTime::try_from(val).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid type conversion from {:?} to duration", val),
+            )
+        })?;
+
+        let d = chrono::Duration::days(i64::from(t.days))
+            + chrono::Duration::hours(i64::from(t.hours))
+            + chrono::Duration::minutes(i64::from(t.minutes))
+            + chrono::Duration::seconds(i64::from(t.seconds))
+            + chrono::Duration::microseconds(i64::from(t.microseconds));
+        Ok(if t.negative { -d } else { d })
+    }
+}
+
+/// Decodes a geometry in the MySQL internal format, i.e. a little-endian `u32` SRID followed by
+/// the [WKB](https://dev.mysql.com/doc/refman/8.0/en/gis-data-formats.html#gis-wkb-format)
+/// representation, into the SRID and the geometry.
+///
+/// This is how `MYSQL_TYPE_GEOMETRY` parameters arrive, as well as any byte string produced by
+/// e.g. `ST_GeomFromText` on the client side.
+#[cfg(feature = "geo")]
+impl<'a, 'b> TryFrom<&'b Value<'a>> for (u32, geo_types::Geometry<f64>) {
+    type Error = io::Error;
+
+    fn try_from(val: &'b Value<'a>) -> io::Result<Self> {
+        let mut v = match val.0 {
+            ValueInner::Bytes(v) => v,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid type conversion from {:?} to geometry", val),
+                ))
+            }
+        };
+
+        let srid = v.read_u32::<LittleEndian>()?;
+        let geometry = geometry::read_wkb(&mut v, 0)?;
+        if !v.is_empty() {
+            return Err(geometry::invalid("trailing bytes after geometry"));
+        }
+        Ok((srid, geometry))
+    }
+}
+
+#[cfg(feature = "geo")]
+mod geometry {
+    use std::io;
+
+    use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+    use geo_types::{
+        Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+        Polygon,
+    };
+
+    // geometry collections may nest, so bound the recursion on untrusted input
+    const MAX_DEPTH: usize = 64;
+
+    pub(super) fn invalid(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid WKB: {}", msg))
+    }
+
+    pub(super) fn read_wkb(input: &mut &[u8], depth: usize) -> io::Result<Geometry<f64>> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("geometry is nested too deeply"));
+        }
+        match input.read_u8()? {
+            0 => read_geometry::<BigEndian>(input, depth),
+            1 => read_geometry::<LittleEndian>(input, depth),
+            b => Err(invalid(&format!("unknown byte order {}", b))),
+        }
+    }
+
+    fn read_geometry<B: ByteOrder>(input: &mut &[u8], depth: usize) -> io::Result<Geometry<f64>> {
+        Ok(match input.read_u32::<B>()? {
+            1 => Geometry::Point(read_point::<B>(input)?),
+            2 => Geometry::LineString(read_line_string::<B>(input)?),
+            3 => Geometry::Polygon(read_polygon::<B>(input)?),
+            4 => Geometry::MultiPoint(MultiPoint(read_many::<B, _, _>(
+                input,
+                depth,
+                |g| match g {
+                    Geometry::Point(p) => Some(p),
+                    _ => None,
+                },
+            )?)),
+            5 => Geometry::MultiLineString(MultiLineString(read_many::<B, _, _>(
+                input,
+                depth,
+                |g| match g {
+                    Geometry::LineString(l) => Some(l),
+                    _ => None,
+                },
+            )?)),
+            6 => Geometry::MultiPolygon(MultiPolygon(read_many::<B, _, _>(
+                input,
+                depth,
+                |g| match g {
+                    Geometry::Polygon(p) => Some(p),
+                    _ => None,
+                },
+            )?)),
+            7 => Geometry::GeometryCollection(GeometryCollection(read_many::<B, _, _>(
+                input, depth, Some,
+            )?)),
+            t => return Err(invalid(&format!("unknown geometry type {}", t))),
+        })
+    }
+
+    fn read_point<B: ByteOrder>(input: &mut &[u8]) -> io::Result<Point<f64>> {
+        let x = input.read_f64::<B>()?;
+        let y = input.read_f64::<B>()?;
+        Ok(Point::new(x, y))
+    }
+
+    fn read_line_string<B: ByteOrder>(input: &mut &[u8]) -> io::Result<LineString<f64>> {
+        let n = input.read_u32::<B>()?;
+        // the count is untrusted, so let the reads run out of input rather than preallocating
+        let mut points = Vec::new();
+        for _ in 0..n {
+            points.push(read_point::<B>(input)?);
+        }
+        Ok(LineString::from(points))
+    }
+
+    fn read_polygon<B: ByteOrder>(input: &mut &[u8]) -> io::Result<Polygon<f64>> {
+        let n = input.read_u32::<B>()?;
+        if n == 0 {
+            return Ok(Polygon::new(LineString(Vec::new()), Vec::new()));
+        }
+        let exterior = read_line_string::<B>(input)?;
+        let mut interiors = Vec::new();
+        for _ in 1..n {
+            interiors.push(read_line_string::<B>(input)?);
+        }
+        Ok(Polygon::new(exterior, interiors))
+    }
+
+    /// Reads the members of a multi-geometry, each of which is a complete WKB geometry with its
+    /// own byte order, and checks that they are of the expected type.
+    fn read_many<B, T, F>(input: &mut &[u8], depth: usize, member: F) -> io::Result<Vec<T>>
+    where
+        B: ByteOrder,
+        F: Fn(Geometry<f64>) -> Option<T>,
+    {
+        let n = input.read_u32::<B>()?;
+        let mut members = Vec::new();
+        for _ in 0..n {
+            let g = read_wkb(input, depth + 1)?;
+            members.push(member(g).ok_or_else(|| invalid("unexpected member geometry type"))?);
+        }
+        Ok(members)
+    }
+}
@@ -12,13 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
 use std::io::{self, Write};
 
 use byteorder::{LittleEndian, WriteBytesExt};
 
 use crate::myc;
 use crate::myc::constants::{ColumnFlags, ColumnType};
-use crate::myc::io::WriteMysqlExt;
+use crate::myc::io::{ReadMysqlExt, WriteMysqlExt};
 use crate::Column;
 
 /// Implementors of this trait can be sent as a single resultset value to a MySQL/MariaDB client.
@@ -26,9 +27,27 @@ pub trait ToMysqlValue {
     /// Encode value using the text-based protocol.
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()>;
 
+    /// Encode value using the text-based protocol, given the column it is sent as.
+    ///
+    /// Most values are formatted the same way regardless of the column they are sent as, so this
+    /// defaults to [`to_mysql_text`](trait.ToMysqlValue.html#tymethod.to_mysql_text).
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, _c: &Column) -> io::Result<()> {
+        self.to_mysql_text(w)
+    }
+
     /// Encode value using the binary protocol.
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()>;
 
+    /// Encode value using the binary protocol, without converting integers to a column type other
+    /// than their own.
+    ///
+    /// This is used instead of [`to_mysql_bin`](trait.ToMysqlValue.html#tymethod.to_mysql_bin)
+    /// when [`IntermediaryOptions::strict_types`](struct.IntermediaryOptions.html#structfield.strict_types)
+    /// is set, and defaults to it.
+    fn to_mysql_bin_strict<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.to_mysql_bin(w, c)
+    }
+
     /// Is this value NULL?
     fn is_null(&self) -> bool {
         false
@@ -41,17 +60,60 @@ macro_rules! mysql_text_trivial {
             w.write_lenenc_str(format!("{}", self).as_bytes())
                 .map(|_| ())
         }
+        fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+            write_text_with_column(self, w, c)
+        }
+    };
+}
+
+// integers are only sent as their own column type in strict mode
+macro_rules! strict_integer {
+    ($($ct:ident),+) => {
+        fn to_mysql_bin_strict<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+            match c.coltype {
+                $(ColumnType::$ct)|+ => self.to_mysql_bin(w, c),
+                _ => Err(bad(self, c)),
+            }
+        }
     };
 }
 
 use std::fmt;
+fn write_text_with_column<T: fmt::Display, W: Write>(
+    v: &T,
+    w: &mut W,
+    c: &Column,
+) -> io::Result<()> {
+    // ZEROFILL columns are left-padded with zeros up to their display width
+    let s = match c.column_length {
+        Some(width) if c.colflags.contains(ColumnFlags::ZEROFILL_FLAG) => {
+            format!("{:0width$}", v, width = width as usize)
+        }
+        _ => format!("{}", v),
+    };
+    w.write_lenenc_str(s.as_bytes()).map(|_| ())
+}
+
 fn bad<V: fmt::Debug>(v: V, c: &Column) -> io::Error {
     io::Error::new(
         io::ErrorKind::InvalidData,
-        format!("tried to use {:?} as {:?}", v, c.coltype),
+        format!("tried to use {:?} as {}", v, column_type(c)),
     )
 }
 
+// the column's type along with its declared width, e.g. `MYSQL_TYPE_NEWDECIMAL(4,2)`, so that
+// errors tell a value that doesn't fit from one of the wrong type
+fn column_type(c: &Column) -> String {
+    match c.column_length {
+        // 31 (NOT_FIXED_DEC) means the number of decimals is not fixed
+        Some(len) if c.decimals > 0 && c.decimals < 31 => {
+            format!("{:?}({},{})", c.coltype, len, c.decimals)
+        }
+        Some(len) => format!("{:?}({})", c.coltype, len),
+        None => format!("{:?}", c.coltype),
+    }
+}
+
 impl<T> ToMysqlValue for Option<T>
 where
     T: ToMysqlValue,
@@ -64,6 +126,14 @@ where
         }
     }
 
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        if let Some(ref v) = *self {
+            v.to_mysql_text_with_column(w, c)
+        } else {
+            w.write_u8(0xFB)
+        }
+    }
+
     fn to_mysql_bin<W: Write>(&self, w: &mut W, ct: &Column) -> io::Result<()> {
         if let Some(ref v) = *self {
             v.to_mysql_bin(w, ct)
@@ -73,32 +143,53 @@ where
         }
     }
 
+    fn to_mysql_bin_strict<W: Write>(&self, w: &mut W, ct: &Column) -> io::Result<()> {
+        if let Some(ref v) = *self {
+            v.to_mysql_bin_strict(w, ct)
+        } else {
+            // should be handled by NULL map
+            unreachable!();
+        }
+    }
+
     fn is_null(&self) -> bool {
         self.is_none()
     }
 }
 
-// NOTE: these rules can all go away when TryFrom stabilizes
-//       see https://github.com/jonhoo/msql-srv/commit/13e5e753e5042a42cc45ad57c2b760561da2fb50
+/// A NULL value of no particular type, for when there is no `T` to write `None::<T>` with, e.g.
+/// `row.write_col(Null)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Null;
+
+impl ToMysqlValue for Null {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_u8(0xFB)
+    }
+
+    fn to_mysql_bin<W: Write>(&self, _: &mut W, _: &Column) -> io::Result<()> {
+        // should be handled by NULL map
+        unreachable!();
+    }
+
+    fn is_null(&self) -> bool {
+        true
+    }
+}
+
 // NOTE: yes, I know the = / => distinction is ugly
+// the bounds are checked with TryFrom, since casting them to the source type, e.g. `i64::MIN` to
+// `u64`, wraps around when only one of the types is signed
 macro_rules! like_try_into {
     ($self:ident, $source:ty = $target:ty, $w:ident, $m:ident, $c:ident) => {{
-        let min = <$target>::min_value() as $source;
-        let max = <$target>::max_value() as $source;
-        if *$self <= max && *$self >= min {
-            $w.$m(*$self as $target)
-        } else {
-            Err(bad($self, $c))
-        }
+        <$target>::try_from(*$self)
+            .map_err(|_| bad($self, $c))
+            .and_then(|v| $w.$m(v))
     }};
     ($self:ident, $source:ty => $target:ty, $w:ident, $m:ident, $c:ident) => {{
-        let min = <$target>::min_value() as $source;
-        let max = <$target>::max_value() as $source;
-        if *$self <= max && *$self >= min {
-            $w.$m::<LittleEndian>(*$self as $target)
-        } else {
-            Err(bad($self, $c))
-        }
+        <$target>::try_from(*$self)
+            .map_err(|_| bad($self, $c))
+            .and_then(|v| $w.$m::<LittleEndian>(v))
     }};
 }
 
@@ -106,6 +197,9 @@ macro_rules! forgiving_numeric {
     ($t:ty) => {
         impl ToMysqlValue for $t {
             mysql_text_trivial!();
+            strict_integer!(MYSQL_TYPE_LONGLONG);
+            // e.g. i64::try_from on an i64
+            #[allow(clippy::useless_conversion)]
             fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
                 let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
                 match c.coltype {
@@ -146,9 +240,13 @@ macro_rules! forgiving_numeric {
 
 forgiving_numeric!(usize);
 forgiving_numeric!(isize);
+// like `myc::value::Value::Int`, 64-bit integers in range are sent as narrower columns too
+forgiving_numeric!(u64);
+forgiving_numeric!(i64);
 
 impl ToMysqlValue for u8 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_TINY);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -174,7 +272,9 @@ impl ToMysqlValue for u8 {
                 }
             }
             ColumnType::MYSQL_TYPE_TINY => {
-                assert!(!signed);
+                if signed {
+                    return Err(bad(self, c));
+                }
                 w.write_u8(*self)
             }
             _ => Err(bad(self, c)),
@@ -184,6 +284,7 @@ impl ToMysqlValue for u8 {
 
 impl ToMysqlValue for i8 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_TINY);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -209,7 +310,9 @@ impl ToMysqlValue for i8 {
                 }
             }
             ColumnType::MYSQL_TYPE_TINY => {
-                assert!(signed);
+                if !signed {
+                    return Err(bad(self, c));
+                }
                 w.write_i8(*self)
             }
             _ => Err(bad(self, c)),
@@ -219,6 +322,7 @@ impl ToMysqlValue for i8 {
 
 impl ToMysqlValue for u16 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_SHORT, MYSQL_TYPE_YEAR);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -237,7 +341,9 @@ impl ToMysqlValue for u16 {
                 }
             }
             ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
-                assert!(!signed);
+                if signed {
+                    return Err(bad(self, c));
+                }
                 w.write_u16::<LittleEndian>(*self)
             }
             _ => Err(bad(self, c)),
@@ -247,6 +353,7 @@ impl ToMysqlValue for u16 {
 
 impl ToMysqlValue for i16 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_SHORT, MYSQL_TYPE_YEAR);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -265,7 +372,9 @@ impl ToMysqlValue for i16 {
                 }
             }
             ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
-                assert!(signed);
+                if !signed {
+                    return Err(bad(self, c));
+                }
                 w.write_i16::<LittleEndian>(*self)
             }
             _ => Err(bad(self, c)),
@@ -275,6 +384,7 @@ impl ToMysqlValue for i16 {
 
 impl ToMysqlValue for u32 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_LONG, MYSQL_TYPE_INT24);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -286,7 +396,9 @@ impl ToMysqlValue for u32 {
                 }
             }
             ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
-                assert!(!signed);
+                if signed {
+                    return Err(bad(self, c));
+                }
                 w.write_u32::<LittleEndian>(*self)
             }
             _ => Err(bad(self, c)),
@@ -296,6 +408,7 @@ impl ToMysqlValue for u32 {
 
 impl ToMysqlValue for i32 {
     mysql_text_trivial!();
+    strict_integer!(MYSQL_TYPE_LONG, MYSQL_TYPE_INT24);
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
         match c.coltype {
@@ -307,7 +420,9 @@ impl ToMysqlValue for i32 {
                 }
             }
             ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
-                assert!(signed);
+                if !signed {
+                    return Err(bad(self, c));
+                }
                 w.write_i32::<LittleEndian>(*self)
             }
             _ => Err(bad(self, c)),
@@ -315,50 +430,89 @@ impl ToMysqlValue for i32 {
     }
 }
 
-impl ToMysqlValue for u64 {
-    mysql_text_trivial!();
-    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
-        let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
-        match c.coltype {
-            ColumnType::MYSQL_TYPE_LONGLONG => {
-                assert!(!signed);
-                w.write_u64::<LittleEndian>(*self)
-            }
-            _ => Err(bad(self, c)),
+// floats sent as a DECIMAL are formatted with the column's decimals, rounding as needed; callers
+// reject non-finite values with `check_finite` first
+macro_rules! float_as_decimal {
+    ($self:ident, $c:ident) => {{
+        if $c.decimals < 31 {
+            // 31 (NOT_FIXED_DEC) means the number of decimals is not fixed
+            format!("{:.*}", usize::from($c.decimals), $self)
+        } else {
+            format!("{}", $self)
         }
-    }
+    }};
 }
 
-impl ToMysqlValue for i64 {
-    mysql_text_trivial!();
-    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
-        let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
-        match c.coltype {
-            ColumnType::MYSQL_TYPE_LONGLONG => {
-                assert!(signed);
-                w.write_i64::<LittleEndian>(*self)
-            }
-            _ => Err(bad(self, c)),
-        }
+fn is_decimal(c: &Column) -> bool {
+    matches!(
+        c.coltype,
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL
+    )
+}
+
+// MySQL has no representation for NaN and infinities, which clients fail to parse as text
+fn check_finite(v: f64) -> io::Result<()> {
+    if v.is_finite() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "tried to send {}, which MySQL cannot represent; use FiniteOrNull to send NULL instead",
+                v
+            ),
+        ))
     }
 }
 
 impl ToMysqlValue for f32 {
-    mysql_text_trivial!();
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        check_finite(f64::from(*self))?;
+        w.write_lenenc_str(format!("{}", self).as_bytes())
+            .map(|_| ())
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        check_finite(f64::from(*self))?;
+        if is_decimal(c) {
+            float_as_decimal!(self, c).to_mysql_text_with_column(w, c)
+        } else {
+            write_text_with_column(self, w, c)
+        }
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        check_finite(f64::from(*self))?;
         match c.coltype {
             ColumnType::MYSQL_TYPE_DOUBLE => w.write_f64::<LittleEndian>(f64::from(*self)),
             ColumnType::MYSQL_TYPE_FLOAT => w.write_f32::<LittleEndian>(*self),
+            ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                float_as_decimal!(self, c).to_mysql_bin(w, c)
+            }
             _ => Err(bad(self, c)),
         }
     }
 }
 
 impl ToMysqlValue for f64 {
-    mysql_text_trivial!();
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        check_finite(*self)?;
+        w.write_lenenc_str(format!("{}", self).as_bytes())
+            .map(|_| ())
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        check_finite(*self)?;
+        if is_decimal(c) {
+            float_as_decimal!(self, c).to_mysql_text_with_column(w, c)
+        } else {
+            write_text_with_column(self, w, c)
+        }
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        check_finite(*self)?;
         match c.coltype {
             ColumnType::MYSQL_TYPE_DOUBLE => w.write_f64::<LittleEndian>(*self),
+            ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                float_as_decimal!(self, c).to_mysql_bin(w, c)
+            }
             _ => Err(bad(self, c)),
         }
     }
@@ -368,6 +522,9 @@ impl ToMysqlValue for String {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         self.as_bytes().to_mysql_text(w)
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.as_bytes().to_mysql_text_with_column(w, c)
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         self.as_bytes().to_mysql_bin(w, c)
     }
@@ -377,17 +534,115 @@ impl ToMysqlValue for str {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         self.as_bytes().to_mysql_text(w)
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.as_bytes().to_mysql_text_with_column(w, c)
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         self.as_bytes().to_mysql_bin(w, c)
     }
 }
 
+fn is_unsigned_decimal(c: &Column) -> bool {
+    is_decimal(c) && c.colflags.contains(ColumnFlags::UNSIGNED_FLAG)
+}
+
+// "-0.00" is just zero, so only a non-zero digit makes a decimal string negative
+fn is_negative_decimal(v: &[u8]) -> bool {
+    v.first() == Some(&b'-') && v.iter().any(|b| (b'1'..=b'9').contains(b))
+}
+
+// an optional sign, then digits with an optional point, e.g. "-12.34", ".5" or "7."
+fn is_decimal_string(v: &[u8]) -> bool {
+    let digits = v
+        .strip_prefix(b"-")
+        .or_else(|| v.strip_prefix(b"+"))
+        .unwrap_or(v);
+    let mut parts = digits.splitn(2, |&b| b == b'.');
+    let int = parts.next().unwrap_or_default();
+    let frac = parts.next().unwrap_or_default();
+    (!int.is_empty() || !frac.is_empty())
+        && int.iter().all(u8::is_ascii_digit)
+        && frac.iter().all(u8::is_ascii_digit)
+}
+
+// clients fail to parse anything else with an opaque error, so reject it here
+fn check_decimal_syntax(v: &[u8], c: &Column) -> io::Result<()> {
+    if is_decimal(c) && !is_decimal_string(v) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "tried to use {:?}, which is not a decimal number, as {}",
+                String::from_utf8_lossy(v),
+                column_type(c)
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_unsigned_decimal(v: &[u8], c: &Column) -> io::Result<()> {
+    if is_unsigned_decimal(c) && is_negative_decimal(v) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "tried to use negative decimal {:?} as UNSIGNED {}",
+                String::from_utf8_lossy(v),
+                column_type(c)
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// a DECIMAL(M,D) column holds at most M-D digits before the point
+fn check_decimal_range(v: &[u8], c: &Column) -> io::Result<()> {
+    let precision = match c.column_length {
+        Some(precision) if is_decimal(c) && c.decimals < 31 => precision,
+        _ => return Ok(()),
+    };
+    let digits = v
+        .strip_prefix(b"-")
+        .or_else(|| v.strip_prefix(b"+"))
+        .unwrap_or(v);
+    let int = digits.split(|&b| b == b'.').next().unwrap_or_default();
+    if !int.iter().all(u8::is_ascii_digit) {
+        return Ok(());
+    }
+    let int_digits = int.iter().skip_while(|&&b| b == b'0').count();
+    if int_digits > precision.saturating_sub(u32::from(c.decimals)) as usize {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "decimal {:?} is out of range for {}",
+                String::from_utf8_lossy(v),
+                column_type(c)
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 impl ToMysqlValue for [u8] {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         w.write_lenenc_str(self).map(|_| ())
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        check_decimal_syntax(self, c)?;
+        check_unsigned_decimal(self, c)?;
+        check_decimal_range(self, c)?;
+        self.to_mysql_text(w)
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         match c.coltype {
+            ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                check_decimal_syntax(self, c)?;
+                check_unsigned_decimal(self, c)?;
+                check_decimal_range(self, c)?;
+                w.write_lenenc_str(self).map(|_| ())
+            }
             ColumnType::MYSQL_TYPE_STRING
             | ColumnType::MYSQL_TYPE_VAR_STRING
             | ColumnType::MYSQL_TYPE_BLOB
@@ -396,21 +651,460 @@ impl ToMysqlValue for [u8] {
             | ColumnType::MYSQL_TYPE_LONG_BLOB
             | ColumnType::MYSQL_TYPE_SET
             | ColumnType::MYSQL_TYPE_ENUM
-            | ColumnType::MYSQL_TYPE_DECIMAL
             | ColumnType::MYSQL_TYPE_VARCHAR
             | ColumnType::MYSQL_TYPE_BIT
-            | ColumnType::MYSQL_TYPE_NEWDECIMAL
             | ColumnType::MYSQL_TYPE_GEOMETRY
-            | ColumnType::MYSQL_TYPE_JSON => w.write_lenenc_str(self).map(|_| ()),
+            | ColumnType::MYSQL_TYPE_JSON
+            | ColumnType::MYSQL_TYPE_TYPED_ARRAY => w.write_lenenc_str(self).map(|_| ()),
             _ => Err(bad(self, c)),
         }
     }
 }
 
+/// A decimal string that is clamped to zero, rather than rejected, when it is negative and sent
+/// as an `UNSIGNED` `DECIMAL` column.
+///
+/// By default, negative decimals sent to unsigned decimal columns produce an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clamped<T>(pub T);
+
+impl<T: AsRef<[u8]>> Clamped<T> {
+    fn clamped(&self, c: &Column) -> Cow<'_, [u8]> {
+        let v = self.0.as_ref();
+        if is_unsigned_decimal(c) && is_negative_decimal(v) {
+            let mut zero = b"0".to_vec();
+            // 31 (NOT_FIXED_DEC) means the number of decimals is not fixed
+            if c.decimals > 0 && c.decimals < 31 {
+                zero.push(b'.');
+                zero.resize(2 + usize::from(c.decimals), b'0');
+            }
+            Cow::Owned(zero)
+        } else {
+            Cow::Borrowed(v)
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> ToMysqlValue for Clamped<T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.as_ref().to_mysql_text(w)
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.clamped(c).to_mysql_text_with_column(w, c)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.clamped(c).to_mysql_bin(w, c)
+    }
+}
+
+/// A float that is sent as NULL when it is NaN or infinite, which MySQL cannot represent.
+///
+/// By default, such floats produce an error.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct FiniteOrNull<T>(pub T);
+
+macro_rules! finite_or_null {
+    ($($t:ty),+) => {$(
+        impl ToMysqlValue for FiniteOrNull<$t> {
+            fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                if self.is_null() {
+                    Null.to_mysql_text(w)
+                } else {
+                    self.0.to_mysql_text(w)
+                }
+            }
+            fn to_mysql_text_with_column<W: Write>(
+                &self,
+                w: &mut W,
+                c: &Column,
+            ) -> io::Result<()> {
+                if self.is_null() {
+                    Null.to_mysql_text(w)
+                } else {
+                    self.0.to_mysql_text_with_column(w, c)
+                }
+            }
+            fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+                self.0.to_mysql_bin(w, c)
+            }
+            fn is_null(&self) -> bool {
+                !self.0.is_finite()
+            }
+        }
+    )+};
+}
+
+finite_or_null!(f32, f64);
+
+/// Bytes that are sent as NULL when empty, for applications that treat an empty BLOB and NULL
+/// alike.
+///
+/// Otherwise, empty bytes are sent as a zero-length string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NullIfEmpty<T>(pub T);
+
+impl<T: AsRef<[u8]>> ToMysqlValue for NullIfEmpty<T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.is_null() {
+            Null.to_mysql_text(w)
+        } else {
+            self.0.as_ref().to_mysql_text(w)
+        }
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        if self.is_null() {
+            Null.to_mysql_text(w)
+        } else {
+            self.0.as_ref().to_mysql_text_with_column(w, c)
+        }
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.0.as_ref().to_mysql_bin(w, c)
+    }
+    fn is_null(&self) -> bool {
+        self.0.as_ref().is_empty()
+    }
+}
+
+/// Bytes meant as text, which are checked to be valid UTF-8 before being sent, so that a bad
+/// sequence fails with an error naming where it is, rather than with an opaque decoding error in
+/// the client.
+///
+/// Otherwise, they are sent like any other bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Utf8Bytes<T>(pub T);
+
+impl<T: AsRef<[u8]>> Utf8Bytes<T> {
+    fn checked(&self) -> io::Result<&[u8]> {
+        let v = self.0.as_ref();
+        match std::str::from_utf8(v) {
+            Ok(_) => Ok(v),
+            Err(e) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid UTF-8 at byte {} of {:?}",
+                    e.valid_up_to(),
+                    String::from_utf8_lossy(v)
+                ),
+            )),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> ToMysqlValue for Utf8Bytes<T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.checked()?.to_mysql_text(w)
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.checked()?.to_mysql_text_with_column(w, c)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.checked()?.to_mysql_bin(w, c)
+    }
+}
+
+/// A cell already encoded for the text protocol, e.g. as received from an upstream MySQL server,
+/// which is forwarded as is.
+///
+/// The bytes must be a complete cell of a text resultset row, i.e. a length-encoded string, or
+/// `0xFB` for NULL. They are not checked against the column, and cannot be sent with the binary
+/// protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawTextCell<T>(pub T);
+
+impl<T: AsRef<[u8]>> ToMysqlValue for RawTextCell<T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.0.as_ref())
+    }
+    fn to_mysql_bin<W: Write>(&self, _: &mut W, c: &Column) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("tried to use a raw text cell as binary {}", column_type(c)),
+        ))
+    }
+}
+
+/// A cell already encoded for the binary protocol, e.g. as received from an upstream MySQL
+/// server, which is forwarded as is.
+///
+/// The bytes must be the encoding of a non-NULL value of the column's type, e.g. 8 little-endian
+/// bytes for a `MYSQL_TYPE_LONGLONG`; NULLs are sent with [`Null`](struct.Null.html) instead. They
+/// are not checked against the column, and cannot be sent with the text protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawBinCell<T>(pub T);
+
+impl<T: AsRef<[u8]>> ToMysqlValue for RawBinCell<T> {
+    fn to_mysql_text<W: Write>(&self, _: &mut W) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "tried to use a raw binary cell as text",
+        ))
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, _: &Column) -> io::Result<()> {
+        w.write_all(self.0.as_ref())
+    }
+}
+
+/// A fixed-point number stored as an integer with an implied decimal point, e.g. an amount of
+/// money in cents for a `DECIMAL(_, 2)` column.
+///
+/// The value is sent as the decimal string with `scale` digits after the point, so that
+/// `Scaled { value: 12345, scale: 2 }` is sent as `123.45`, and
+/// `Scaled { value: -5, scale: 2 }` as `-0.05`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scaled {
+    /// The number, multiplied by `10^scale`.
+    pub value: i64,
+    /// The number of digits after the decimal point.
+    pub scale: u8,
+}
+
+impl fmt::Display for Scaled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = usize::from(self.scale);
+        let digits = format!("{:0width$}", self.value.unsigned_abs(), width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        if self.value < 0 {
+            f.write_str("-")?;
+        }
+        f.write_str(int)?;
+        if scale > 0 {
+            write!(f, ".{}", frac)?;
+        }
+        Ok(())
+    }
+}
+
+impl ToMysqlValue for Scaled {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.to_string().to_mysql_text(w)
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.to_string().to_mysql_text_with_column(w, c)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.to_string().to_mysql_bin(w, c)
+    }
+}
+
+/// An integer that may also be sent as a `DOUBLE` or `FLOAT` column, by rounding it to the
+/// nearest representable float, e.g. an aggregate computed as a `u64` for a `DOUBLE` column.
+///
+/// By default, integers sent to float columns of binary resultsets produce an error, as large
+/// integers lose precision: `Lossy(u64::MAX)` is sent as 2^64 to a `DOUBLE` column. Text
+/// resultsets, and columns of other types, are sent as with the integer itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lossy<T>(pub T);
+
+macro_rules! lossy_float {
+    ($($t:ty),+) => {$(
+        impl ToMysqlValue for Lossy<$t> {
+            fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                self.0.to_mysql_text(w)
+            }
+            fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+                self.0.to_mysql_text_with_column(w, c)
+            }
+            fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+                match c.coltype {
+                    ColumnType::MYSQL_TYPE_DOUBLE => w.write_f64::<LittleEndian>(self.0 as f64),
+                    ColumnType::MYSQL_TYPE_FLOAT => w.write_f32::<LittleEndian>(self.0 as f32),
+                    _ => self.0.to_mysql_bin(w, c),
+                }
+            }
+            fn to_mysql_bin_strict<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+                match c.coltype {
+                    ColumnType::MYSQL_TYPE_DOUBLE | ColumnType::MYSQL_TYPE_FLOAT => {
+                        self.to_mysql_bin(w, c)
+                    }
+                    _ => self.0.to_mysql_bin_strict(w, c),
+                }
+            }
+        }
+    )+};
+}
+
+lossy_float!(u8, u16, u32, u64, i8, i16, i32, i64, usize, isize);
+
+/// A list of values sent as a single comma-separated string, e.g. for a `SET` column.
+///
+/// Each element is rendered with its
+/// [`to_mysql_text`](trait.ToMysqlValue.html#tymethod.to_mysql_text), so that
+/// `CommaJoined(&["a", "b"])` is sent as `a,b`. Elements may not be NULL, nor contain a comma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommaJoined<'a, T>(pub &'a [T]);
+
+impl<'a, T: ToMysqlValue> CommaJoined<'a, T> {
+    fn joined(&self) -> io::Result<Vec<u8>> {
+        let mut joined = Vec::new();
+        let mut text = Vec::new();
+        for (i, v) in self.0.iter().enumerate() {
+            if v.is_null() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "cannot join a NULL value",
+                ));
+            }
+            text.clear();
+            v.to_mysql_text(&mut text)?;
+            let mut rest = &text[..];
+            let len = rest.read_lenenc_int()? as usize;
+            let v = rest.get(..len).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "truncated text value")
+            })?;
+            if v.contains(&b',') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "cannot join {:?}, which contains a comma",
+                        String::from_utf8_lossy(v)
+                    ),
+                ));
+            }
+            if i > 0 {
+                joined.push(b',');
+            }
+            joined.extend_from_slice(v);
+        }
+        Ok(joined)
+    }
+}
+
+impl<'a, T: ToMysqlValue> ToMysqlValue for CommaJoined<'a, T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.joined()?.to_mysql_text(w)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.joined()?.to_mysql_bin(w, c)
+    }
+}
+
+/// A value that can be an element of a [`JsonArray`](struct.JsonArray.html).
+pub trait JsonScalar {
+    /// Append this value to `json` as a JSON number, string or boolean.
+    fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()>;
+}
+
+macro_rules! json_number {
+    ($($t:ty),+) => {$(
+        impl JsonScalar for $t {
+            fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+                write!(json, "{}", self)
+            }
+        }
+    )+};
+}
+
+json_number!(u8, u16, u32, u64, i8, i16, i32, i64, usize, isize);
+
+macro_rules! json_float {
+    ($($t:ty),+) => {$(
+        impl JsonScalar for $t {
+            fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+                if !self.is_finite() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("cannot represent {} in JSON", self),
+                    ));
+                }
+                write!(json, "{}", self)
+            }
+        }
+    )+};
+}
+
+json_float!(f32, f64);
+
+impl JsonScalar for bool {
+    fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+        json.extend_from_slice(if *self { b"true" } else { b"false" });
+        Ok(())
+    }
+}
+
+impl JsonScalar for str {
+    fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+        json.push(b'"');
+        for c in self.chars() {
+            match c {
+                '"' => json.extend_from_slice(b"\\\""),
+                '\\' => json.extend_from_slice(b"\\\\"),
+                '\n' => json.extend_from_slice(b"\\n"),
+                '\r' => json.extend_from_slice(b"\\r"),
+                '\t' => json.extend_from_slice(b"\\t"),
+                c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32)?,
+                c => json.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        json.push(b'"');
+        Ok(())
+    }
+}
+
+impl JsonScalar for String {
+    fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+        self.as_str().write_json(json)
+    }
+}
+
+impl<'a, T: JsonScalar + ?Sized> JsonScalar for &'a T {
+    fn write_json(&self, json: &mut Vec<u8>) -> io::Result<()> {
+        (*self).write_json(json)
+    }
+}
+
+/// A list of scalars sent as a compact JSON array, e.g. for a `MYSQL_TYPE_JSON` column.
+///
+/// `JsonArray(vec![1, 2, 3])` is sent as `[1,2,3]`, and strings are quoted and escaped. Nested
+/// arrays and objects are not supported.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsonArray<T>(pub Vec<T>);
+
+impl<T: JsonScalar> JsonArray<T> {
+    fn rendered(&self) -> io::Result<Vec<u8>> {
+        let mut json = vec![b'['];
+        for (i, v) in self.0.iter().enumerate() {
+            if i > 0 {
+                json.push(b',');
+            }
+            v.write_json(&mut json)?;
+        }
+        json.push(b']');
+        Ok(json)
+    }
+}
+
+impl<T: JsonScalar> ToMysqlValue for JsonArray<T> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.rendered()?.to_mysql_text(w)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        self.rendered()?.to_mysql_bin(w, c)
+    }
+}
+
 impl ToMysqlValue for Vec<u8> {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         (self[..]).to_mysql_text(w)
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        (self[..]).to_mysql_text_with_column(w, c)
+    }
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        (self[..]).to_mysql_bin(w, c)
+    }
+}
+
+/// Memory-mapped files are written straight from the mapping, without first being copied into a
+/// `Vec<u8>`.
+#[cfg(feature = "memmap2")]
+impl ToMysqlValue for memmap2::Mmap {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (self[..]).to_mysql_text(w)
+    }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        (self[..]).to_mysql_text_with_column(w, c)
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         (self[..]).to_mysql_bin(w, c)
     }
@@ -423,12 +1117,21 @@ where
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         (*self).to_mysql_text(w)
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        (*self).to_mysql_text_with_column(w, c)
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         (*self).to_mysql_bin(w, c)
     }
+    fn to_mysql_bin_strict<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        (*self).to_mysql_bin_strict(w, c)
+    }
+    fn is_null(&self) -> bool {
+        (*self).is_null()
+    }
 }
 
-use chrono::{self, Datelike, NaiveDate, NaiveDateTime, Timelike};
+use chrono::{self, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 impl ToMysqlValue for NaiveDate {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         w.write_lenenc_str(
@@ -449,6 +1152,8 @@ impl ToMysqlValue for NaiveDate {
     }
 }
 
+/// A `NaiveDateTime` sent as a `MYSQL_TYPE_DATE` column is truncated to its date, and its time
+/// is discarded.
 impl ToMysqlValue for NaiveDateTime {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
         let us = self.nanosecond() / 1_000;
@@ -484,8 +1189,16 @@ impl ToMysqlValue for NaiveDateTime {
             .map(|_| ())
         }
     }
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        if c.coltype == ColumnType::MYSQL_TYPE_DATE {
+            self.date().to_mysql_text(w)
+        } else {
+            self.to_mysql_text(w)
+        }
+    }
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         match c.coltype {
+            ColumnType::MYSQL_TYPE_DATE => self.date().to_mysql_bin(w, c),
             ColumnType::MYSQL_TYPE_DATETIME | ColumnType::MYSQL_TYPE_TIMESTAMP => {
                 let us = self.nanosecond() / 1_000;
 
@@ -511,56 +1224,102 @@ impl ToMysqlValue for NaiveDateTime {
     }
 }
 
-use std::time::Duration;
-impl ToMysqlValue for Duration {
+/// Truncate a microsecond value to the given fractional seconds precision.
+fn truncate_micros(us: u32, fsp: u8) -> u32 {
+    let scale = 10u32.pow(6 - u32::from(fsp.min(6)));
+    us - us % scale
+}
+
+impl ToMysqlValue for NaiveTime {
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
-        let s = self.as_secs();
-        //let d = s / (24 * 3600);
-        // assert!(d <= 34);
-        //let h = (s % (24 * 3600)) / 3600;
-        let h = s / 3600;
-        let m = (s % 3600) / 60;
-        let s = s % 60;
-        let us = self.subsec_micros();
+        // leap seconds are represented as nanoseconds >= 1_000_000_000
+        let us = (self.nanosecond() / 1_000).min(999_999);
+
         if us != 0 {
-            w.write_lenenc_str(format!("{:02}:{:02}:{:02}.{:06}", h, m, s, us).as_bytes())
-                .map(|_| ())
+            w.write_lenenc_str(
+                format!(
+                    "{:02}:{:02}:{:02}.{:06}",
+                    self.hour(),
+                    self.minute(),
+                    self.second(),
+                    us
+                )
+                .as_bytes(),
+            )
+            .map(|_| ())
         } else {
-            w.write_lenenc_str(format!("{:02}:{:02}:{:02}", h, m, s).as_bytes())
-                .map(|_| ())
+            w.write_lenenc_str(
+                format!(
+                    "{:02}:{:02}:{:02}",
+                    self.hour(),
+                    self.minute(),
+                    self.second()
+                )
+                .as_bytes(),
+            )
+            .map(|_| ())
         }
     }
 
-    #[allow(clippy::many_single_char_names)]
-    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
-        let s = self.as_secs();
-        let d = s / (24 * 3600);
-        assert!(d <= 34);
-        let h = (s % (24 * 3600)) / 3600;
-        let m = (s % 3600) / 60;
-        let s = s % 60;
-        let us = self.subsec_micros();
+    /// Formats the fractional part with exactly as many digits as the column's fsp, truncating or
+    /// zero-padding the microseconds as needed.
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        if c.coltype != ColumnType::MYSQL_TYPE_TIME {
+            return self.to_mysql_text(w);
+        }
 
+        let fsp = c.decimals.min(6);
+        if fsp == 0 {
+            w.write_lenenc_str(
+                format!(
+                    "{:02}:{:02}:{:02}",
+                    self.hour(),
+                    self.minute(),
+                    self.second()
+                )
+                .as_bytes(),
+            )
+            .map(|_| ())
+        } else {
+            let us = (self.nanosecond() / 1_000).min(999_999);
+            let frac = us / 10u32.pow(6 - u32::from(fsp));
+            w.write_lenenc_str(
+                format!(
+                    "{:02}:{:02}:{:02}.{:0width$}",
+                    self.hour(),
+                    self.minute(),
+                    self.second(),
+                    frac,
+                    width = usize::from(fsp)
+                )
+                .as_bytes(),
+            )
+            .map(|_| ())
+        }
+    }
+
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         match c.coltype {
             ColumnType::MYSQL_TYPE_TIME => {
-                if self.as_secs() == 0 && us == 0 {
-                    w.write_u8(0u8)?;
-                } else {
-                    if us != 0 {
-                        w.write_u8(12u8)?;
-                    } else {
-                        w.write_u8(8u8)?;
-                    }
+                let us = truncate_micros((self.nanosecond() / 1_000).min(999_999), c.decimals);
 
-                    w.write_u8(0u8)?; // positive only (for now)
-                    w.write_u32::<LittleEndian>(d as u32)?;
-                    w.write_u8(h as u8)?;
-                    w.write_u8(m as u8)?;
-                    w.write_u8(s as u8)?;
+                if self.hour() == 0 && self.minute() == 0 && self.second() == 0 && us == 0 {
+                    return w.write_u8(0u8);
+                }
 
-                    if us != 0 {
-                        w.write_u32::<LittleEndian>(us)?;
-                    }
+                if us != 0 {
+                    w.write_u8(12u8)?;
+                } else {
+                    w.write_u8(8u8)?;
+                }
+                w.write_u8(0u8)?; // positive
+                w.write_u32::<LittleEndian>(0)?; // days
+                w.write_u8(self.hour() as u8)?;
+                w.write_u8(self.minute() as u8)?;
+                w.write_u8(self.second() as u8)?;
+
+                if us != 0 {
+                    w.write_u32::<LittleEndian>(us)?;
                 }
                 Ok(())
             }
@@ -569,6 +1328,181 @@ impl ToMysqlValue for Duration {
     }
 }
 
+use std::time::Duration;
+/// Split a duration into the (days, hours, minutes, seconds, microseconds) components used by the
+/// binary encoding of `MYSQL_TYPE_TIME`.
+fn time_components(dur: &Duration) -> (u64, u64, u64, u64, u32) {
+    let s = dur.as_secs();
+    let d = s / (24 * 3600);
+    let h = (s % (24 * 3600)) / 3600;
+    let m = (s % 3600) / 60;
+    let s = s % 60;
+    (d, h, m, s, dur.subsec_micros())
+}
+
+impl ToMysqlValue for Duration {
+    /// Days are folded into the hours field (e.g. `50:00:00`), which is how MySQL itself displays
+    /// `TIME` values; the binary encoding carries the same value as days and hours separately.
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_lenenc_str(time_text(false, self).as_bytes())
+            .map(|_| ())
+    }
+
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        match c.coltype {
+            ColumnType::MYSQL_TYPE_TIME => write_time_bin(false, self, w),
+            _ => Err(bad(self, c)),
+        }
+    }
+}
+
+/// Negative durations are sent as negative `TIME` values, e.g. `-01:30:00`.
+impl ToMysqlValue for chrono::Duration {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (neg, dur) = signed_time(self);
+        w.write_lenenc_str(time_text(neg, &dur).as_bytes())
+            .map(|_| ())
+    }
+
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        match c.coltype {
+            ColumnType::MYSQL_TYPE_TIME => {
+                let (neg, dur) = signed_time(self);
+                write_time_bin(neg, &dur, w)
+            }
+            _ => Err(bad(self, c)),
+        }
+    }
+}
+
+// the sign and magnitude of a signed duration
+fn signed_time(d: &chrono::Duration) -> (bool, Duration) {
+    let neg = *d < chrono::Duration::zero();
+    let abs = if neg { -*d } else { *d };
+    // only fails for negative durations
+    (neg, abs.to_std().unwrap_or_default())
+}
+
+/// Format a `TIME` the way MySQL displays it, e.g. `-838:59:59` or `-00:00:00.500000`.
+///
+/// Days are folded into the hours, which are zero-padded to at least two digits, and the sign
+/// goes before the hours. Microseconds are only shown if there are any.
+#[allow(clippy::many_single_char_names)]
+fn time_text(neg: bool, dur: &Duration) -> String {
+    let (d, h, m, s, us) = time_components(dur);
+    let sign = if neg && !dur.is_zero() { "-" } else { "" };
+    let h = d * 24 + h;
+    if us != 0 {
+        format!("{}{:02}:{:02}:{:02}.{:06}", sign, h, m, s, us)
+    } else {
+        format!("{}{:02}:{:02}:{:02}", sign, h, m, s)
+    }
+}
+
+#[allow(clippy::many_single_char_names)]
+fn write_time_bin<W: Write>(neg: bool, dur: &Duration, w: &mut W) -> io::Result<()> {
+    let (d, h, m, s, us) = time_components(dur);
+    if d > 34 {
+        // TIME values range from -838:59:59 to 838:59:59
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("time of {} days is out of range", d),
+        ));
+    }
+
+    if dur.is_zero() {
+        return w.write_u8(0u8);
+    }
+
+    if us != 0 {
+        w.write_u8(12u8)?;
+    } else {
+        w.write_u8(8u8)?;
+    }
+
+    w.write_u8(u8::from(neg))?;
+    w.write_u32::<LittleEndian>(d as u32)?;
+    w.write_u8(h as u8)?;
+    w.write_u8(m as u8)?;
+    w.write_u8(s as u8)?;
+
+    if us != 0 {
+        w.write_u32::<LittleEndian>(us)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    //! The `time` crate types are encoded exactly like their `chrono` counterparts.
+
+    use super::*;
+
+    fn naive_date(d: &time::Date) -> NaiveDate {
+        NaiveDate::from_ymd(d.year(), u8::from(d.month()).into(), d.day().into())
+    }
+
+    fn naive_time(t: &time::Time) -> NaiveTime {
+        NaiveTime::from_hms_nano(
+            t.hour().into(),
+            t.minute().into(),
+            t.second().into(),
+            t.nanosecond(),
+        )
+    }
+
+    fn naive_date_time(dt: &time::PrimitiveDateTime) -> NaiveDateTime {
+        naive_date(&dt.date()).and_time(naive_time(&dt.time()))
+    }
+
+    macro_rules! like_chrono {
+        ($t:ty, $convert:expr) => {
+            impl ToMysqlValue for $t {
+                fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    $convert(self).to_mysql_text(w)
+                }
+                fn to_mysql_text_with_column<W: Write>(
+                    &self,
+                    w: &mut W,
+                    c: &Column,
+                ) -> io::Result<()> {
+                    $convert(self).to_mysql_text_with_column(w, c)
+                }
+                fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+                    $convert(self).to_mysql_bin(w, c)
+                }
+            }
+        };
+    }
+
+    like_chrono!(time::Date, naive_date);
+    like_chrono!(time::Time, naive_time);
+    like_chrono!(time::PrimitiveDateTime, naive_date_time);
+    // offset date-times are sent as their UTC date and time
+    like_chrono!(time::OffsetDateTime, |dt: &time::OffsetDateTime| {
+        let dt = dt.to_offset(time::UtcOffset::UTC);
+        naive_date_time(&time::PrimitiveDateTime::new(dt.date(), dt.time()))
+    });
+}
+
+/// The magnitude of a `myc::value::Value::Time`, or an error if it is out of range.
+#[allow(clippy::many_single_char_names)]
+fn time_value(d: u32, h: u8, m: u8, s: u8, us: u32) -> io::Result<Duration> {
+    u64::from(d)
+        .checked_mul(86_400)
+        .and_then(|secs| secs.checked_add(u64::from(h) * 3_600))
+        .and_then(|secs| secs.checked_add(u64::from(m) * 60 + u64::from(s)))
+        .and_then(|secs| {
+            Duration::from_secs(secs).checked_add(Duration::from_micros(u64::from(us)))
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("time of {} days is out of range", d),
+            )
+        })
+}
+
 impl ToMysqlValue for myc::value::Value {
     #[allow(clippy::many_single_char_names)]
     fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
@@ -585,63 +1519,33 @@ impl ToMysqlValue for myc::value::Value {
                     .to_mysql_text(w)
             }
             myc::value::Value::Time(neg, d, h, m, s, us) => {
-                if neg {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "negative times not yet supported",
-                    ));
-                }
-                (chrono::Duration::days(i64::from(d))
-                    + chrono::Duration::hours(i64::from(h))
-                    + chrono::Duration::minutes(i64::from(m))
-                    + chrono::Duration::seconds(i64::from(s))
-                    + chrono::Duration::microseconds(i64::from(us)))
-                .to_std()
-                .expect("only positive times at the moment")
-                .to_mysql_text(w)
+                let dur = time_value(d, h, m, s, us)?;
+                w.write_lenenc_str(time_text(neg, &dur).as_bytes())
+                    .map(|_| ())
             }
         }
     }
 
+    fn to_mysql_text_with_column<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
+        match *self {
+            myc::value::Value::Bytes(ref bytes) => bytes.to_mysql_text_with_column(w, c),
+            myc::value::Value::Int(n) => n.to_mysql_text_with_column(w, c),
+            myc::value::Value::UInt(n) => n.to_mysql_text_with_column(w, c),
+            myc::value::Value::Float(f) => f.to_mysql_text_with_column(w, c),
+            myc::value::Value::Double(f) => f.to_mysql_text_with_column(w, c),
+            _ => self.to_mysql_text(w),
+        }
+    }
+
     #[allow(clippy::many_single_char_names)]
     fn to_mysql_bin<W: Write>(&self, w: &mut W, c: &Column) -> io::Result<()> {
         match *self {
             myc::value::Value::NULL => unreachable!(),
             myc::value::Value::Bytes(ref bytes) => bytes.to_mysql_bin(w, c),
-            myc::value::Value::Int(n) => {
-                // we *could* just delegate to i64 impl here, but then you couldn't use myc::value::Value
-                // and return, say, a short. also, myc uses i64 for *every* number type, *except*
-                // u64, so we even need to coerce across unsigned :( the good news is that our
-                // impls for numbers auto-upgrade to wider coltypes, so we can just downcast to the
-                // smallest containing type, and then call on that
-                let signed = !c.colflags.contains(ColumnFlags::UNSIGNED_FLAG);
-                if signed {
-                    if n >= i64::from(i8::min_value()) && n <= i64::from(i8::max_value()) {
-                        (n as i8).to_mysql_bin(w, c)
-                    } else if n >= i64::from(i16::min_value()) && n <= i64::from(i16::max_value()) {
-                        (n as i16).to_mysql_bin(w, c)
-                    } else if n >= i64::from(i32::min_value()) && n <= i64::from(i32::max_value()) {
-                        (n as i32).to_mysql_bin(w, c)
-                    } else {
-                        n.to_mysql_bin(w, c)
-                    }
-                } else if n < 0 {
-                    Err(bad(self, c))
-                } else if n <= i64::from(u8::max_value()) {
-                    (n as u8).to_mysql_bin(w, c)
-                } else if n <= i64::from(u16::max_value()) {
-                    (n as u16).to_mysql_bin(w, c)
-                } else if n <= i64::from(u32::max_value()) {
-                    (n as u32).to_mysql_bin(w, c)
-                } else {
-                    // must work since u64::max_value() > i64::max_value(), and n >= 0
-                    (n as u64).to_mysql_bin(w, c)
-                }
-            }
-            myc::value::Value::UInt(n) => {
-                // we are not as lenient with unsigned ints because the mysql crate isn't either
-                n.to_mysql_bin(w, c)
-            }
+            // the impls for 64-bit integers downcast to narrower columns when the value fits, and
+            // myc uses i64 for *every* number type, *except* u64
+            myc::value::Value::Int(n) => n.to_mysql_bin(w, c),
+            myc::value::Value::UInt(n) => n.to_mysql_bin(w, c),
             myc::value::Value::Float(f) => f.to_mysql_bin(w, c),
             myc::value::Value::Double(f) => f.to_mysql_bin(w, c),
             myc::value::Value::Date(y, mo, d, h, mi, s, us) => {
@@ -649,22 +1553,10 @@ impl ToMysqlValue for myc::value::Value {
                     .and_hms_micro(u32::from(h), u32::from(mi), u32::from(s), us)
                     .to_mysql_bin(w, c)
             }
-            myc::value::Value::Time(neg, d, h, m, s, us) => {
-                if neg {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "negative times not yet supported",
-                    ));
-                }
-                (chrono::Duration::days(i64::from(d))
-                    + chrono::Duration::hours(i64::from(h))
-                    + chrono::Duration::minutes(i64::from(m))
-                    + chrono::Duration::seconds(i64::from(s))
-                    + chrono::Duration::microseconds(i64::from(us)))
-                .to_std()
-                .expect("only positive times at the moment")
-                .to_mysql_bin(w, c)
-            }
+            myc::value::Value::Time(neg, d, h, m, s, us) => match c.coltype {
+                ColumnType::MYSQL_TYPE_TIME => write_time_bin(neg, &time_value(d, h, m, s, us)?, w),
+                _ => Err(bad(self, c)),
+            },
         }
     }
 
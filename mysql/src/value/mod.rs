@@ -15,5 +15,8 @@
 mod decode;
 mod encode;
 
-pub use self::decode::{Value, ValueInner};
-pub use self::encode::ToMysqlValue;
+pub use self::decode::{MysqlTime, Value, ValueInner};
+pub use self::encode::{
+    Clamped, CommaJoined, FiniteOrNull, JsonArray, JsonScalar, Lossy, Null, NullIfEmpty,
+    RawBinCell, RawTextCell, Scaled, ToMysqlValue, Utf8Bytes,
+};
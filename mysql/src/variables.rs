@@ -0,0 +1,265 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use crate::myc::constants::ColumnType;
+use crate::Column;
+
+/// System variables and status counters answered by the intermediary itself, without calling
+/// [`AsyncMysqlShim::on_query`](trait.AsyncMysqlShim.html#tymethod.on_query).
+///
+/// Once set as [`IntermediaryOptions::system_variables`](struct.IntermediaryOptions.html), the
+/// following queries are answered from it:
+///
+///  - `SELECT @@name[, @@name ...] [LIMIT 1]`, where every name is known, optionally prefixed
+///    with `session.`, `global.` or `local.`, and optionally aliased with `AS alias`.
+///  - `SHOW [GLOBAL | SESSION] VARIABLES [LIKE 'pattern']`.
+///  - `SHOW [GLOBAL | SESSION] STATUS [LIKE 'pattern']`.
+///
+/// Any other query, including a `SELECT` of an unknown variable, still goes to `on_query`.
+/// Variable names are case-insensitive. `version` defaults to
+/// [`AsyncMysqlShim::version`](trait.AsyncMysqlShim.html#method.version).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemVariables {
+    variables: BTreeMap<String, String>,
+    status: BTreeMap<String, String>,
+}
+
+impl Default for SystemVariables {
+    /// The variables most drivers and clients look up when connecting.
+    fn default() -> Self {
+        SystemVariables::empty()
+            .with_variable("auto_increment_increment", "1")
+            .with_variable("autocommit", "1")
+            .with_variable("character_set_client", "utf8mb4")
+            .with_variable("character_set_connection", "utf8mb4")
+            .with_variable("character_set_results", "utf8mb4")
+            .with_variable("character_set_server", "utf8mb4")
+            .with_variable("collation_connection", "utf8mb4_general_ci")
+            .with_variable("collation_server", "utf8mb4_general_ci")
+            .with_variable("interactive_timeout", "28800")
+            .with_variable("lower_case_table_names", "0")
+            .with_variable("max_allowed_packet", "67108864")
+            .with_variable("net_write_timeout", "60")
+            .with_variable("sql_mode", "")
+            .with_variable("system_time_zone", "UTC")
+            .with_variable("time_zone", "SYSTEM")
+            .with_variable("transaction_isolation", "REPEATABLE-READ")
+            .with_variable("tx_isolation", "REPEATABLE-READ")
+            .with_variable("version_comment", "")
+            .with_variable("wait_timeout", "28800")
+    }
+}
+
+impl SystemVariables {
+    /// No variables or status counters at all.
+    pub fn empty() -> Self {
+        SystemVariables {
+            variables: BTreeMap::new(),
+            status: BTreeMap::new(),
+        }
+    }
+
+    /// Answer `name` with `value`, replacing any previous value.
+    pub fn with_variable(mut self, name: &str, value: &str) -> Self {
+        self.variables
+            .insert(name.to_ascii_lowercase(), value.to_owned());
+        self
+    }
+
+    /// Answer the status counter `name` with `value` in `SHOW STATUS`, replacing any previous
+    /// value.
+    pub fn with_status(mut self, name: &str, value: &str) -> Self {
+        self.status.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    /// The value of the variable `name`, if known.
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub(crate) fn with_default_version(mut self, version: &str) -> Self {
+        if !self.variables.contains_key("version") {
+            self.variables
+                .insert("version".to_owned(), version.to_owned());
+        }
+        self
+    }
+
+    /// The columns and rows answering `query`, or `None` if the query is not one answered from
+    /// these variables.
+    pub(crate) fn answer(&self, query: &str) -> Option<(Vec<Column>, Vec<Vec<String>>)> {
+        let query = query.trim().trim_end_matches(';').trim_end();
+        if let Some(rest) = strip_keyword(query, "SELECT") {
+            self.select(rest)
+        } else if let Some(rest) = strip_keyword(query, "SHOW") {
+            let rest = strip_keyword(rest, "GLOBAL")
+                .or_else(|| strip_keyword(rest, "SESSION"))
+                .unwrap_or(rest);
+            if let Some(pattern) = strip_keyword(rest, "VARIABLES") {
+                show(&self.variables, pattern)
+            } else if let Some(pattern) = strip_keyword(rest, "STATUS") {
+                show(&self.status, pattern)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    fn select(&self, exprs: &str) -> Option<(Vec<Column>, Vec<Vec<String>>)> {
+        let exprs = match exprs.len().checked_sub(" LIMIT 1".len()) {
+            Some(at) if exprs[at..].eq_ignore_ascii_case(" LIMIT 1") => &exprs[..at],
+            _ => exprs,
+        };
+
+        let mut cols = Vec::new();
+        let mut row = Vec::new();
+        for expr in exprs.split(',') {
+            let expr = expr.trim();
+            let (var, alias) = match expr.find(|c: char| c.is_ascii_whitespace()) {
+                Some(at) => {
+                    let alias = strip_keyword(expr[at..].trim_start(), "AS")?;
+                    (&expr[..at], alias.trim_matches('`'))
+                }
+                None => (expr, expr),
+            };
+            let name = strip_scope(var.strip_prefix("@@")?, &["session.", "global.", "local."]);
+            let value = self.variable(name)?;
+            cols.push(value_column(alias, value));
+            row.push(value.to_owned());
+        }
+        Some((cols, vec![row]))
+    }
+}
+
+fn show(vars: &BTreeMap<String, String>, pattern: &str) -> Option<(Vec<Column>, Vec<Vec<String>>)> {
+    let pattern = like_clause(pattern)?;
+
+    let cols = vec![
+        Column::new("Variable_name", ColumnType::MYSQL_TYPE_VAR_STRING).not_null(),
+        Column::new("Value", ColumnType::MYSQL_TYPE_VAR_STRING),
+    ];
+    let rows = vars
+        .iter()
+        .filter(|(name, _)| pattern.map_or(true, |p| like(p.as_bytes(), name.as_bytes())))
+        .map(|(name, value)| vec![name.clone(), value.clone()])
+        .collect();
+    Some((cols, rows))
+}
+
+// strips a leading case-insensitive `session.`-like scope off a variable name
+fn strip_scope<'a>(name: &'a str, scopes: &[&str]) -> &'a str {
+    scopes
+        .iter()
+        .find_map(|scope| {
+            name.get(..scope.len())
+                .filter(|s| s.eq_ignore_ascii_case(scope))
+                .map(|_| &name[scope.len()..])
+        })
+        .unwrap_or(name)
+}
+
+// whether `query` is a `SET` statement, of variables, `NAMES`, `CHARACTER SET` or the like
+pub(crate) fn is_set(query: &str) -> bool {
+    strip_keyword(query.trim_start(), "SET").is_some()
+}
+
+// the SQL mode set by `SET [SESSION | LOCAL] sql_mode = 'modes'`, also spelled with `@@`,
+// `@@session.`, `@@local.` or `:=`, where `DEFAULT` stands for no mode at all
+pub(crate) fn sql_mode_set_by(query: &str) -> Option<String> {
+    let query = query.trim().trim_end_matches(';').trim_end();
+    let rest = strip_keyword(query, "SET")?;
+    let rest = strip_keyword(rest, "SESSION")
+        .or_else(|| strip_keyword(rest, "LOCAL"))
+        .unwrap_or(rest);
+    let rest = match rest.strip_prefix("@@") {
+        Some(name) => strip_scope(name, &["session.", "local."]),
+        None => rest,
+    };
+    let rest = rest
+        .get(.."sql_mode".len())
+        .filter(|name| name.eq_ignore_ascii_case("sql_mode"))
+        .map(|_| rest["sql_mode".len()..].trim_start())?;
+    let value = rest
+        .strip_prefix(":=")
+        .or_else(|| rest.strip_prefix('='))?
+        .trim_start();
+    if value.eq_ignore_ascii_case("DEFAULT") {
+        return Some(String::new());
+    }
+    let value = value
+        .strip_prefix('\'')
+        .and_then(|v| v.strip_suffix('\''))
+        .or_else(|| value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))?;
+    // e.g. `SET sql_mode = 'a', autocommit = 'b'` sets more than the mode
+    if value.contains(|c| c == '\'' || c == '"') {
+        return None;
+    }
+    Some(value.to_owned())
+}
+
+// the pattern of an optional trailing `LIKE 'pattern'`, or `None` if `s` is not one
+pub(crate) fn like_clause(s: &str) -> Option<Option<&str>> {
+    if s.is_empty() {
+        return Some(None);
+    }
+    let pattern = strip_keyword(s, "LIKE")?;
+    let pattern = pattern
+        .strip_prefix('\'')
+        .and_then(|p| p.strip_suffix('\''))
+        .or_else(|| pattern.strip_prefix('"').and_then(|p| p.strip_suffix('"')))?;
+    Some(Some(pattern))
+}
+
+fn value_column(name: &str, value: &str) -> Column {
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        Column::new(name, ColumnType::MYSQL_TYPE_LONGLONG).unsigned()
+    } else {
+        Column::new(name, ColumnType::MYSQL_TYPE_VAR_STRING)
+    }
+}
+
+// strips a leading case-insensitive keyword followed by whitespace or the end of `s`
+pub(crate) fn strip_keyword<'a>(s: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = s.get(..keyword.len())?;
+    let rest = &s[keyword.len()..];
+    if head.eq_ignore_ascii_case(keyword)
+        && (rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_whitespace()))
+    {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+/// Case-insensitive SQL `LIKE`, where `%` matches any run of characters and `_` any single one.
+pub(crate) fn like(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'%', rest)) => (0..=s.len()).any(|i| like(rest, &s[i..])),
+        Some((b'\\', [c, rest @ ..])) => {
+            s.first().map_or(false, |b| b.eq_ignore_ascii_case(c)) && like(rest, &s[1..])
+        }
+        Some((b'_', rest)) => !s.is_empty() && like(rest, &s[1..]),
+        Some((c, rest)) => {
+            s.first().map_or(false, |b| b.eq_ignore_ascii_case(c)) && like(rest, &s[1..])
+        }
+    }
+}
@@ -25,8 +25,11 @@ use crate::{Column, ErrorKind, OkResponse};
 pub(crate) async fn write_eof_packet<W: AsyncWrite + Unpin>(
     w: &mut PacketWriter<W>,
     s: StatusFlags,
+    warnings: u16,
 ) -> io::Result<()> {
-    w.write_all(&[0xFE, 0x00, 0x00])?;
+    let s = s | w.server_status;
+    w.write_u8(0xFE)?;
+    w.write_u16::<LittleEndian>(warnings)?;
     w.write_u16::<LittleEndian>(s.bits())?;
     w.end_packet().await
 }
@@ -34,25 +37,37 @@ pub(crate) async fn write_eof_packet<W: AsyncWrite + Unpin>(
 pub(crate) async fn write_ok_packet<W: AsyncWrite + Unpin>(
     w: &mut PacketWriter<W>,
     client_capabilities: CapabilityFlags,
-    ok_packet: OkResponse,
+    mut ok_packet: OkResponse,
 ) -> io::Result<()> {
+    ok_packet.status_flags |= w.server_status;
+    let track = client_capabilities.contains(CapabilityFlags::CLIENT_SESSION_TRACK);
+    if track && !ok_packet.session_state_changes.is_empty() {
+        ok_packet
+            .status_flags
+            .insert(StatusFlags::SERVER_SESSION_STATE_CHANGED);
+    }
+
     w.write_u8(ok_packet.header)?; // OK packet type
     w.write_lenenc_int(ok_packet.affected_rows)?;
     w.write_lenenc_int(ok_packet.last_insert_id)?;
     if client_capabilities.contains(CapabilityFlags::CLIENT_PROTOCOL_41) {
         w.write_u16::<LittleEndian>(ok_packet.status_flags.bits())?;
-        w.write_all(&[0x00, 0x00])?; // no warnings
+        w.write_u16::<LittleEndian>(ok_packet.warnings)?;
     } else if client_capabilities.contains(CapabilityFlags::CLIENT_TRANSACTIONS) {
         w.write_u16::<LittleEndian>(ok_packet.status_flags.bits())?;
     }
 
-    if client_capabilities.contains(CapabilityFlags::CLIENT_SESSION_TRACK) {
+    if track {
         w.write_lenenc_str(ok_packet.info.as_bytes())?;
         if ok_packet
             .status_flags
             .contains(StatusFlags::SERVER_SESSION_STATE_CHANGED)
         {
-            w.write_lenenc_str(ok_packet.session_state_info.as_bytes())?;
+            let mut info = ok_packet.session_state_info.into_bytes();
+            for change in &ok_packet.session_state_changes {
+                change.write_to(&mut info)?;
+            }
+            w.write_lenenc_str(&info)?;
         }
     } else {
         w.write_all(ok_packet.info.as_bytes())?;
@@ -82,6 +97,7 @@ pub(crate) async fn write_prepare_ok<'a, PI, CI, W>(
     columns: CI,
     w: &mut PacketWriter<W>,
     client_capabilities: CapabilityFlags,
+    default_charset: u16,
 ) -> io::Result<()>
 where
     PI: IntoIterator<Item = &'a Column>,
@@ -103,10 +119,10 @@ where
     w.end_packet().await?;
 
     if pi.len() > 0 {
-        write_column_definitions_41(pi, w, client_capabilities, false).await?;
+        write_column_definitions_41(pi, w, client_capabilities, default_charset, false).await?;
     }
     if ci.len() > 0 {
-        write_column_definitions_41(ci, w, client_capabilities, false).await?;
+        write_column_definitions_41(ci, w, client_capabilities, default_charset, false).await?;
     }
     Ok(())
 }
@@ -117,6 +133,7 @@ pub(crate) async fn write_column_definitions_41<'a, I, W>(
     i: I,
     w: &mut PacketWriter<W>,
     client_capabilities: CapabilityFlags,
+    default_charset: u16,
     is_com_field_list: bool,
 ) -> io::Result<()>
 where
@@ -125,19 +142,22 @@ where
 {
     for c in i {
         let c = c.borrow();
-        use crate::myc::constants::UTF8_GENERAL_CI;
         w.write_lenenc_str(b"def")?;
         w.write_lenenc_str(b"")?;
         w.write_lenenc_str(c.table.as_bytes())?;
-        w.write_lenenc_str(b"")?;
+        w.write_lenenc_str(c.org_table.as_bytes())?;
         w.write_lenenc_str(c.column.as_bytes())?;
-        w.write_lenenc_str(b"")?;
+        w.write_lenenc_str(c.org_name.as_bytes())?;
         w.write_lenenc_int(0xC)?;
-        w.write_u16::<LittleEndian>(UTF8_GENERAL_CI)?;
-        w.write_u32::<LittleEndian>(1024)?;
+        let charset = match c.charset {
+            0 => default_charset,
+            charset => charset,
+        };
+        w.write_u16::<LittleEndian>(charset)?;
+        w.write_u32::<LittleEndian>(c.column_length_bytes_in(charset))?;
         w.write_u8(c.coltype as u8)?;
         w.write_u16::<LittleEndian>(c.colflags.bits())?;
-        w.write_all(&[0x00])?; // decimals
+        w.write_u8(c.decimals)?;
         w.write_all(&[0x00, 0x00])?; // unused
 
         if is_com_field_list {
@@ -147,7 +167,7 @@ where
     }
 
     if !client_capabilities.contains(CapabilityFlags::CLIENT_DEPRECATE_EOF) {
-        write_eof_packet(w, StatusFlags::empty()).await
+        write_eof_packet(w, StatusFlags::empty(), 0).await
     } else {
         Ok(())
     }
@@ -157,6 +177,7 @@ pub(crate) async fn column_definitions<'a, I, W>(
     i: I,
     w: &mut PacketWriter<W>,
     client_capabilities: CapabilityFlags,
+    default_charset: u16,
 ) -> io::Result<()>
 where
     I: IntoIterator<Item = &'a Column>,
@@ -166,5 +187,5 @@ where
     let i = i.into_iter();
     w.write_lenenc_int(i.len() as u64)?;
     w.end_packet().await?;
-    write_column_definitions_41(i, w, client_capabilities, false).await
+    write_column_definitions_41(i, w, client_capabilities, default_charset, false).await
 }
@@ -16,6 +16,9 @@ use std::error::Error;
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::FutureExt;
@@ -23,16 +26,28 @@ use mysql_async::prelude::*;
 use mysql_async::Opts;
 use mysql_common as myc;
 use opensrv_mysql::{
-    AsyncMysqlIntermediary, AsyncMysqlShim, Column, ErrorKind, OkResponse, ParamParser,
-    QueryResultWriter, StatementMetaWriter, U24_MAX,
+    write_stream, AsyncMysqlIntermediary, AsyncMysqlShim, AuthStep, BulkParam, Column, CommaJoined,
+    ErrorKind, FiniteOrNull, InitWriter, IntermediaryOptions, JsonArray, MysqlIntermediaryBuilder,
+    MysqlRow, Null, NullIfEmpty, ObservedCommand, OkResponse, ParamParser, ProcessRow,
+    QueryObserver, QueryResultWriter, RawBinCell, RawTextCell, SessionStateChange,
+    StatementMetaWriter, SystemVariables, ValueInner, U24_MAX,
 };
-use tokio::io::BufWriter;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 use tokio::net::tcp::OwnedWriteHalf;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
 
 struct TestingShim<Q, P, E> {
     columns: Vec<Column>,
     params: Vec<Column>,
+    schemas: Arc<Mutex<Vec<String>>>,
+    known_schemas: Option<Vec<&'static str>>,
+    password: Option<&'static str>,
+    auth_plugin: &'static str,
+    processes: Vec<ProcessRow>,
+    server_status: myc::constants::StatusFlags,
+    catalog: Option<Vec<(&'static str, Vec<&'static str>)>>,
+    refusal: Option<ErrorKind>,
+    resets: Arc<AtomicUsize>,
     on_q: Q,
     on_p: P,
     on_e: E,
@@ -62,6 +77,67 @@ where
 {
     type Error = io::Error;
 
+    fn server_status(&self) -> myc::constants::StatusFlags {
+        self.server_status
+    }
+
+    async fn on_connect(&self) -> Result<(), ErrorKind> {
+        self.refusal.map_or(Ok(()), Err)
+    }
+
+    async fn auth_plugin_for_username(&self, _user: &[u8]) -> &str {
+        self.auth_plugin
+    }
+
+    async fn authenticate(
+        &self,
+        auth_plugin: &str,
+        _username: &[u8],
+        salt: &[u8],
+        auth_data: &[u8],
+    ) -> bool {
+        match self.password {
+            None => true,
+            Some(password) if auth_plugin == "mysql_clear_password" => {
+                password.as_bytes() == auth_data
+            }
+            Some(password) => {
+                let expected = myc::scramble::scramble_native(salt, password.as_bytes());
+                expected.as_ref().map(|e| &e[..]) == Some(auth_data)
+            }
+        }
+    }
+
+    async fn on_process_list(&self) -> Vec<ProcessRow> {
+        self.processes.clone()
+    }
+
+    async fn on_show_databases(&self) -> Option<Vec<String>> {
+        let catalog = self.catalog.as_ref()?;
+        Some(catalog.iter().map(|(db, _)| db.to_string()).collect())
+    }
+
+    async fn on_show_tables(&self, schema: &str) -> Option<Vec<String>> {
+        let catalog = self.catalog.as_ref()?;
+        Some(
+            catalog
+                .iter()
+                .filter(|(db, _)| *db == schema)
+                .flat_map(|(_, tables)| tables.iter().map(|t| t.to_string()))
+                .collect(),
+        )
+    }
+
+    async fn on_kill(&mut self, id: u32) -> Result<(), ErrorKind> {
+        let before = self.processes.len();
+        self.processes.retain(|p| p.id != u64::from(id));
+        if self.processes.len() == before {
+            Err(ErrorKind::ER_NO_SUCH_THREAD)
+        } else {
+            Ok(())
+        }
+    }
+
     async fn on_prepare<'a>(
         &'a mut self,
         query: &'a str,
@@ -82,6 +158,22 @@ where
 
     async fn on_close<'a>(&'a mut self, _stmt: u32) {}
 
+    async fn on_reset_connection(&mut self) {
+        self.resets.fetch_add(1, Ordering::SeqCst);
+    }
+
+    async fn on_init<'a>(
+        &'a mut self,
+        schema: &'a str,
+        writer: InitWriter<'a, BufWriter<OwnedWriteHalf>>,
+    ) -> Result<(), Self::Error> {
+        self.schemas.lock().unwrap().push(schema.to_owned());
+        match &self.known_schemas {
+            Some(known) if !known.contains(&schema) => writer.unknown_database(schema).await,
+            _ => writer.ok().await,
+        }
+    }
+
     async fn on_query<'a>(
         &'a mut self,
         query: &'a str,
@@ -122,6 +214,15 @@ where
         TestingShim {
             columns: Vec::new(),
             params: Vec::new(),
+            schemas: Arc::default(),
+            known_schemas: None,
+            password: None,
+            auth_plugin: "mysql_native_password",
+            processes: Vec::new(),
+            server_status: myc::constants::StatusFlags::empty(),
+            catalog: None,
+            refusal: None,
+            resets: Arc::default(),
             on_q,
             on_p,
             on_e,
@@ -138,6 +239,57 @@ where
         self
     }
 
+    fn with_known_schemas(mut self, schemas: Vec<&'static str>) -> Self {
+        self.known_schemas = Some(schemas);
+        self
+    }
+
+    fn with_password(mut self, password: &'static str) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    fn with_auth_plugin(mut self, auth_plugin: &'static str) -> Self {
+        self.auth_plugin = auth_plugin;
+        self
+    }
+
+    fn with_processes(mut self, processes: Vec<ProcessRow>) -> Self {
+        self.processes = processes;
+        self
+    }
+
+    fn with_server_status(mut self, status: myc::constants::StatusFlags) -> Self {
+        self.server_status = status;
+        self
+    }
+
+    fn with_refusal(mut self, refusal: ErrorKind) -> Self {
+        self.refusal = Some(refusal);
+        self
+    }
+
+    fn with_catalog(mut self, catalog: Vec<(&'static str, Vec<&'static str>)>) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Serve a single connection with the given options, returning the port to connect to.
+    async fn serve(self, opts: IntermediaryOptions) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+
+            let (r, w) = socket.into_split();
+            let w = BufWriter::with_capacity(100 * 1024, w);
+            let _ = AsyncMysqlIntermediary::run_with_options(self, r, w, &opts).await;
+        });
+
+        port
+    }
+
     async fn test<C, F>(self, c: C)
     where
         F: Future<Output = Result<(), Box<dyn Error>>> + 'static + Send,
@@ -177,6 +329,49 @@ async fn it_connects() {
     .await;
 }
 
+#[tokio::test]
+async fn it_inits_db_on_connect() {
+    let shim = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+    let schemas = shim.schemas.clone();
+    let port = shim.serve(IntermediaryOptions::default()).await;
+
+    let db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}/foo", port)).unwrap())
+            .await
+            .unwrap();
+    assert_eq!(*schemas.lock().unwrap(), vec!["foo".to_owned()]);
+    db.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn it_verifies_native_password() {
+    for (password, ok) in [("secret", true), ("wrong", false)] {
+        let port = TestingShim::new(
+            |_, _| unreachable!(),
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .with_password("secret")
+        .serve(IntermediaryOptions::default())
+        .await;
+
+        let url = format!("mysql://root:{}@127.0.0.1:{}", password, port);
+        let res = mysql_async::Conn::new(Opts::from_url(&url).unwrap()).await;
+        match res {
+            Ok(db) if ok => db.disconnect().await.unwrap(),
+            Err(mysql_async::Error::Server(e)) if !ok => {
+                assert_eq!(e.code, ErrorKind::ER_ACCESS_DENIED_NO_PASSWORD_ERROR as u16);
+            }
+            Ok(_) => panic!("authenticated with a wrong password"),
+            Err(e) => panic!("unexpected {:?}", e),
+        }
+    }
+}
+
 #[tokio::test]
 async fn it_pings() {
     TestingShim::new(
@@ -216,6 +411,7 @@ async fn no_rows() {
                     column: "a".to_owned(),
                     coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                     colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
                 }];
                 w.start(&cols[..]).await?.finish().await
             }
@@ -232,6 +428,62 @@ async fn no_rows() {
     .await;
 }
 
+#[tokio::test]
+async fn empty_with_schema() {
+    TestingShim::new(
+        move |_, w| {
+            async move {
+                let cols = [
+                    Column {
+                        table: "t".to_owned(),
+                        column: "a".to_owned(),
+                        coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+                        colflags: myc::constants::ColumnFlags::NOT_NULL_FLAG,
+                        ..Default::default()
+                    },
+                    Column {
+                        table: "t".to_owned(),
+                        column: "b".to_owned(),
+                        coltype: myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+                        colflags: myc::constants::ColumnFlags::empty(),
+                        ..Default::default()
+                    },
+                ];
+                w.empty(&cols[..]).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let mut result = db.query_iter("SELECT * FROM t WHERE 1=0").await?;
+        let cols = result.columns().unwrap();
+        assert_eq!(cols.len(), 2);
+        assert_eq!(cols[0].name_str(), "a");
+        assert_eq!(cols[0].table_str(), "t");
+        assert_eq!(
+            cols[0].column_type(),
+            myc::constants::ColumnType::MYSQL_TYPE_SHORT
+        );
+        assert!(cols[0]
+            .flags()
+            .contains(myc::constants::ColumnFlags::NOT_NULL_FLAG));
+        assert_eq!(cols[1].name_str(), "b");
+        assert_eq!(
+            cols[1].column_type(),
+            myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING
+        );
+        let rs: Vec<mysql_async::Row> = result.collect().await?;
+        assert_eq!(rs.len(), 0);
+        drop(result);
+        let rs: Vec<mysql_async::Row> = db.query("SELECT * FROM t WHERE 1=0").await?;
+        assert_eq!(rs.len(), 0);
+        Ok(())
+    })
+    .await;
+}
+
 #[tokio::test]
 async fn no_columns() {
     TestingShim::new(
@@ -342,6 +594,7 @@ async fn empty_on_drop() {
                     column: "a".to_owned(),
                     coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                     colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
                 }];
                 let row_writer = w.start(&cols[..]).await?;
                 row_writer.finish().await
@@ -359,6 +612,58 @@ async fn empty_on_drop() {
     .await;
 }
 
+#[tokio::test]
+async fn error_after_rows() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let cols = [Column {
+                    table: String::new(),
+                    column: "a".to_owned(),
+                    coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
+                    colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
+                }];
+                let mut w = w.start(&cols).await?;
+                for i in 0..10i32 {
+                    w.write_row(std::iter::once(i)).await?;
+                }
+                // half-written rows are dropped
+                w.write_col(10i32)?;
+                w.error(ErrorKind::ER_QUERY_INTERRUPTED, "cursor failed")
+                    .await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let mut result = db.query_iter("SELECT a FROM foo").await?;
+        let mut rows = 0;
+        let err = loop {
+            match result.next().await {
+                Ok(Some(row)) => {
+                    assert_eq!(row.get::<i32, _>(0), Some(rows));
+                    rows += 1;
+                }
+                Ok(None) => panic!("expected an error after the rows"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(rows, 10);
+        match err {
+            mysql_async::Error::Server(e) => {
+                assert_eq!(e.code, ErrorKind::ER_QUERY_INTERRUPTED as u16);
+                assert_eq!(e.message, "cursor failed");
+            }
+            e => panic!("unexpected {:?}", e),
+        }
+        Ok(())
+    })
+    .await;
+}
+
 #[tokio::test]
 async fn it_queries_nulls() {
     TestingShim::new(
@@ -369,6 +674,7 @@ async fn it_queries_nulls() {
                     column: "a".to_owned(),
                     coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                     colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
                 }];
                 let mut w = w.start(cols).await?;
                 w.write_col(None::<i16>)?;
@@ -399,6 +705,7 @@ async fn it_queries() {
                     column: "a".to_owned(),
                     coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                     colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
                 }];
                 let mut w = w.start(cols).await?;
                 w.write_col(1024i16)?;
@@ -419,6 +726,119 @@ async fn it_queries() {
     .await;
 }
 
+#[tokio::test]
+async fn it_reports_multibyte_column_length() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let cols = &[Column {
+                    table: String::new(),
+                    column: "a".to_owned(),
+                    coltype: myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+                    colflags: myc::constants::ColumnFlags::empty(),
+                    charset: myc::constants::UTF8MB4_GENERAL_CI,
+                    column_length: Some(10),
+                    ..Default::default()
+                }];
+                let mut w = w.start(cols).await?;
+                w.write_col("héllo")?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SELECT a FROM foo").await?;
+        assert_eq!(rs.len(), 1);
+        let col = &rs[0].columns_ref()[0];
+        assert_eq!(col.column_length(), 40);
+        assert_eq!(col.character_set(), myc::constants::UTF8MB4_GENERAL_CI);
+        assert_eq!(rs[0].get::<String, _>(0), Some("héllo".to_owned()));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_reports_warnings() {
+    TestingShim::new(
+        |q, w| {
+            let insert = q.starts_with("INSERT");
+            async move {
+                if insert {
+                    return w
+                        .completed(OkResponse {
+                            affected_rows: 1,
+                            warnings: 2,
+                            ..Default::default()
+                        })
+                        .await;
+                }
+                let cols = &[Column {
+                    table: String::new(),
+                    column: "a".to_owned(),
+                    coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+                    colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
+                }];
+                let mut w = w.start(cols).await?;
+                w.write_col(1024i16)?;
+                w.set_warnings(3);
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<i16> = db.query("SELECT a FROM foo").await?;
+        assert_eq!(rs, vec![1024]);
+        assert_eq!(db.get_warnings(), 3);
+        db.query_drop("INSERT INTO foo VALUES (1)").await?;
+        assert_eq!(db.get_warnings(), 2);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_exposes_client_charset() {
+    TestingShim::new(
+        |_, w| {
+            let charset = w.session().charset();
+            async move {
+                let cols = &[Column {
+                    table: String::new(),
+                    column: "charset".to_owned(),
+                    coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+                    colflags: myc::constants::ColumnFlags::UNSIGNED_FLAG,
+                    ..Default::default()
+                }];
+                let mut w = w.start(cols).await?;
+                w.write_col(charset)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SELECT charset").await?;
+        assert_eq!(rs.len(), 1);
+        // the handler sees the collation the client connected with, which also is the default
+        // collation of the columns sent back
+        let charset = rs[0].get::<u16, _>(0).unwrap();
+        assert_ne!(charset, 0);
+        assert_eq!(rs[0].columns_ref()[0].character_set(), charset);
+        Ok(())
+    })
+    .await;
+}
+
 #[tokio::test]
 async fn it_queries_many_rows() {
     TestingShim::new(
@@ -430,12 +850,14 @@ async fn it_queries_many_rows() {
                         column: "a".to_owned(),
                         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                         colflags: myc::constants::ColumnFlags::empty(),
+                        ..Default::default()
                     },
                     Column {
                         table: String::new(),
                         column: "b".to_owned(),
                         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
                         colflags: myc::constants::ColumnFlags::empty(),
+                        ..Default::default()
                     },
                 ];
                 let mut w = w.start(cols).await?;
@@ -471,6 +893,7 @@ async fn it_prepares() {
         column: "a".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
     let cols2 = cols.clone();
     let params = vec![Column {
@@ -478,6 +901,7 @@ async fn it_prepares() {
         column: "c".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
 
     TestingShim::new(
@@ -527,42 +951,49 @@ async fn insert_exec() {
             column: "username".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "email".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "pw".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "created".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_DATETIME,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "session".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "rss".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "mail".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_VARCHAR,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
     ];
 
@@ -664,6 +1095,7 @@ async fn send_long() {
         column: "a".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
     let cols2 = cols.clone();
     let params = vec![Column {
@@ -671,6 +1103,7 @@ async fn send_long() {
         column: "c".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_BLOB,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
 
     TestingShim::new(
@@ -720,12 +1153,14 @@ async fn it_prepares_many() {
             column: "a".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
         Column {
             table: String::new(),
             column: "b".to_owned(),
             coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
             colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
         },
     ];
     let cols2 = cols.clone();
@@ -776,6 +1211,7 @@ async fn prepared_empty() {
         column: "a".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
     let cols2 = cols.clone();
     let params = vec![Column {
@@ -783,6 +1219,7 @@ async fn prepared_empty() {
         column: "c".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
 
     TestingShim::new(
@@ -814,6 +1251,7 @@ async fn prepared_no_params() {
         column: "a".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
     let cols2 = cols.clone();
     let params = vec![];
@@ -846,56 +1284,134 @@ async fn prepared_no_params() {
 }
 
 #[tokio::test]
-async fn prepared_nulls() {
-    let cols = vec![
-        Column {
-            table: String::new(),
-            column: "a".to_owned(),
-            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
-            colflags: myc::constants::ColumnFlags::empty(),
-        },
-        Column {
-            table: String::new(),
-            column: "b".to_owned(),
-            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
-            colflags: myc::constants::ColumnFlags::empty(),
-        },
-    ];
+async fn prepared_row_stream() {
+    let cols = vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
+        colflags: myc::constants::ColumnFlags::NOT_NULL_FLAG,
+        ..Default::default()
+    }];
     let cols2 = cols.clone();
-    let params = vec![
-        Column {
-            table: String::new(),
-            column: "c".to_owned(),
-            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
-            colflags: myc::constants::ColumnFlags::empty(),
-        },
-        Column {
-            table: String::new(),
-            column: "d".to_owned(),
-            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
-            colflags: myc::constants::ColumnFlags::empty(),
-        },
-    ];
 
     TestingShim::new(
         |_, _| unreachable!(),
         |_| 0,
-        move |_, params, w| {
+        move |_, _, w| {
             let cols = cols.clone();
             async move {
-                assert_eq!(params.len(), 2);
-                assert!(params[0].value.is_null());
-                assert!(!params[1].value.is_null());
-                assert_eq!(
-                    params[0].coltype,
-                    myc::constants::ColumnType::MYSQL_TYPE_NULL
-                );
-                // rust-mysql sends all numbers as LONGLONG :'(
-                assert_eq!(
-                    params[1].coltype,
-                    myc::constants::ColumnType::MYSQL_TYPE_LONGLONG
-                );
-                assert_eq!(Into::<i8>::into(params[1].value), 42i8);
+                let mut w = w.start(&cols).await?;
+                let rows = futures::stream::iter((0..50_000i32).map(|i| Ok(std::iter::once(i))));
+                w.write_row_stream(rows).await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        let prep = db.prep("SELECT a FROM b").await.unwrap();
+        let rs: Vec<i32> = db.exec(prep, ()).await?;
+        assert_eq!(rs.len(), 50_000);
+        assert!(rs.into_iter().eq(0..50_000));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn prepared_reports_warnings() {
+    let cols = vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+        colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
+    }];
+    let cols2 = cols.clone();
+
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(1024i16)?;
+                w.set_warnings(3);
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        let prep = db.prep("SELECT a FROM b").await.unwrap();
+        let rs: Vec<i16> = db.exec(prep, ()).await?;
+        assert_eq!(rs, vec![1024]);
+        assert_eq!(db.get_warnings(), 3);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn prepared_nulls() {
+    let cols = vec![
+        Column {
+            table: String::new(),
+            column: "a".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+        Column {
+            table: String::new(),
+            column: "b".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+    ];
+    let cols2 = cols.clone();
+    let params = vec![
+        Column {
+            table: String::new(),
+            column: "c".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+        Column {
+            table: String::new(),
+            column: "d".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+    ];
+
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        move |_, params, w| {
+            let cols = cols.clone();
+            async move {
+                assert_eq!(params.len(), 2);
+                assert!(params[0].value.is_null());
+                assert!(!params[1].value.is_null());
+                assert_eq!(
+                    params[0].coltype,
+                    myc::constants::ColumnType::MYSQL_TYPE_NULL
+                );
+                // rust-mysql sends all numbers as LONGLONG :'(
+                assert_eq!(
+                    params[1].coltype,
+                    myc::constants::ColumnType::MYSQL_TYPE_LONGLONG
+                );
+                assert_eq!(Into::<i8>::into(params[1].value), 42i8);
 
                 let mut w = w.start(&cols).await?;
                 w.write_row(vec![None::<i16>, Some(42)]).await?;
@@ -925,6 +1441,7 @@ async fn prepared_no_rows() {
         column: "a".to_owned(),
         coltype: myc::constants::ColumnType::MYSQL_TYPE_SHORT,
         colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
     }];
     let cols2 = cols.clone();
     TestingShim::new(
@@ -994,6 +1511,7 @@ async fn large_packet() {
                     column: "a".to_owned(),
                     coltype: myc::constants::ColumnType::MYSQL_TYPE_BLOB,
                     colflags: myc::constants::ColumnFlags::empty(),
+                    ..Default::default()
                 }];
                 let mut row_writer = w.start(&cols).await?;
                 let blob_col = vec![0; U24_MAX + 1];
@@ -1024,3 +1542,3367 @@ async fn large_packet() {
     })
     .await;
 }
+
+#[tokio::test]
+async fn large_varchar() {
+    // a single text column larger than one packet, so its lenenc prefix and payload are
+    // split across fragments
+    fn text() -> String {
+        (0..24 * 1024 * 1024)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect()
+    }
+
+    let cols = vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+        colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
+    }];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_row(vec![text()]).await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_row(vec![text()]).await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let row: String = db.query_first("SELECT a FROM foo").await?.unwrap();
+        assert!(row == text());
+
+        let row: String = db.exec_first("SELECT a FROM foo", ()).await?.unwrap();
+        assert!(row == text());
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn prepared_not_null_wide() {
+    let cols: Vec<_> = (0..64)
+        .map(|i| Column {
+            table: String::new(),
+            column: format!("c{}", i),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
+            colflags: myc::constants::ColumnFlags::NOT_NULL_FLAG,
+            ..Default::default()
+        })
+        .collect();
+    let cols2 = cols.clone();
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                for r in 0..10i32 {
+                    w.write_row((0..64i32).map(|c| r * 64 + c)).await?;
+                }
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        let prep = db.prep("SELECT * FROM wide").await?;
+        let rs: Vec<mysql_async::Row> = db.exec(prep, ()).await?;
+        assert_eq!(rs.len(), 10);
+        for (r, row) in rs.iter().enumerate() {
+            assert_eq!(row.len(), 64);
+            for c in 0..64 {
+                assert_eq!(row.get::<i32, _>(c), Some(r as i32 * 64 + c as i32));
+            }
+        }
+        Ok(())
+    })
+    .await;
+}
+
+async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await.unwrap();
+    let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    (header[3], payload)
+}
+
+async fn write_packet(stream: &mut TcpStream, seq: u8, payload: &[u8]) {
+    let len = (payload.len() as u32).to_le_bytes();
+    stream
+        .write_all(&[len[0], len[1], len[2], seq])
+        .await
+        .unwrap();
+    stream.write_all(payload).await.unwrap();
+}
+
+/// Log in to the server listening on `port` as a hand-rolled client that announces
+/// `capabilities`, for exercising protocol features `mysql_async` does not support.
+async fn raw_connect(port: u16, capabilities: myc::constants::CapabilityFlags) -> TcpStream {
+    use myc::constants::CapabilityFlags;
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    assert_eq!(handshake[0], 10);
+
+    let capabilities = capabilities
+        | CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH;
+    let mut response = capabilities.bits().to_le_bytes().to_vec();
+    response.extend(16_777_216u32.to_le_bytes());
+    response.push(0x21);
+    response.extend([0u8; 23]);
+    response.extend(b"root\0");
+    // empty auth response
+    response.push(0x00);
+    response.extend(b"mysql_native_password\0");
+    write_packet(&mut client, 1, &response).await;
+
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    client
+}
+
+#[tokio::test]
+async fn it_reads_query_attributes() {
+    let port = TestingShim::new(
+        |q, w| {
+            let attrs: Vec<_> = w
+                .query_attributes()
+                .iter()
+                .map(|a| match a.value.into_inner() {
+                    ValueInner::Bytes(b) => format!("{}={}", a.name, String::from_utf8_lossy(b)),
+                    v => format!("{}={:?}", a.name, v),
+                })
+                .collect();
+            let info = format!("{} {}", q, attrs.join(","));
+            async move {
+                w.completed(OkResponse {
+                    info,
+                    ..Default::default()
+                })
+                .await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        query_attributes: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = raw_connect(
+        port,
+        myc::constants::CapabilityFlags::CLIENT_QUERY_ATTRIBUTES,
+    )
+    .await;
+
+    // COM_QUERY with a single VARCHAR query attribute
+    let mut query = vec![0x03, 0x01, 0x01, 0x00, 0x01, 0xfd, 0x00, 0x0b];
+    query.extend(b"traceparent");
+    query.push(0x06);
+    query.extend(b"00-abc");
+    query.extend(b"SELECT 1");
+    write_packet(&mut client, 0, &query).await;
+
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    assert!(ok.ends_with(b"SELECT 1 traceparent=00-abc"));
+}
+
+#[tokio::test]
+async fn it_reads_execute_query_attributes() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 1,
+        |_, params, w| {
+            let attrs: Vec<_> = w
+                .query_attributes()
+                .iter()
+                .map(|a| match a.value.into_inner() {
+                    ValueInner::Bytes(b) => format!("{}={}", a.name, String::from_utf8_lossy(b)),
+                    v => format!("{}={:?}", a.name, v),
+                })
+                .collect();
+            let params: Vec<_> = params
+                .into_iter()
+                .map(|p| format!("{:?}", p.value.into_inner()))
+                .collect();
+            let info = format!("{} {}", params.join(","), attrs.join(","));
+            async move {
+                w.completed(OkResponse {
+                    info,
+                    ..Default::default()
+                })
+                .await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )])
+    .serve(IntermediaryOptions {
+        query_attributes: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = raw_connect(
+        port,
+        myc::constants::CapabilityFlags::CLIENT_QUERY_ATTRIBUTES,
+    )
+    .await;
+
+    write_packet(&mut client, 0, b"\x16SELECT a FROM t WHERE a = ?").await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    // the parameter definition and its EOF packet
+    read_packet(&mut client).await;
+    read_packet(&mut client).await;
+
+    // COM_STMT_EXECUTE of statement 1 with one parameter and one VARCHAR query attribute
+    let mut execute = vec![0x17, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00];
+    execute.extend([0x02, 0x00, 0x01, 0x08, 0x00, 0x00, 0xfd, 0x00, 0x0b]);
+    execute.extend(b"traceparent");
+    execute.extend(42i64.to_le_bytes());
+    execute.push(0x06);
+    execute.extend(b"00-abc");
+    write_packet(&mut client, 0, &execute).await;
+
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    assert!(ok.ends_with(b"Int(42) traceparent=00-abc"));
+}
+
+#[tokio::test]
+async fn it_survives_unknown_commands() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+
+    write_packet(&mut client, 0, &[0xee]).await;
+    let (_, err) = read_packet(&mut client).await;
+    assert_eq!(err[0], 0xff);
+    assert_eq!(
+        u16::from_le_bytes([err[1], err[2]]),
+        ErrorKind::ER_UNKNOWN_COM_ERROR as u16
+    );
+    assert!(err.ends_with(b"Unknown command"));
+
+    // COM_PING
+    write_packet(&mut client, 0, &[0x0e]).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+}
+
+struct PluginShim(&'static str);
+
+#[async_trait]
+impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for PluginShim {
+    type Error = io::Error;
+
+    fn default_auth_plugin(&self) -> &str {
+        self.0
+    }
+
+    async fn on_prepare<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: StatementMetaWriter<'a, W>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_execute<'a>(
+        &'a mut self,
+        _: u32,
+        _: ParamParser<'a>,
+        _: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_close<'a>(&'a mut self, _: u32) {}
+
+    async fn on_query<'a>(&'a mut self, _: &'a str, _: QueryResultWriter<'a, W>) -> io::Result<()> {
+        unreachable!()
+    }
+}
+
+#[tokio::test]
+async fn it_advertises_default_auth_plugin() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (r, w) = socket.into_split();
+        // the client hangs up after reading the handshake
+        let _ = AsyncMysqlIntermediary::run_on(PluginShim("caching_sha2_password"), r, w).await;
+    });
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+
+    assert_eq!(handshake[0], 10);
+    assert!(handshake.ends_with(b"\0caching_sha2_password\0"));
+}
+
+#[derive(opensrv_mysql_derive::MysqlRow)]
+struct User {
+    id: u64,
+    #[mysql(rename = "user_name")]
+    name: String,
+    #[mysql(column_type = "LONG", unsigned)]
+    age: u8,
+    email: Option<String>,
+}
+
+fn users() -> Vec<User> {
+    vec![
+        User {
+            id: 1,
+            name: "alice".to_owned(),
+            age: 30,
+            email: Some("alice@example.com".to_owned()),
+        },
+        User {
+            id: 2,
+            name: "bob".to_owned(),
+            age: 41,
+            email: None,
+        },
+    ]
+}
+
+fn check_users(rs: Vec<mysql_async::Row>) {
+    assert_eq!(rs.len(), 2);
+    let cols = rs[0].columns_ref();
+    assert_eq!(cols[0].name_str(), "id");
+    assert_eq!(cols[1].name_str(), "user_name");
+    assert_eq!(
+        cols[2].column_type(),
+        myc::constants::ColumnType::MYSQL_TYPE_LONG
+    );
+    assert!(cols[2]
+        .flags()
+        .contains(myc::constants::ColumnFlags::UNSIGNED_FLAG));
+    assert_eq!(cols[3].name_str(), "email");
+    assert_eq!(rs[0].get::<u64, _>(0), Some(1));
+    assert_eq!(rs[0].get::<String, _>(1), Some("alice".to_owned()));
+    assert_eq!(rs[0].get::<u32, _>(2), Some(30));
+    assert_eq!(
+        rs[0].get::<Option<String>, _>(3),
+        Some(Some("alice@example.com".to_owned()))
+    );
+    assert_eq!(rs[1].get::<u64, _>(0), Some(2));
+    assert_eq!(rs[1].get::<String, _>(1), Some("bob".to_owned()));
+    assert_eq!(rs[1].get::<u32, _>(2), Some(41));
+    assert_eq!(rs[1].get::<Option<String>, _>(3), Some(None));
+}
+
+#[tokio::test]
+async fn it_queries_derived_rows() {
+    TestingShim::new(
+        |_, w| async move { w.write_rows(users()).await }.boxed(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SELECT * FROM users").await?;
+        check_users(rs);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn prepared_derived_rows() {
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        |_, _, w| async move { w.write_rows(users()).await }.boxed(),
+    )
+    .with_params(vec![])
+    .with_columns(User::columns())
+    .test(|mut db| async move {
+        let prep = db.prep("SELECT * FROM users").await.unwrap();
+        let rs: Vec<mysql_async::Row> = db.exec(prep, ()).await?;
+        check_users(rs);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_observes_queries() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let observed = events.clone();
+    let observer = QueryObserver::new(move |e| {
+        let command = match e.command {
+            ObservedCommand::Query(q) => q.to_owned(),
+            ObservedCommand::Execute(id) => format!("execute {}", id),
+        };
+        observed
+            .lock()
+            .unwrap()
+            .push((command, e.elapsed, e.succeeded));
+    });
+
+    let port = TestingShim::new(
+        |_, w| {
+            async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                w.completed(OkResponse::default()).await
+            }
+            .boxed()
+        },
+        |_| 41,
+        |_, _, w| w.completed(OkResponse::default()).boxed(),
+    )
+    .serve(IntermediaryOptions {
+        query_observer: Some(observer),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    db.query_drop("SELECT a FROM foo").await.unwrap();
+    db.exec_drop("SELECT b FROM foo", ()).await.unwrap();
+    db.disconnect().await.unwrap();
+
+    let events = events.lock().unwrap();
+    let (_, elapsed, succeeded) = events
+        .iter()
+        .find(|(q, _, _)| q == "SELECT a FROM foo")
+        .unwrap();
+    assert!(*elapsed >= Duration::from_millis(10));
+    assert!(*succeeded);
+    assert!(events.iter().any(|(q, _, _)| q == "execute 41"));
+}
+
+async fn clear_password_connect(secure_transport: bool) -> Vec<u8> {
+    use myc::constants::CapabilityFlags;
+
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_password("secret")
+    .with_auth_plugin("mysql_clear_password")
+    .serve(IntermediaryOptions {
+        secure_transport,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    assert_eq!(handshake[0], 10);
+
+    let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH;
+    let mut response = capabilities.bits().to_le_bytes().to_vec();
+    response.extend(16_777_216u32.to_le_bytes());
+    response.push(0x21);
+    response.extend([0u8; 23]);
+    response.extend(b"root\0");
+    response.push(7);
+    response.extend(b"secret\0");
+    response.extend(b"mysql_clear_password\0");
+    write_packet(&mut client, 1, &response).await;
+
+    read_packet(&mut client).await.1
+}
+
+#[tokio::test]
+async fn it_accepts_clear_password_over_secure_transport() {
+    let ok = clear_password_connect(true).await;
+    assert_eq!(ok[0], 0x00);
+}
+
+#[tokio::test]
+async fn it_switches_a_native_password_response_to_clear_password() {
+    use myc::constants::CapabilityFlags;
+
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_password("secret")
+    .with_auth_plugin("mysql_clear_password")
+    .serve(IntermediaryOptions {
+        secure_transport: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    assert_eq!(handshake[0], 10);
+
+    let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH;
+    let mut response = capabilities.bits().to_le_bytes().to_vec();
+    response.extend(16_777_216u32.to_le_bytes());
+    response.push(0x21);
+    response.extend([0u8; 23]);
+    response.extend(b"root\0");
+    response.push(20);
+    response.extend([0x2a; 20]);
+    response.extend(b"mysql_native_password\0");
+    write_packet(&mut client, 1, &response).await;
+
+    let (seq, switch) = read_packet(&mut client).await;
+    assert_eq!(switch[0], 0xfe);
+    assert!(switch[1..].starts_with(b"mysql_clear_password\0"));
+
+    write_packet(&mut client, seq + 1, b"secret\0").await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+}
+
+#[tokio::test]
+async fn it_refuses_clear_password_over_plain_tcp() {
+    let err = clear_password_connect(false).await;
+    assert_eq!(err[0], 0xff);
+    assert_eq!(
+        u16::from_le_bytes([err[1], err[2]]),
+        ErrorKind::ER_ACCESS_DENIED_ERROR as u16
+    );
+}
+
+#[tokio::test]
+async fn it_limits_result_set_size() {
+    let cols = vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+        colflags: myc::constants::ColumnFlags::empty(),
+        ..Default::default()
+    }];
+    let port = TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let rows = if q == "SELECT small" { 5 } else { 100 };
+            async move {
+                let mut w = w.start(&cols).await?;
+                for _ in 0..rows {
+                    w.write_row(vec!["x".repeat(100)]).await?;
+                }
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        max_result_set_size: Some(1000),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let rows: Vec<String> = db.query("SELECT small").await.unwrap();
+    assert_eq!(rows.len(), 5);
+
+    match db.query::<String, _>("SELECT big").await {
+        Err(mysql_async::Error::Server(e)) => {
+            assert_eq!(e.code, ErrorKind::ER_OUTOFMEMORY as u16);
+        }
+        r => panic!("expected the resultset to fail, got {:?}", r),
+    }
+}
+
+#[tokio::test]
+async fn it_lists_and_kills_processes() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_processes(vec![ProcessRow {
+        id: 7,
+        user: "root".to_owned(),
+        host: "localhost".to_owned(),
+        db: None,
+        command: "Query".to_owned(),
+        time: 3,
+        state: None,
+        info: Some("SHOW PROCESSLIST".to_owned()),
+    }])
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+
+    // COM_PROCESS_INFO
+    write_packet(&mut client, 0, &[0x0a]).await;
+    let (_, count) = read_packet(&mut client).await;
+    assert_eq!(count, [8]);
+    for _ in 0..8 {
+        read_packet(&mut client).await;
+    }
+    let (_, eof) = read_packet(&mut client).await;
+    assert_eq!(eof[0], 0xfe);
+    let (_, row) = read_packet(&mut client).await;
+    assert_eq!(
+        row,
+        b"\x017\x04root\x09localhost\xfb\x05Query\x013\xfb\x10SHOW PROCESSLIST"
+    );
+    let (_, eof) = read_packet(&mut client).await;
+    assert_eq!(eof[0], 0xfe);
+
+    // COM_PROCESS_KILL of the listed process, then of the now unknown one
+    write_packet(&mut client, 0, &[0x0c, 7, 0, 0, 0]).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+
+    write_packet(&mut client, 0, &[0x0c, 7, 0, 0, 0]).await;
+    let (_, err) = read_packet(&mut client).await;
+    assert_eq!(err[0], 0xff);
+    assert_eq!(
+        u16::from_le_bytes([err[1], err[2]]),
+        ErrorKind::ER_NO_SUCH_THREAD as u16
+    );
+}
+
+#[tokio::test]
+async fn it_caches_prepared_statements() {
+    let prepares = Arc::new(AtomicUsize::new(0));
+    let counted = prepares.clone();
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            1
+        },
+        |_, _, w| w.completed(OkResponse::default()).boxed(),
+    )
+    .serve(IntermediaryOptions {
+        statement_cache_size: 16,
+        ..Default::default()
+    })
+    .await;
+
+    // without a client side cache, every prep is sent to the server
+    let opts = mysql_async::OptsBuilder::default()
+        .ip_or_hostname("127.0.0.1")
+        .tcp_port(port)
+        .stmt_cache_size(0);
+    let mut db = mysql_async::Conn::new(opts).await.unwrap();
+    for _ in 0..1000 {
+        let stmt = db.prep("SELECT a FROM b").await.unwrap();
+        db.exec_drop(&stmt, ()).await.unwrap();
+    }
+    assert_eq!(prepares.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn it_resets_the_statement_cache() {
+    let prepares = Arc::new(AtomicUsize::new(0));
+    let counted = prepares.clone();
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            1
+        },
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        statement_cache_size: 16,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+    let mut prepare = vec![0x16];
+    prepare.extend(b"SELECT a FROM b");
+    for _ in 0..2 {
+        write_packet(&mut client, 0, &prepare).await;
+        let (_, ok) = read_packet(&mut client).await;
+        assert_eq!(ok[..5], [0x00, 1, 0, 0, 0]);
+    }
+    assert_eq!(prepares.load(Ordering::SeqCst), 1);
+
+    // COM_RESET_CONNECTION
+    write_packet(&mut client, 0, &[0x1f]).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+
+    write_packet(&mut client, 0, &prepare).await;
+    read_packet(&mut client).await;
+    assert_eq!(prepares.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn it_executes_with_the_prepared_id() {
+    let next_id = Arc::new(AtomicUsize::new(40));
+    let executed = Arc::new(Mutex::new(Vec::new()));
+    let seen = executed.clone();
+    TestingShim::new(
+        |_, _| unreachable!(),
+        move |_| next_id.fetch_add(1, Ordering::SeqCst) as u32,
+        move |id, _, w| {
+            seen.lock().unwrap().push(id);
+            w.completed(OkResponse::default()).boxed()
+        },
+    )
+    .test(move |mut db| async move {
+        let a = db.prep("SELECT a FROM b").await?;
+        let b = db.prep("SELECT c FROM d").await?;
+        assert_eq!((a.id(), b.id()), (40, 41));
+
+        db.exec_drop(&b, ()).await?;
+        db.exec_drop(&a, ()).await?;
+        assert_eq!(*executed.lock().unwrap(), vec![41, 40]);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_rejects_widened_integers_in_strict_mode() {
+    async fn serve(strict_types: bool) -> mysql_async::Conn {
+        let cols = vec![Column {
+            table: String::new(),
+            column: "a".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+            colflags: myc::constants::ColumnFlags::UNSIGNED_FLAG,
+            ..Default::default()
+        }];
+        let cols2 = cols.clone();
+        let port = TestingShim::new(
+            |_, _| unreachable!(),
+            |_| 0,
+            move |_, _, w| {
+                let cols = cols.clone();
+                async move {
+                    let mut w = w.start(&cols).await?;
+                    w.write_col(7u8)?;
+                    w.finish().await
+                }
+                .boxed()
+            },
+        )
+        .with_params(vec![])
+        .with_columns(cols2)
+        .serve(IntermediaryOptions {
+            strict_types,
+            ..Default::default()
+        })
+        .await;
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap()
+    }
+
+    let mut lenient = serve(false).await;
+    let rows: Vec<u64> = lenient.exec("SELECT a", ()).await.unwrap();
+    assert_eq!(rows, vec![7]);
+
+    let mut strict = serve(true).await;
+    assert!(strict.exec::<u64, _, _>("SELECT a", ()).await.is_err());
+}
+
+#[tokio::test]
+async fn it_reports_primary_key_columns() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let cols = [
+                    Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG)
+                        .table("t")
+                        .primary_key()
+                        .auto_increment()
+                        .unsigned(),
+                    Column::new("name", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING)
+                        .table("t")
+                        .not_null(),
+                ];
+                w.empty(&cols[..]).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let mut result = db.query_iter("SELECT * FROM t").await?;
+        let cols = result.columns().unwrap();
+        assert_eq!(cols[0].name_str(), "id");
+        assert!(cols[0].flags().contains(
+            myc::constants::ColumnFlags::PRI_KEY_FLAG
+                | myc::constants::ColumnFlags::AUTO_INCREMENT_FLAG
+                | myc::constants::ColumnFlags::NOT_NULL_FLAG
+                | myc::constants::ColumnFlags::UNSIGNED_FLAG
+        ));
+        assert_eq!(cols[1].flags(), myc::constants::ColumnFlags::NOT_NULL_FLAG);
+        let rs: Vec<mysql_async::Row> = result.collect().await?;
+        assert!(rs.is_empty());
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_answers_system_variables() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        system_variables: Some(SystemVariables::default().with_variable("sql_mode", "ANSI")),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let comment: Option<String> = db
+        .query_first("select @@version_comment limit 1")
+        .await
+        .unwrap();
+    assert_eq!(comment.as_deref(), Some(""));
+
+    let version: Option<String> = db.query_first("SELECT @@version").await.unwrap();
+    assert_eq!(version.as_deref(), Some("5.1.10-alpha-msql-proxy"));
+
+    let vars: Vec<(String, String)> = db.query("SHOW VARIABLES LIKE 'sql_mode'").await.unwrap();
+    assert_eq!(vars, vec![("sql_mode".to_owned(), "ANSI".to_owned())]);
+
+    let packet: Option<(u64, u64)> = db
+        .query_first("SELECT @@max_allowed_packet, @@wait_timeout")
+        .await
+        .unwrap();
+    assert_eq!(packet, Some((67108864, 28800)));
+}
+
+#[tokio::test]
+async fn it_limits_system_variables_resultsets() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        system_variables: Some(SystemVariables::default().with_variable("big", &"x".repeat(2000))),
+        max_result_set_size: Some(1000),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    match db.query_first::<String, _>("SELECT @@big").await {
+        Err(mysql_async::Error::Server(e)) => {
+            assert_eq!(e.code, ErrorKind::ER_OUTOFMEMORY as u16);
+        }
+        r => panic!("expected the resultset to fail, got {:?}", r),
+    }
+}
+
+#[tokio::test]
+async fn it_sends_comma_joined_sets() {
+    let tags = vec!["a", "b"];
+    TestingShim::new(
+        move |_, w| {
+            let tags = tags.clone();
+            async move {
+                let cols = [Column::new(
+                    "tags",
+                    myc::constants::ColumnType::MYSQL_TYPE_SET,
+                )];
+                let mut w = w.start(&cols[..]).await?;
+                w.write_row(std::iter::once(CommaJoined(&tags))).await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let tags: Option<String> = db.query_first("SELECT tags FROM t").await?;
+        let tags = tags.unwrap();
+        assert_eq!(tags, "a,b");
+        // FIND_IN_SET('b', tags)
+        assert_eq!(
+            tags.split(',').position(|t| t == "b").map(|i| i + 1),
+            Some(2)
+        );
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_sends_json_arrays() {
+    let cols = vec![Column::new(
+        "ids",
+        myc::constants::ColumnType::MYSQL_TYPE_JSON,
+    )];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_row(std::iter::once(JsonArray(vec![1i64, 2, 3])))
+                    .await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_row(std::iter::once(JsonArray(vec!["a", "b"])))
+                    .await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let ids: Option<String> = db.query_first("SELECT ids FROM t").await?;
+        let ids = ids.unwrap();
+        assert_eq!(ids, "[1,2,3]");
+        // JSON_LENGTH(ids)
+        assert_eq!(ids.trim_matches(&['[', ']'][..]).split(',').count(), 3);
+
+        let names: Option<String> = db.exec_first("SELECT names FROM t", ()).await?;
+        assert_eq!(names.as_deref(), Some(r#"["a","b"]"#));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_passes_the_peer_addr() {
+    let seen = Arc::new(Mutex::new(None));
+    let seen2 = seen.clone();
+    let shim = TestingShim::new(
+        move |_, w| {
+            *seen2.lock().unwrap() = Some(w.session().peer_addr());
+            w.completed(OkResponse::default()).boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, addr) = listener.accept().await.unwrap();
+        let (r, w) = socket.into_split();
+        let w = BufWriter::with_capacity(100 * 1024, w);
+        AsyncMysqlIntermediary::run_with_peer_addr(shim, r, w, Some(addr), &Default::default())
+            .await
+            .unwrap();
+        addr
+    });
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    db.query_drop("SELECT 1").await.unwrap();
+    db.disconnect().await.unwrap();
+
+    let addr = server.await.unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(*seen.lock().unwrap(), Some(Some(addr)));
+}
+
+#[tokio::test]
+async fn it_sends_session_state_changes() {
+    let port = TestingShim::new(
+        |_, w| {
+            w.completed(OkResponse {
+                session_state_changes: vec![
+                    SessionStateChange::Schema("db".to_owned()),
+                    SessionStateChange::TransactionState("________".to_owned()),
+                ],
+                ..Default::default()
+            })
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        session_track: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    db.query_drop("SET autocommit = 0").await.unwrap();
+    db.query_drop("SELECT 1").await.unwrap();
+    db.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn it_ends_unfinished_responses() {
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONG,
+    )];
+    TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let q = q.to_owned();
+            async move {
+                match &*q {
+                    // returns before sending anything
+                    "SELECT nothing" => drop(w),
+                    // returns without finishing the resultset, mid-row
+                    "SELECT partial" => {
+                        let mut w = w.start(&cols).await?;
+                        w.write_row(std::iter::once(1i32)).await?;
+                        w.write_col(2i32)?;
+                    }
+                    _ => {
+                        let mut w = w.start(&cols).await?;
+                        w.write_row(std::iter::once(3i32)).await?;
+                        w.finish().await?;
+                    }
+                }
+                Ok(())
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        match db.query_drop("SELECT nothing").await {
+            Err(mysql_async::Error::Server(e)) => {
+                assert_eq!(e.code, ErrorKind::ER_UNKNOWN_ERROR as u16);
+            }
+            r => panic!("expected an error, got {:?}", r),
+        }
+        let rows: Vec<i32> = db.query("SELECT partial").await?;
+        assert_eq!(rows, vec![1]);
+        let rows: Vec<i32> = db.query("SELECT a").await?;
+        assert_eq!(rows, vec![3]);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_configures_tcp_options() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _client = TcpStream::connect(addr).await.unwrap();
+    let (socket, _) = listener.accept().await.unwrap();
+    assert!(!socket.nodelay().unwrap());
+
+    IntermediaryOptions::default()
+        .configure_tcp(&socket)
+        .unwrap();
+    assert!(!socket.nodelay().unwrap());
+
+    IntermediaryOptions {
+        tcp_nodelay: true,
+        tcp_keepalive: Some(Duration::from_secs(60)),
+        ..Default::default()
+    }
+    .configure_tcp(&socket)
+    .unwrap();
+    assert!(socket.nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn it_runs_on_tcp() {
+    let shim = TestingShim::new(
+        |_, w| w.completed(OkResponse::default()).boxed(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let opts = IntermediaryOptions {
+            tcp_nodelay: true,
+            ..Default::default()
+        };
+        AsyncMysqlIntermediary::run_on_tcp(shim, socket, &opts)
+            .await
+            .unwrap();
+    });
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    db.query_drop("SELECT 1").await.unwrap();
+    db.disconnect().await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn it_truncates_datetimes_sent_as_dates() {
+    let cols = vec![Column::new(
+        "d",
+        myc::constants::ColumnType::MYSQL_TYPE_DATE,
+    )];
+    let cols2 = cols.clone();
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                let dt = chrono::NaiveDate::from_ymd(2022, 8, 17).and_hms(12, 34, 56);
+                w.write_row(std::iter::once(dt)).await?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        let rows: Vec<chrono::NaiveDate> = db.exec("SELECT d", ()).await?;
+        assert_eq!(rows, vec![chrono::NaiveDate::from_ymd(2022, 8, 17)]);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_writes_untyped_nulls() {
+    let cols = vec![
+        Column::new("a", myc::constants::ColumnType::MYSQL_TYPE_SHORT),
+        Column::new("b", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(Null)?;
+                w.write_col("x")?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(42i16)?;
+                w.write_col(Null)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SELECT a, b FROM foo").await?;
+        assert_eq!(rs[0][0], mysql_async::Value::NULL);
+        assert_eq!(rs[0].get::<String, _>(1).as_deref(), Some("x"));
+
+        let rs: Vec<mysql_async::Row> = db.exec("SELECT a, b FROM foo", ()).await?;
+        assert_eq!(rs[0].get::<i16, _>(0), Some(42));
+        assert_eq!(rs[0][1], mysql_async::Value::NULL);
+        Ok(())
+    })
+    .await;
+}
+
+#[cfg(feature = "geo")]
+#[tokio::test]
+async fn it_decodes_geometry_params() {
+    let params = vec![Column::new(
+        "c",
+        myc::constants::ColumnType::MYSQL_TYPE_GEOMETRY,
+    )];
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+    )];
+    let cols2 = cols.clone();
+
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        move |_, params, w| {
+            let cols = cols.clone();
+            async move {
+                assert_eq!(params.len(), 1);
+                let (srid, geometry) =
+                    <(u32, geo_types::Geometry<f64>)>::try_from(&params[0].value)?;
+                assert_eq!(srid, 4326);
+                assert_eq!(
+                    geometry,
+                    geo_types::Geometry::Point(geo_types::point!(x: 1.5, y: -2.0))
+                );
+
+                let mut w = w.start(&cols).await?;
+                w.write_col(1i16)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(params)
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        // POINT(1.5 -2) with SRID 4326, as returned by ST_GeomFromText
+        let mut point = 4326u32.to_le_bytes().to_vec();
+        point.push(1);
+        point.extend(1u32.to_le_bytes());
+        point.extend(1.5f64.to_le_bytes());
+        point.extend((-2.0f64).to_le_bytes());
+
+        let rows: Vec<i16> = db.exec("SELECT a FROM b WHERE c = ?", (point,)).await?;
+        assert_eq!(rows, vec![1]);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_writes_whole_tables() {
+    fn table() -> Vec<Vec<myc::value::Value>> {
+        (1..=5)
+            .map(|i| {
+                vec![
+                    myc::value::Value::Int(i),
+                    myc::value::Value::Bytes(format!("row{}", i).into_bytes()),
+                    myc::value::Value::Double(i as f64 / 2.0),
+                ]
+            })
+            .collect()
+    }
+
+    let cols = vec![
+        Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG),
+        Column::new("name", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+        Column::new("score", myc::constants::ColumnType::MYSQL_TYPE_DOUBLE),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move { w.write_table(&cols, table()).await }.boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move { w.write_table(&cols, table()).await }.boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let expected: Vec<(i64, String, f64)> = (1..=5)
+            .map(|i| (i, format!("row{}", i), i as f64 / 2.0))
+            .collect();
+        let rows: Vec<(i64, String, f64)> = db.query("SELECT id, name, score FROM t").await?;
+        assert_eq!(rows, expected);
+        let rows: Vec<(i64, String, f64)> = db.exec("SELECT id, name, score FROM t", ()).await?;
+        assert_eq!(rows, expected);
+        Ok(())
+    })
+    .await;
+}
+
+#[test]
+fn it_builds_intermediary_options() {
+    let builder = MysqlIntermediaryBuilder::new()
+        .strict_types(true)
+        .max_result_set_size(1024)
+        .tcp_keepalive(Duration::from_secs(60));
+    assert_eq!(
+        builder.options(),
+        &IntermediaryOptions {
+            strict_types: true,
+            max_result_set_size: Some(1024),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        MysqlIntermediaryBuilder::new().options(),
+        &IntermediaryOptions::default()
+    );
+}
+
+#[tokio::test]
+async fn it_runs_a_built_intermediary() {
+    let shim = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        MysqlIntermediaryBuilder::new()
+            .tcp_nodelay(true)
+            .system_variables(SystemVariables::default().with_variable("sql_mode", "ANSI"))
+            .run_on_tcp(shim, socket)
+            .await
+            .unwrap();
+    });
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let mode: Option<String> = db.query_first("SELECT @@sql_mode").await.unwrap();
+    assert_eq!(mode.as_deref(), Some("ANSI"));
+    db.disconnect().await.unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn it_rejects_unknown_databases() {
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_known_schemas(vec!["foo"])
+    .test(|mut db| async move {
+        db.query_drop("USE foo").await?;
+        match db.query_drop("USE bar").await {
+            Err(mysql_async::Error::Server(e)) => {
+                assert_eq!(e.code, 1049);
+                assert_eq!(e.state, "42000");
+                assert_eq!(e.message, "Unknown database 'bar'");
+            }
+            r => panic!("expected an unknown database error, got {:?}", r),
+        }
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_tells_binary_from_text_results() {
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_SHORT,
+    )];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                assert!(!w.is_binary());
+                let mut w = w.start(&cols).await?;
+                assert!(!w.is_binary());
+                w.write_col(1i16)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                assert!(w.is_binary());
+                let mut w = w.start(&cols).await?;
+                assert!(w.is_binary());
+                w.write_col(2i16)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let text: Option<i16> = db.query_first("SELECT a").await?;
+        assert_eq!(text, Some(1));
+        let bin: Option<i16> = db.exec_first("SELECT a", ()).await?;
+        assert_eq!(bin, Some(2));
+        Ok(())
+    })
+    .await;
+}
+
+struct FixedSaltShim([u8; 20]);
+
+#[async_trait]
+impl AsyncMysqlShim<BufWriter<OwnedWriteHalf>> for FixedSaltShim {
+    type Error = io::Error;
+
+    fn salt(&self) -> io::Result<[u8; 20]> {
+        Ok(self.0)
+    }
+
+    async fn authenticate(
+        &self,
+        _auth_plugin: &str,
+        _username: &[u8],
+        salt: &[u8],
+        auth_data: &[u8],
+    ) -> bool {
+        assert_eq!(salt, self.0);
+        let expected = myc::scramble::scramble_native(salt, b"password");
+        expected.as_ref().map(|e| &e[..]) == Some(auth_data)
+    }
+
+    async fn on_prepare<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: StatementMetaWriter<'a, BufWriter<OwnedWriteHalf>>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_execute<'a>(
+        &'a mut self,
+        _: u32,
+        _: ParamParser<'a>,
+        _: QueryResultWriter<'a, BufWriter<OwnedWriteHalf>>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_close<'a>(&'a mut self, _: u32) {}
+
+    async fn on_query<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: QueryResultWriter<'a, BufWriter<OwnedWriteHalf>>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+}
+
+#[tokio::test]
+async fn it_authenticates_with_a_fixed_scramble() {
+    use myc::constants::CapabilityFlags;
+
+    let salt = *b"0123456789abcdefghij";
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        AsyncMysqlIntermediary::run_on_tcp(FixedSaltShim(salt), socket, &Default::default())
+            .await
+            .unwrap();
+    });
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (seq, handshake) = read_packet(&mut client).await;
+    assert_eq!(seq, 0);
+
+    let capabilities = (CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA
+        | CapabilityFlags::CLIENT_CONNECT_WITH_DB
+        | CapabilityFlags::CLIENT_DEPRECATE_EOF)
+        .bits()
+        .to_le_bytes();
+    let mut expected = vec![10];
+    expected.extend(b"5.1.10-alpha-msql-proxy\0");
+    expected.extend(8u32.to_le_bytes());
+    expected.extend(&salt[..8]);
+    expected.push(0);
+    expected.extend(&capabilities[..2]);
+    expected.push(0x21);
+    expected.extend([0, 0]);
+    expected.extend(&capabilities[2..]);
+    expected.push(21);
+    expected.extend([0; 10]);
+    expected.extend(&salt[8..]);
+    expected.push(0);
+    expected.extend(b"mysql_native_password\0");
+    assert_eq!(handshake, expected);
+
+    let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH;
+    let mut response = capabilities.bits().to_le_bytes().to_vec();
+    response.extend(16_777_216u32.to_le_bytes());
+    response.push(0x21);
+    response.extend([0u8; 23]);
+    response.extend(b"root\0");
+    let auth = myc::scramble::scramble_native(&salt, b"password").unwrap();
+    response.push(auth.len() as u8);
+    response.extend(auth);
+    response.extend(b"mysql_native_password\0");
+    write_packet(&mut client, 1, &response).await;
+
+    let (seq, ok) = read_packet(&mut client).await;
+    assert_eq!(seq, 2);
+    assert_eq!(ok[0], 0x00);
+
+    // COM_QUIT
+    write_packet(&mut client, 0, &[0x01]).await;
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn it_sends_random_scrambles() {
+    let mut scrambles = Vec::new();
+    for _ in 0..2 {
+        let port = TestingShim::new(
+            |_, _| unreachable!(),
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .serve(IntermediaryOptions::default())
+        .await;
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let (_, handshake) = read_packet(&mut client).await;
+        let start = 1 + b"5.1.10-alpha-msql-proxy\0".len() + 4;
+        let scramble = handshake[start..start + 8].to_vec();
+        assert!(scramble
+            .iter()
+            .all(|&b| (0x21..=0x7e).contains(&b) && b != b'$'));
+        scrambles.push(scramble);
+    }
+    assert_ne!(scrambles[0], scrambles[1]);
+}
+
+#[tokio::test]
+async fn it_sends_a_collation_per_column() {
+    // ascii_general_ci
+    const ASCII_GENERAL_CI: u16 = 11;
+    let cols = vec![
+        Column::new("name", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING)
+            .charset(myc::constants::UTF8MB4_GENERAL_CI),
+        Column::new("code", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING)
+            .charset(ASCII_GENERAL_CI),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move { w.write_table(&cols, [["héllo", "HX"]]).await }.boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move { w.write_table(&cols, [["héllo", "HX"]]).await }.boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SELECT name, code FROM t").await?;
+        let charsets: Vec<u16> = rs[0]
+            .columns_ref()
+            .iter()
+            .map(|c| c.character_set())
+            .collect();
+        assert_eq!(
+            charsets,
+            vec![myc::constants::UTF8MB4_GENERAL_CI, ASCII_GENERAL_CI]
+        );
+
+        let rs: Vec<mysql_async::Row> = db.exec("SELECT name, code FROM t", ()).await?;
+        let charsets: Vec<u16> = rs[0]
+            .columns_ref()
+            .iter()
+            .map(|c| c.character_set())
+            .collect();
+        assert_eq!(
+            charsets,
+            vec![myc::constants::UTF8MB4_GENERAL_CI, ASCII_GENERAL_CI]
+        );
+        assert_eq!(rs[0].get::<String, _>(0), Some("héllo".to_owned()));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_forwards_raw_cells() {
+    let cols = vec![
+        Column::new("a", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG),
+        Column::new("b", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                // as sent by an upstream server for the text protocol
+                let mut w = w.start(&cols).await?;
+                w.write_col(RawTextCell(b"\x0242"))?;
+                w.write_col(RawTextCell(b"\x05hello"))?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                // as sent by an upstream server for the binary protocol
+                let mut w = w.start(&cols).await?;
+                w.write_col(RawBinCell(42i64.to_le_bytes()))?;
+                w.write_col(RawBinCell(b"\x05hello"))?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let row: Option<(i64, String)> = db.query_first("SELECT a, b").await?;
+        assert_eq!(row, Some((42, "hello".to_owned())));
+        let row: Option<(i64, String)> = db.exec_first("SELECT a, b", ()).await?;
+        assert_eq!(row, Some((42, "hello".to_owned())));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_executes_statements_without_params() {
+    let cols = vec![Column::new(
+        "1",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )];
+    let cols2 = cols.clone();
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |q| {
+            assert_eq!(q, "SELECT 1");
+            1
+        },
+        move |stmt, params, w| {
+            let cols = cols.clone();
+            async move {
+                assert_eq!(stmt, 1);
+                assert!(params.is_empty());
+                let mut w = w.start(&cols).await?;
+                w.write_col(1i64)?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols2)
+    .test(|mut db| async move {
+        let stmt = db.prep("SELECT 1").await?;
+        for _ in 0..3 {
+            let one: Option<i64> = db.exec_first(stmt.clone(), ()).await?;
+            assert_eq!(one, Some(1));
+        }
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_sends_ok_info() {
+    for session_track in [false, true] {
+        let port = TestingShim::new(
+            |_, w| {
+                w.completed(OkResponse {
+                    affected_rows: 3,
+                    info: "Records: 3  Duplicates: 1  Warnings: 0".to_owned(),
+                    ..Default::default()
+                })
+                .boxed()
+            },
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .serve(IntermediaryOptions {
+            session_track,
+            ..Default::default()
+        })
+        .await;
+
+        let mut db =
+            mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+                .await
+                .unwrap();
+        db.query_drop("INSERT INTO t VALUES (1), (2), (3) ON DUPLICATE KEY UPDATE a = a")
+            .await
+            .unwrap();
+        assert_eq!(db.affected_rows(), 3);
+        assert_eq!(db.info(), "Records: 3  Duplicates: 1  Warnings: 0");
+    }
+}
+
+#[tokio::test]
+async fn it_counts_connection_metrics() {
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )];
+    let port = TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let q = q.to_owned();
+            async move {
+                let metrics = w.session().metrics().unwrap().clone();
+                let mut w = w.start(&cols).await?;
+                if q == "SELECT a" {
+                    for i in 0..5u64 {
+                        w.write_row(std::iter::once(i)).await?;
+                    }
+                } else {
+                    assert!(metrics.bytes_read() > 0);
+                    assert!(metrics.bytes_written() > 0);
+                    w.write_row(std::iter::once(metrics.rows_sent())).await?;
+                    w.write_row(std::iter::once(metrics.queries())).await?;
+                }
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        metrics: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    // the client may run queries of its own while connecting
+    let before: Vec<u64> = db.query("SELECT counters").await.unwrap();
+    let rows: Vec<u64> = db.query("SELECT a").await.unwrap();
+    assert_eq!(rows, vec![0, 1, 2, 3, 4]);
+    let after: Vec<u64> = db.query("SELECT counters").await.unwrap();
+    // the rows of the first `SELECT counters`, then those of `SELECT a`
+    assert_eq!(after[0] - before[0], 2 + 5);
+    assert_eq!(after[1] - before[1], 2);
+}
+
+#[tokio::test]
+async fn it_reports_the_server_status() {
+    use myc::constants::{CapabilityFlags, StatusFlags};
+
+    async fn serve(status: StatusFlags) -> u16 {
+        TestingShim::new(
+            |_, w| w.completed(OkResponse::default()).boxed(),
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .with_server_status(status)
+        .serve(IntermediaryOptions::default())
+        .await
+    }
+
+    for status in [
+        StatusFlags::empty(),
+        StatusFlags::SERVER_STATUS_AUTOCOMMIT,
+        StatusFlags::SERVER_STATUS_IN_TRANS_READONLY,
+    ] {
+        let mut client = TcpStream::connect(("127.0.0.1", serve(status).await))
+            .await
+            .unwrap();
+        let (_, handshake) = read_packet(&mut client).await;
+        // protocol, version, connection id, scramble part 1, filler, capabilities and charset
+        let at = 1 + b"5.1.10-alpha-msql-proxy\0".len() + 4 + 8 + 1 + 2 + 1;
+        assert_eq!(handshake[at..at + 2], status.bits().to_le_bytes());
+
+        let mut client = raw_connect(serve(status).await, CapabilityFlags::empty()).await;
+        write_packet(&mut client, 0, b"\x03SET x = 1").await;
+        let (_, ok) = read_packet(&mut client).await;
+        // header, affected rows and last insert id come first
+        assert_eq!(ok[0], 0x00);
+        assert_eq!(ok[3..5], status.bits().to_le_bytes());
+    }
+}
+
+#[tokio::test]
+async fn it_sends_original_names() {
+    let cols = vec![
+        Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG)
+            .table("x")
+            .original("a", "id"),
+        Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG)
+            .table("b")
+            .original("b", "b_id"),
+    ];
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move { w.write_table(&cols, [[1u64, 2u64]]).await }.boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db
+            .query("SELECT x.id, b.b_id AS id FROM a x JOIN b ON x.id = b.a_id")
+            .await?;
+        let names: Vec<_> = rs[0]
+            .columns_ref()
+            .iter()
+            .map(|c| {
+                (
+                    c.table_str().into_owned(),
+                    c.org_table_str().into_owned(),
+                    c.name_str().into_owned(),
+                    c.org_name_str().into_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (
+                    "x".to_owned(),
+                    "a".to_owned(),
+                    "id".to_owned(),
+                    "id".to_owned()
+                ),
+                (
+                    "b".to_owned(),
+                    "b".to_owned(),
+                    "id".to_owned(),
+                    "b_id".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(rs[0].get::<u64, _>(0), Some(1));
+        assert_eq!(rs[0].get::<u64, _>(1), Some(2));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_shuts_down_between_commands() {
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let tx = Arc::new(Mutex::new(Some(tx)));
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )];
+    let shim = TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            // shut down while the query is running
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(());
+            }
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                w.write_table(&cols, [[42u64]]).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (r, w) = socket.into_split();
+        AsyncMysqlIntermediary::run_until(shim, r, BufWriter::new(w), &Default::default(), async {
+            let _ = rx.await;
+        })
+        .await
+    });
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    // the running query completes
+    let rows: Vec<u64> = db.query("SELECT a").await.unwrap();
+    assert_eq!(rows, vec![42]);
+    // and the connection is closed instead of reading the next command
+    server.await.unwrap().unwrap();
+    assert!(db.query_drop("SELECT a").await.is_err());
+}
+
+#[tokio::test]
+async fn it_describes_statement_params() {
+    let params = vec![
+        Column::new("a", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG),
+        Column::new("b", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+    ];
+    let cols = vec![Column::new(
+        "c",
+        myc::constants::ColumnType::MYSQL_TYPE_LONG,
+    )];
+    TestingShim::new(|_, _| unreachable!(), |_| 1, |_, _, _| unreachable!())
+        .with_params(params.clone())
+        .with_columns(cols.clone())
+        .test(|mut db| async move {
+            let stmt = db.prep("SELECT c FROM t WHERE a = ? AND b = ?").await?;
+            let types: Vec<_> = stmt.params().iter().map(|p| p.column_type()).collect();
+            assert_eq!(
+                types,
+                vec![
+                    myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+                    myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+                ]
+            );
+            assert_eq!(stmt.columns().len(), 1);
+            Ok(())
+        })
+        .await;
+
+    // clients that do not support CLIENT_DEPRECATE_EOF get an EOF packet after each block
+    let port = TestingShim::new(|_, _| unreachable!(), |_| 1, |_, _, _| unreachable!())
+        .with_params(params)
+        .with_columns(cols)
+        .serve(IntermediaryOptions::default())
+        .await;
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+    write_packet(&mut client, 0, b"\x16SELECT c FROM t WHERE a = ? AND b = ?").await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    // the number of columns, then of params
+    assert_eq!(ok[5..9], [1, 0, 2, 0]);
+    let mut kinds = Vec::new();
+    for _ in 0..5 {
+        let (_, packet) = read_packet(&mut client).await;
+        kinds.push(if packet[0] == 0xfe && packet.len() < 9 {
+            "eof"
+        } else {
+            "def"
+        });
+    }
+    assert_eq!(kinds, vec!["def", "def", "eof", "def", "eof"]);
+}
+
+#[tokio::test]
+async fn it_shows_databases_and_tables() {
+    TestingShim::new(
+        |q, w| {
+            assert_eq!(q, "SHOW TABLES");
+            w.completed(OkResponse::default()).boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_catalog(vec![
+        ("shop", vec!["orders", "order_items", "users"]),
+        ("logs", vec![]),
+    ])
+    .test(|mut db| async move {
+        let rs: Vec<mysql_async::Row> = db.query("SHOW DATABASES").await?;
+        assert_eq!(rs[0].columns_ref()[0].name_str(), "Database");
+        let dbs: Vec<String> = rs.into_iter().map(|r| r.get(0).unwrap()).collect();
+        assert_eq!(dbs, vec!["shop", "logs"]);
+
+        let rs: Vec<mysql_async::Row> = db.query("SHOW TABLES FROM shop").await?;
+        assert_eq!(rs[0].columns_ref()[0].name_str(), "Tables_in_shop");
+        let tables: Vec<String> = rs.into_iter().map(|r| r.get(0).unwrap()).collect();
+        assert_eq!(tables, vec!["orders", "order_items", "users"]);
+
+        let tables: Vec<String> = db.query("SHOW TABLES IN shop LIKE 'order_'").await?;
+        assert_eq!(tables, vec!["orders"]);
+
+        // without a schema, the query is left to the handler
+        db.query_drop("SHOW TABLES").await?;
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_flushes_complete_responses() {
+    for defer_flush in [false, true] {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let rx = Arc::new(tokio::sync::Mutex::new(Some(rx)));
+        let cols = vec![Column::new(
+            "a",
+            myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+        )];
+        let port = TestingShim::new(
+            move |_, w| {
+                let cols = cols.clone();
+                let rx = rx.clone();
+                async move {
+                    w.write_table(&cols, [[1u64]]).await?;
+                    // keep working on the command after the response is complete
+                    if let Some(rx) = rx.lock().await.take() {
+                        let _ = rx.await;
+                    }
+                    Ok(())
+                }
+                .boxed()
+            },
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .serve(IntermediaryOptions {
+            defer_flush,
+            ..Default::default()
+        })
+        .await;
+
+        let mut db =
+            mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+                .await
+                .unwrap();
+        let query = db.query_first::<u64, _>("SELECT a");
+        tokio::pin!(query);
+        let early = tokio::time::timeout(Duration::from_millis(200), &mut query).await;
+        if defer_flush {
+            // the response is only sent once the handler returns
+            assert!(early.is_err());
+            tx.send(()).unwrap();
+            assert_eq!(query.await.unwrap(), Some(1));
+        } else {
+            assert_eq!(early.unwrap().unwrap(), Some(1));
+            tx.send(()).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn it_refuses_connections() {
+    let serve = || {
+        TestingShim::new(
+            |_, _| unreachable!(),
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .with_refusal(ErrorKind::ER_CON_COUNT_ERROR)
+        .serve(IntermediaryOptions::default())
+    };
+
+    let port = serve().await;
+
+    let url = format!("mysql://127.0.0.1:{}", port);
+    match mysql_async::Conn::new(Opts::from_url(&url).unwrap()).await {
+        Err(mysql_async::Error::Server(e)) => {
+            assert_eq!(e.code, ErrorKind::ER_CON_COUNT_ERROR as u16);
+            assert_eq!(e.code, 1040);
+        }
+        Ok(_) => panic!("connected despite the refusal"),
+        Err(e) => panic!("unexpected {:?}", e),
+    }
+
+    // the ERR packet takes the place of the initial handshake
+    let port = serve().await;
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap();
+    let (seq, err) = read_packet(&mut stream).await;
+    assert_eq!(seq, 0);
+    assert_eq!(err[..3], [0xff, 0x10, 0x04]);
+    assert_eq!(&err[3..], b"Too many connections");
+}
+
+#[tokio::test]
+async fn it_interrupts_slow_queries() {
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )];
+    let port = TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let q = q.to_owned();
+            async move {
+                match &q[..] {
+                    "SELECT SLEEP(60)" => {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        w.completed(OkResponse::default()).await
+                    }
+                    "SELECT a, SLEEP(60)" => {
+                        // a row is sent before the handler stalls
+                        let mut w = w.start(&cols).await?;
+                        w.write_row([1u64]).await?;
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                        w.finish().await
+                    }
+                    _ => w.write_table(&cols, [[2u64]]).await,
+                }
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        query_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    for q in ["SELECT SLEEP(60)", "SELECT a, SLEEP(60)"] {
+        match db.query_drop(q).await {
+            Err(mysql_async::Error::Server(e)) => {
+                assert_eq!(e.code, ErrorKind::ER_QUERY_INTERRUPTED as u16);
+                assert_eq!(e.code, 1317);
+            }
+            res => panic!("{} was not interrupted: {:?}", q, res),
+        }
+    }
+
+    // the connection is still usable
+    let a: Option<u64> = db.query_first("SELECT a").await.unwrap();
+    assert_eq!(a, Some(2));
+}
+
+#[tokio::test]
+async fn it_reports_io_errors() {
+    assert_eq!(
+        ErrorKind::from(&io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "upstream"
+        )),
+        ErrorKind::ER_CONNECT_TO_FOREIGN_DATA_SOURCE
+    );
+
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let err = io::Error::new(io::ErrorKind::TimedOut, "upstream timed out");
+                w.error_from_io(&err).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        match db.query_drop("SELECT 1").await {
+            Err(mysql_async::Error::Server(e)) => {
+                assert_eq!(e.code, 1317);
+                assert_eq!(e.message, "upstream timed out");
+            }
+            res => panic!("unexpected {:?}", res),
+        }
+        Ok(())
+    })
+    .await;
+}
+
+// authenticates clients that answer two challenges, sent after their response to the scramble
+struct TwoStepShim;
+
+#[async_trait]
+impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for TwoStepShim {
+    type Error = io::Error;
+
+    fn default_auth_plugin(&self) -> &str {
+        "two_step"
+    }
+
+    async fn auth_plugin_for_username(&self, _user: &[u8]) -> &str {
+        "two_step"
+    }
+
+    async fn auth_step(
+        &self,
+        _auth_plugin: &str,
+        _username: &[u8],
+        _salt: &[u8],
+        round: usize,
+        auth_data: &[u8],
+    ) -> AuthStep {
+        match (round, auth_data) {
+            (0, b"hello") => AuthStep::MoreData(b"challenge-1".to_vec()),
+            (1, b"answer-1") => AuthStep::MoreData(b"challenge-2".to_vec()),
+            (2, b"answer-2") => AuthStep::Accept,
+            _ => AuthStep::Reject,
+        }
+    }
+
+    async fn on_prepare<'a>(
+        &'a mut self,
+        _: &'a str,
+        _: StatementMetaWriter<'a, W>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_execute<'a>(
+        &'a mut self,
+        _: u32,
+        _: ParamParser<'a>,
+        _: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_close<'a>(&'a mut self, _: u32) {}
+
+    async fn on_query<'a>(&'a mut self, _: &'a str, w: QueryResultWriter<'a, W>) -> io::Result<()> {
+        w.completed(OkResponse::default()).await
+    }
+}
+
+#[tokio::test]
+async fn it_exchanges_auth_more_data() {
+    for (answers, ok) in [
+        (["answer-1", "answer-2"], true),
+        (["answer-1", "wrong"], false),
+    ] {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (r, w) = socket.into_split();
+            let _ = AsyncMysqlIntermediary::run_on(TwoStepShim, r, w).await;
+        });
+
+        let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        read_packet(&mut client).await;
+
+        let capabilities = myc::constants::CapabilityFlags::CLIENT_PROTOCOL_41
+            | myc::constants::CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | myc::constants::CapabilityFlags::CLIENT_PLUGIN_AUTH;
+        let mut response = capabilities.bits().to_le_bytes().to_vec();
+        response.extend(16_777_216u32.to_le_bytes());
+        response.push(0x21);
+        response.extend([0u8; 23]);
+        response.extend(b"root\0");
+        response.push(5);
+        response.extend(b"hello");
+        response.extend(b"two_step\0");
+        write_packet(&mut client, 1, &response).await;
+
+        let mut seq = 2;
+        for (i, answer) in answers.iter().enumerate() {
+            let (rseq, more) = read_packet(&mut client).await;
+            assert_eq!(rseq, seq);
+            assert_eq!(more[0], 0x01);
+            assert_eq!(&more[1..], format!("challenge-{}", i + 1).as_bytes());
+            write_packet(&mut client, seq + 1, answer.as_bytes()).await;
+            seq += 2;
+        }
+
+        let (rseq, reply) = read_packet(&mut client).await;
+        assert_eq!(rseq, seq);
+        if ok {
+            assert_eq!(reply[0], 0x00);
+            write_packet(&mut client, 0, b"\x03SELECT 1").await;
+            let (_, ok) = read_packet(&mut client).await;
+            assert_eq!(ok[0], 0x00);
+        } else {
+            assert_eq!(reply[0], 0xff);
+            assert_eq!(
+                u16::from_le_bytes([reply[1], reply[2]]),
+                ErrorKind::ER_ACCESS_DENIED_NO_PASSWORD_ERROR as u16
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn it_does_not_advertise_ssl() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    assert_eq!(handshake[0], 10);
+    // skip the version, the connection id and the first part of the scramble
+    let version_end = handshake.iter().position(|&b| b == 0).unwrap();
+    let rest = &handshake[version_end + 1 + 4 + 8 + 1..];
+    let capabilities = u32::from_le_bytes([rest[0], rest[1], rest[5], rest[6]]);
+    let capabilities = myc::constants::CapabilityFlags::from_bits_truncate(capabilities);
+    assert!(capabilities.contains(myc::constants::CapabilityFlags::CLIENT_PROTOCOL_41));
+    assert!(!capabilities.contains(myc::constants::CapabilityFlags::CLIENT_SSL));
+}
+
+#[tokio::test]
+async fn it_sends_non_finite_floats_as_null() {
+    let cols = vec![
+        Column::new("a", myc::constants::ColumnType::MYSQL_TYPE_DOUBLE),
+        Column::new("b", myc::constants::ColumnType::MYSQL_TYPE_DOUBLE),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let nan = q.contains("nan");
+            async move {
+                if nan {
+                    // NaN cannot be sent as is
+                    let mut w = w.start(&cols).await?;
+                    assert!(w.write_col(f64::NAN).is_err());
+                    w.write_col(1.0f64)?;
+                    w.write_col(2.0f64)?;
+                    w.finish().await
+                } else {
+                    let mut w = w.start(&cols).await?;
+                    w.write_col(FiniteOrNull(f64::INFINITY))?;
+                    w.write_col(FiniteOrNull(1.5f64))?;
+                    w.finish().await
+                }
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(FiniteOrNull(f64::NEG_INFINITY))?;
+                w.write_col(FiniteOrNull(f64::NAN))?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let row: Option<(Option<f64>, Option<f64>)> = db.query_first("SELECT a, b").await?;
+        assert_eq!(row, Some((None, Some(1.5))));
+
+        let row: Option<(f64, f64)> = db.query_first("SELECT nan").await?;
+        assert_eq!(row, Some((1.0, 2.0)));
+
+        let row: Option<(Option<f64>, Option<f64>)> = db.exec_first("SELECT a, b", ()).await?;
+        assert_eq!(row, Some((None, None)));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_refuses_tls_requests() {
+    let serve = || {
+        TestingShim::new(
+            |_, w| w.completed(OkResponse::default()).boxed(),
+            |_| unreachable!(),
+            |_, _, _| unreachable!(),
+        )
+        .serve(IntermediaryOptions::default())
+    };
+
+    // an SSL request holds only the capabilities, the max packet size, the collation and filler
+    let port = serve().await;
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    read_packet(&mut client).await;
+    let capabilities = myc::constants::CapabilityFlags::CLIENT_PROTOCOL_41
+        | myc::constants::CapabilityFlags::CLIENT_SECURE_CONNECTION
+        | myc::constants::CapabilityFlags::CLIENT_SSL;
+    let mut request = capabilities.bits().to_le_bytes().to_vec();
+    request.extend(16_777_216u32.to_le_bytes());
+    request.push(0x21);
+    request.extend([0u8; 23]);
+    write_packet(&mut client, 1, &request).await;
+    let (seq, err) = read_packet(&mut client).await;
+    assert_eq!(seq, 2);
+    assert_eq!(err[0], 0xff);
+    assert_eq!(
+        u16::from_le_bytes([err[1], err[2]]),
+        ErrorKind::ER_HANDSHAKE_ERROR as u16
+    );
+
+    // plaintext clients are served as usual
+    let port = serve().await;
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+    write_packet(&mut client, 0, b"\x03SELECT 1").await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+}
+
+#[tokio::test]
+async fn it_tracks_no_backslash_escapes() {
+    let cols = vec![Column::new(
+        "statements",
+        myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+    )];
+    let port = TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let statements = w.session().split_statements(q).len() as u64;
+            let set = q.starts_with("SET ");
+            async move {
+                if set {
+                    w.completed(OkResponse::default()).await
+                } else {
+                    w.write_table(&cols, [[statements]]).await
+                }
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let query = r"SELECT 'a\'; SELECT 'b'";
+    let n: Option<u64> = db.query_first(query).await.unwrap();
+    assert_eq!(n, Some(1));
+
+    db.query_drop("SET sql_mode = 'NO_BACKSLASH_ESCAPES'")
+        .await
+        .unwrap();
+    let n: Option<u64> = db.query_first(query).await.unwrap();
+    assert_eq!(n, Some(2));
+
+    db.query_drop("SET sql_mode = DEFAULT").await.unwrap();
+    let n: Option<u64> = db.query_first(query).await.unwrap();
+    assert_eq!(n, Some(1));
+}
+
+#[tokio::test]
+async fn it_presents_as_mariadb() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        mariadb: true,
+        system_variables: Some(SystemVariables::default()),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    // mysql_async only reads the version after the `5.5.5-` prefix of a MariaDB server
+    assert_eq!(db.server_version(), (10, 6, 0));
+    let version: Option<String> = db.query_first("SELECT @@version").await.unwrap();
+    assert_eq!(version.as_deref(), Some("5.5.5-10.6.0-MariaDB-opensrv"));
+}
+
+#[tokio::test]
+async fn it_sends_mariadb_capabilities() {
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        mariadb: true,
+        ..Default::default()
+    })
+    .await;
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    let version_end = handshake.iter().position(|&b| b == 0).unwrap();
+    assert_eq!(&handshake[1..version_end], b"5.5.5-10.6.0-MariaDB-opensrv");
+    let rest = &handshake[version_end + 1 + 4 + 8 + 1..];
+    // MariaDB servers leave CLIENT_LONG_PASSWORD (CLIENT_MYSQL to MariaDB) unset
+    let capabilities =
+        myc::constants::CapabilityFlags::from_bits_truncate(u32::from(u16::from_le_bytes([
+            rest[0], rest[1],
+        ])));
+    assert!(!capabilities.contains(myc::constants::CapabilityFlags::CLIENT_LONG_PASSWORD));
+    // the extended capabilities follow the 6 reserved bytes after the scramble length
+    assert_eq!(&rest[8..14], &[0x00; 6]);
+    assert_eq!(&rest[14..18], &0u32.to_le_bytes());
+}
+
+/// A shim that inserts the rows of bulk executions into `table`.
+struct BulkShim {
+    table: Arc<Mutex<Vec<(i64, Option<String>)>>>,
+    params: Vec<Column>,
+}
+
+impl BulkShim {
+    fn new(table: Arc<Mutex<Vec<(i64, Option<String>)>>>) -> Self {
+        BulkShim {
+            table,
+            params: vec![
+                Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG),
+                Column::new("name", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+            ],
+        }
+    }
+}
+
+#[async_trait]
+impl<W: AsyncWrite + Send + Unpin> AsyncMysqlShim<W> for BulkShim {
+    type Error = io::Error;
+
+    async fn on_prepare<'a>(
+        &'a mut self,
+        _: &'a str,
+        info: StatementMetaWriter<'a, W>,
+    ) -> io::Result<()> {
+        info.reply(1, &self.params, &[]).await
+    }
+
+    async fn on_execute<'a>(
+        &'a mut self,
+        _: u32,
+        _: ParamParser<'a>,
+        _: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        unreachable!()
+    }
+
+    async fn on_bulk_execute<'a>(
+        &'a mut self,
+        _: u32,
+        rows: Vec<Vec<BulkParam<'a>>>,
+        w: QueryResultWriter<'a, W>,
+    ) -> io::Result<()> {
+        for row in &rows {
+            let id = match &row[0] {
+                BulkParam::Value(v) => match v.value.into_inner() {
+                    ValueInner::Int(id) => id,
+                    v => panic!("bad id {:?}", v),
+                },
+                _ => panic!("no id"),
+            };
+            let name = match &row[1] {
+                BulkParam::Value(v) if v.value.is_null() => None,
+                BulkParam::Value(v) => Some(<&str>::from(v.value).to_owned()),
+                _ => panic!("no name"),
+            };
+            self.table.lock().unwrap().push((id, name));
+        }
+        w.completed(OkResponse {
+            affected_rows: rows.len() as u64,
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn on_close<'a>(&'a mut self, _: u32) {}
+
+    async fn on_query<'a>(&'a mut self, _: &'a str, _: QueryResultWriter<'a, W>) -> io::Result<()> {
+        unreachable!()
+    }
+}
+
+#[tokio::test]
+async fn it_bulk_executes() {
+    let table = Arc::default();
+    let shim = BulkShim::new(Arc::clone(&table));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = MysqlIntermediaryBuilder::new()
+            .mariadb(true)
+            .bulk_execute(true)
+            .run_on_tcp(shim, socket)
+            .await;
+    });
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+    let mut prepare = vec![0x16];
+    prepare.extend(b"INSERT INTO t VALUES (?, ?)");
+    write_packet(&mut client, 0, &prepare).await;
+    let (_, prepared) = read_packet(&mut client).await;
+    assert_eq!(prepared[0], 0x00);
+    let stmt = &prepared[1..5];
+    // two parameter definitions, then EOF
+    for _ in 0..3 {
+        read_packet(&mut client).await;
+    }
+
+    let mut bulk = vec![0xfa];
+    bulk.extend(stmt);
+    // SEND_TYPES_TO_SERVER, then a signed LONGLONG and a VAR_STRING
+    bulk.extend(128u16.to_le_bytes());
+    bulk.extend([0x08, 0x00, 0xfd, 0x00]);
+    for id in 0..1000i64 {
+        bulk.push(0x00);
+        bulk.extend(id.to_le_bytes());
+        if id % 10 == 0 {
+            bulk.push(0x01);
+        } else {
+            let name = format!("row-{}", id);
+            bulk.push(0x00);
+            bulk.push(name.len() as u8);
+            bulk.extend(name.as_bytes());
+        }
+    }
+    write_packet(&mut client, 0, &bulk).await;
+
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    // 1000 affected rows, as a length-encoded integer
+    assert_eq!(&ok[1..4], &[0xfc, 0xe8, 0x03]);
+
+    let table = table.lock().unwrap();
+    assert_eq!(table.len(), 1000);
+    assert_eq!(table[0], (0, None));
+    assert_eq!(table[999], (999, Some("row-999".to_owned())));
+}
+
+#[tokio::test]
+async fn it_advertises_bulk_execute() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let _ = MysqlIntermediaryBuilder::new()
+            .mariadb(true)
+            .bulk_execute(true)
+            .run_on_tcp(BulkShim::new(Arc::default()), socket)
+            .await;
+    });
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let (_, handshake) = read_packet(&mut client).await;
+    let version_end = handshake.iter().position(|&b| b == 0).unwrap();
+    let rest = &handshake[version_end + 1 + 4 + 8 + 1..];
+    // MARIADB_CLIENT_STMT_BULK_OPERATIONS, bit 34 of MariaDB's capabilities
+    let extended = u32::from_le_bytes([rest[14], rest[15], rest[16], rest[17]]);
+    assert_eq!(extended, 1 << 2);
+}
+
+#[tokio::test]
+async fn it_sends_empty_bytes_as_null_if_asked() {
+    let cols = vec![
+        Column::new("a", myc::constants::ColumnType::MYSQL_TYPE_BLOB),
+        Column::new("b", myc::constants::ColumnType::MYSQL_TYPE_BLOB),
+        Column::new("c", myc::constants::ColumnType::MYSQL_TYPE_BLOB),
+    ];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(Vec::<u8>::new())?;
+                w.write_col(NullIfEmpty(Vec::<u8>::new()))?;
+                w.write_col(NullIfEmpty(b"abc".to_vec()))?;
+                w.finish().await
+            }
+            .boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move {
+                let mut w = w.start(&cols).await?;
+                w.write_col(Vec::<u8>::new())?;
+                w.write_col(NullIfEmpty(Vec::<u8>::new()))?;
+                w.write_col(NullIfEmpty(b"abc".to_vec()))?;
+                w.finish().await
+            }
+            .boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let expected = Some((Some(Vec::new()), None, Some(b"abc".to_vec())));
+        type Row = (Option<Vec<u8>>, Option<Vec<u8>>, Option<Vec<u8>>);
+        let row: Option<Row> = db.query_first("SELECT a, b, c").await?;
+        assert_eq!(row, expected);
+        let row: Option<Row> = db.exec_first("SELECT a, b, c", ()).await?;
+        assert_eq!(row, expected);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_answers_zero_columns_with_ok() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let mut w = w.start(&[]).await?;
+                w.set_warnings(1);
+                w.finish_with_info("created").await
+            }
+            .boxed()
+        },
+        |_| 0,
+        |_, _, w| async move { w.start(&[]).await?.finish().await }.boxed(),
+    )
+    .with_params(vec![])
+    .test(|mut db| async move {
+        // an OK packet rather than a resultset without columns, so the connection stays in sync
+        for _ in 0..2 {
+            db.query_drop("CREATE TABLE t (a INT)").await?;
+            assert_eq!(db.info(), "created");
+            assert_eq!(db.get_warnings(), 1);
+        }
+        let rows: Vec<mysql_async::Row> = db.exec("CREATE TABLE t (a INT)", ()).await?;
+        assert!(rows.is_empty());
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_keeps_the_session_across_callbacks() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen2 = seen.clone();
+    let seen3 = seen.clone();
+    let shim = TestingShim::new(
+        move |_, w| {
+            let session = w.session();
+            seen2.lock().unwrap().push((
+                session.connection_id(),
+                session.peer_addr(),
+                session.client_capabilities(),
+            ));
+            w.completed(OkResponse::default()).boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let session = w.session();
+            seen3.lock().unwrap().push((
+                session.connection_id(),
+                session.peer_addr(),
+                session.client_capabilities(),
+            ));
+            w.completed(OkResponse::default()).boxed()
+        },
+    )
+    .with_params(vec![]);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (socket, addr) = listener.accept().await.unwrap();
+        let (r, w) = socket.into_split();
+        let w = BufWriter::with_capacity(100 * 1024, w);
+        AsyncMysqlIntermediary::run_with_peer_addr(shim, r, w, Some(addr), &Default::default())
+            .await
+            .unwrap();
+        addr
+    });
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let id = db.id();
+    db.query_drop("SELECT 1").await.unwrap();
+    db.exec_drop("SELECT 1", ()).await.unwrap();
+    db.query_drop("SELECT 2").await.unwrap();
+    db.disconnect().await.unwrap();
+
+    let addr = server.await.unwrap();
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 3);
+    for (connection_id, peer_addr, capabilities) in seen.iter() {
+        assert_eq!(*connection_id, id);
+        assert_eq!(*connection_id, 8);
+        assert_eq!(*peer_addr, Some(addr));
+        assert!(capabilities.contains(myc::constants::CapabilityFlags::CLIENT_PROTOCOL_41));
+    }
+    assert!(seen.windows(2).all(|w| w[0] == w[1]));
+}
+
+#[tokio::test]
+async fn it_describes_statement_columns_without_executing() {
+    let cols = vec![
+        Column::new("id", myc::constants::ColumnType::MYSQL_TYPE_LONGLONG)
+            .unsigned()
+            .not_null(),
+        Column::new("name", myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING),
+    ];
+    TestingShim::new(
+        |_, w| w.completed(OkResponse::default()).boxed(),
+        |_| 1,
+        // the statement is never executed
+        |_, _, _| unreachable!(),
+    )
+    .with_columns(cols)
+    .test(|mut db| async move {
+        let stmt = db.prep("SELECT id, name FROM t").await?;
+        assert!(stmt.params().is_empty());
+        let columns: Vec<_> = stmt
+            .columns()
+            .iter()
+            .map(|c| (c.name_str().into_owned(), c.column_type(), c.flags()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                (
+                    "id".to_owned(),
+                    myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+                    myc::constants::ColumnFlags::UNSIGNED_FLAG
+                        | myc::constants::ColumnFlags::NOT_NULL_FLAG
+                ),
+                (
+                    "name".to_owned(),
+                    myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+                    myc::constants::ColumnFlags::empty()
+                ),
+            ]
+        );
+        db.close(stmt).await?;
+
+        // the connection goes on as usual
+        db.query_drop("SELECT 1").await?;
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_limits_prepared_statements() {
+    let mut next_id = 0;
+    let port = TestingShim::new(
+        |_, _| unreachable!(),
+        move |_| {
+            next_id += 1;
+            next_id
+        },
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        max_prepared_statements: Some(2),
+        ..Default::default()
+    })
+    .await;
+
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}", port)).unwrap())
+            .await
+            .unwrap();
+    let a = db.prep("SELECT a").await.unwrap();
+    db.prep("SELECT b").await.unwrap();
+    match db.prep("SELECT c").await {
+        Err(mysql_async::Error::Server(e)) => {
+            assert_eq!(e.code, ErrorKind::ER_MAX_PREPARED_STMT_COUNT_REACHED as u16);
+            assert_eq!(e.code, 1461);
+            assert_eq!(
+                e.message,
+                "Can't create more than max_prepared_stmt_count statements (current value: 2)"
+            );
+        }
+        res => panic!("a third statement was prepared: {:?}", res.map(|s| s.id())),
+    }
+
+    // closing a statement makes room for another
+    db.close(a).await.unwrap();
+    db.prep("SELECT c").await.unwrap();
+    assert!(db.prep("SELECT d").await.is_err());
+}
+
+fn stream_columns() -> [Column; 2] {
+    [
+        Column {
+            table: String::new(),
+            column: "a".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+        Column {
+            table: String::new(),
+            column: "b".to_owned(),
+            coltype: myc::constants::ColumnType::MYSQL_TYPE_LONG,
+            colflags: myc::constants::ColumnFlags::empty(),
+            ..Default::default()
+        },
+    ]
+}
+
+#[tokio::test]
+async fn it_writes_a_stream_of_rows() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let cols = stream_columns();
+                let rows =
+                    futures::stream::iter((0..100i32).map(|i| Ok::<_, io::Error>(vec![i, -i])));
+                write_stream(w, &cols, rows).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let rs: Vec<(i32, i32)> = db.query("SELECT a, b FROM foo").await?;
+        assert_eq!(rs.len(), 100);
+        assert!(rs.into_iter().eq((0..100).map(|i| (i, -i))));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_ends_a_failed_stream_with_an_error() {
+    TestingShim::new(
+        |_, w| {
+            async move {
+                let cols = stream_columns();
+                let rows = futures::stream::iter((0..5i32).map(|i| {
+                    if i < 3 {
+                        Ok(vec![i, -i])
+                    } else {
+                        Err(io::Error::new(io::ErrorKind::Other, "upstream went away"))
+                    }
+                }));
+                write_stream(w, &cols, rows).await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        let mut result = db.query_iter("SELECT a, b FROM foo").await?;
+        let mut rows = 0;
+        let err = loop {
+            match result.next().await {
+                Ok(Some(row)) => {
+                    assert_eq!(row.get::<i32, _>(0), Some(rows));
+                    assert_eq!(row.get::<i32, _>(1), Some(-rows));
+                    rows += 1;
+                }
+                Ok(None) => panic!("expected an error after the rows"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(rows, 3);
+        match err {
+            mysql_async::Error::Server(e) => {
+                assert_eq!(e.code, ErrorKind::ER_UNKNOWN_ERROR as u16);
+                assert_eq!(e.message, "upstream went away");
+            }
+            e => panic!("unexpected {:?}", e),
+        }
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_reads_null_params_across_bitmap_bytes() {
+    let params: Vec<_> = (0..9)
+        .map(|i| {
+            Column::new(
+                &format!("p{}", i),
+                myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+            )
+        })
+        .collect();
+
+    TestingShim::new(
+        |_, _| unreachable!(),
+        |_| 0,
+        |_, params, w| {
+            async move {
+                let got: Vec<Option<i64>> = params
+                    .iter()
+                    .map(|p| (!p.value.is_null()).then(|| Into::<i64>::into(p.value)))
+                    .collect();
+                assert_eq!(
+                    got,
+                    vec![
+                        None,
+                        Some(2),
+                        Some(3),
+                        Some(4),
+                        Some(5),
+                        Some(6),
+                        Some(7),
+                        None,
+                        None
+                    ]
+                );
+                w.completed(OkResponse::default()).await
+            }
+            .boxed()
+        },
+    )
+    .with_params(params)
+    .test(|mut db| async move {
+        let none: Option<i64> = None;
+        db.exec_drop(
+            "INSERT INTO t VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (none, 2, 3, 4, 5, 6, 7, none, none),
+        )
+        .await?;
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_acknowledges_set_statements() {
+    let cols = vec![Column::new(
+        "mode",
+        myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+    )];
+    let port = TestingShim::new(
+        move |q, w| {
+            assert!(!q.to_ascii_uppercase().starts_with("SET "), "{}", q);
+            let cols = cols.clone();
+            let mode = w.session().sql_mode().to_owned();
+            async move { w.write_table(&cols, [[mode]]).await }.boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions {
+        acknowledge_set_statements: true,
+        ..Default::default()
+    })
+    .await;
+
+    // the statements drivers and the mysql client send as they connect
+    let opts = mysql_async::OptsBuilder::default()
+        .ip_or_hostname("127.0.0.1")
+        .tcp_port(port)
+        .init(vec![
+            "SET NAMES utf8mb4",
+            "SET autocommit=1",
+            "SET SESSION sql_mode='STRICT_TRANS_TABLES,NO_BACKSLASH_ESCAPES'",
+        ]);
+    let mut db = mysql_async::Conn::new(opts).await.unwrap();
+    let mode: Option<String> = db.query_first("SELECT @@sql_mode").await.unwrap();
+    assert_eq!(
+        mode.as_deref(),
+        Some("STRICT_TRANS_TABLES,NO_BACKSLASH_ESCAPES")
+    );
+    db.query_drop("set character_set_results = NULL")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn it_sends_unsigned_values_beyond_i64() {
+    let cols = vec![Column {
+        table: String::new(),
+        column: "a".to_owned(),
+        coltype: myc::constants::ColumnType::MYSQL_TYPE_LONGLONG,
+        colflags: myc::constants::ColumnFlags::UNSIGNED_FLAG,
+        ..Default::default()
+    }];
+    let cols2 = cols.clone();
+    let cols3 = cols.clone();
+    let rows = || {
+        [u64::MAX, i64::MAX as u64 + 1]
+            .map(|n| [myc::value::Value::UInt(n)])
+            .to_vec()
+    };
+
+    TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            async move { w.write_table(&cols, rows()).await }.boxed()
+        },
+        |_| 0,
+        move |_, _, w| {
+            let cols = cols2.clone();
+            async move { w.write_table(&cols, rows()).await }.boxed()
+        },
+    )
+    .with_params(vec![])
+    .with_columns(cols3)
+    .test(|mut db| async move {
+        let expected = vec![u64::MAX, 9223372036854775808];
+        let text: Vec<u64> = db.query("SELECT a FROM b").await?;
+        assert_eq!(text, expected);
+        let text: Vec<String> = db.query("SELECT a FROM b").await?;
+        assert_eq!(text[0], "18446744073709551615");
+        let bin: Vec<u64> = db.exec("SELECT a FROM b", ()).await?;
+        assert_eq!(bin, expected);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_denies_queries_before_any_result() {
+    let cols = vec![Column::new(
+        "a",
+        myc::constants::ColumnType::MYSQL_TYPE_LONG,
+    )];
+    TestingShim::new(
+        move |q, w| {
+            let cols = cols.clone();
+            let denied = q.contains("secrets");
+            async move {
+                if denied {
+                    w.error(
+                        ErrorKind::ER_TABLEACCESS_DENIED_ERROR,
+                        "SELECT command denied to user 'u'@'localhost' for table 'secrets'",
+                    )
+                    .await
+                } else {
+                    w.write_table(&cols, [[1i32]]).await
+                }
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .test(|mut db| async move {
+        match db.query_drop("SELECT a FROM secrets").await {
+            Err(mysql_async::Error::Server(e)) => {
+                assert_eq!(e.code, 1142);
+                assert_eq!(e.state, "42000");
+                assert_eq!(
+                    e.message,
+                    "SELECT command denied to user 'u'@'localhost' for table 'secrets'"
+                );
+            }
+            r => panic!("unexpected {:?}", r),
+        }
+        // the connection is still in sync
+        let a: Option<i32> = db.query_first("SELECT a FROM b").await?;
+        assert_eq!(a, Some(1));
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_never_requests_local_infile() {
+    let port = TestingShim::new(
+        |q, w| {
+            let local = w
+                .session()
+                .client_capabilities()
+                .contains(myc::constants::CapabilityFlags::CLIENT_LOCAL_FILES);
+            let info = format!("{} {}", q, local);
+            async move {
+                w.completed(OkResponse {
+                    info,
+                    ..Default::default()
+                })
+                .await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::CLIENT_LOCAL_FILES).await;
+    let mut query = vec![0x03];
+    query.extend(b"LOAD DATA LOCAL INFILE 'rows.csv' INTO TABLE t");
+    write_packet(&mut client, 0, &query).await;
+
+    // the query is handed to on_query, and no 0xFB file request reaches the client
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    assert!(ok.ends_with(b"INTO TABLE t false"));
+}
+
+#[tokio::test]
+async fn it_writes_tuple_rows() {
+    type Row = (u64, String, Option<i32>, f64, Vec<u8>);
+    fn rows() -> Vec<Row> {
+        vec![
+            (1, "alice".to_owned(), Some(-3), 1.5, b"a".to_vec()),
+            (2, "bob".to_owned(), None, -0.25, Vec::new()),
+        ]
+    }
+    fn check(rs: Vec<mysql_async::Row>) {
+        let cols = rs[0].columns_ref();
+        assert_eq!(cols.len(), 5);
+        assert_eq!(cols[0].name_str(), "0");
+        assert_eq!(cols[4].name_str(), "4");
+        assert!(cols[0]
+            .flags()
+            .contains(myc::constants::ColumnFlags::UNSIGNED_FLAG));
+        assert!(!cols[2]
+            .flags()
+            .contains(myc::constants::ColumnFlags::NOT_NULL_FLAG));
+        let rs: Vec<Row> = rs.into_iter().map(mysql_async::from_row).collect();
+        assert_eq!(rs, rows());
+    }
+
+    TestingShim::new(
+        |_, w| async move { w.write_rows(rows()).await }.boxed(),
+        |_| 0,
+        |_, _, w| async move { w.write_rows(rows()).await }.boxed(),
+    )
+    .with_params(vec![])
+    .with_columns(Row::columns())
+    .test(|mut db| async move {
+        check(db.query("SELECT * FROM users").await?);
+        check(db.exec("SELECT * FROM users", ()).await?);
+        Ok(())
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn it_tracks_the_current_database() {
+    let cols = vec![Column::new(
+        "DATABASE()",
+        myc::constants::ColumnType::MYSQL_TYPE_VAR_STRING,
+    )];
+    let port = TestingShim::new(
+        move |_, w| {
+            let cols = cols.clone();
+            let db = w.session().database().map(str::to_owned);
+            async move { w.write_table(&cols, [[db]]).await }.boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    )
+    .with_known_schemas(vec!["foo", "bar"])
+    .serve(IntermediaryOptions::default())
+    .await;
+
+    // named when connecting
+    let mut db =
+        mysql_async::Conn::new(Opts::from_url(&format!("mysql://127.0.0.1:{}/bar", port)).unwrap())
+            .await
+            .unwrap();
+    let current: Option<Option<String>> = db.query_first("SELECT DATABASE()").await.unwrap();
+    assert_eq!(current, Some(Some("bar".to_owned())));
+
+    db.query_drop("USE foo").await.unwrap();
+    let current: Option<Option<String>> = db.query_first("SELECT DATABASE()").await.unwrap();
+    assert_eq!(current, Some(Some("foo".to_owned())));
+
+    // a rejected database leaves the current one as it was
+    assert!(db.query_drop("USE baz").await.is_err());
+    let current: Option<Option<String>> = db.query_first("SELECT DATABASE()").await.unwrap();
+    assert_eq!(current, Some(Some("foo".to_owned())));
+}
+
+#[tokio::test]
+async fn it_resets_the_session() {
+    let shim = TestingShim::new(
+        |q, w| {
+            let info = format!(
+                "{} mode={} db={:?}",
+                q,
+                w.session().sql_mode(),
+                w.session().database()
+            );
+            async move {
+                w.completed(OkResponse {
+                    info,
+                    ..Default::default()
+                })
+                .await
+            }
+            .boxed()
+        },
+        |_| unreachable!(),
+        |_, _, _| unreachable!(),
+    );
+    let resets = shim.resets.clone();
+    let port = shim.serve(IntermediaryOptions::default()).await;
+
+    let mut client = raw_connect(port, myc::constants::CapabilityFlags::empty()).await;
+    let mut query = vec![0x03];
+    query.extend(b"SET sql_mode = 'ANSI'");
+    write_packet(&mut client, 0, &query).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+
+    // COM_INIT_DB
+    let mut init = vec![0x02];
+    init.extend(b"foo");
+    write_packet(&mut client, 0, &init).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+
+    let mut query = vec![0x03];
+    query.extend(b"SELECT 1");
+    write_packet(&mut client, 0, &query).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert!(ok.ends_with(b"SELECT 1 mode=ANSI db=Some(\"foo\")"));
+
+    // COM_RESET_CONNECTION
+    write_packet(&mut client, 0, &[0x1f]).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert_eq!(ok[0], 0x00);
+    assert_eq!(resets.load(Ordering::SeqCst), 1);
+
+    write_packet(&mut client, 0, &query).await;
+    let (_, ok) = read_packet(&mut client).await;
+    assert!(ok.ends_with(b"SELECT 1 mode= db=None"));
+}